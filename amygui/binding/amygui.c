@@ -285,6 +285,11 @@ Vector3 GuiColorPickerHSVWheel(
 
 // Draw texture 1:1 within rec
 void DrawTextureDirect(Texture texture, Rectangle rec) {
+    DrawTextureDirectTint(texture, rec, WHITE);
+}
+
+// Draw texture 1:1 within rec, multiplied by tint
+void DrawTextureDirectTint(Texture texture, Rectangle rec, Color tint) {
     float left = rec.x;
     float right = left + rec.width;
     float top = rec.y;
@@ -293,7 +298,7 @@ void DrawTextureDirect(Texture texture, Rectangle rec) {
     rlSetTexture(texture.id);
     rlBegin(RL_QUADS);
 
-        rlColor4ub(255, 255, 255, 255);
+        rlColor4ub(tint.r, tint.g, tint.b, tint.a);
         rlNormal3f(0.0, 0.0, 1.0);
 
         // Top left
@@ -315,3 +320,42 @@ void DrawTextureDirect(Texture texture, Rectangle rec) {
     rlEnd();
     rlSetTexture(0);
 }
+
+// Draw texture stretched over an arbitrary quad, corners clockwise from the top left
+void DrawTextureQuad(Texture texture, Vector2 topLeft, Vector2 topRight, Vector2 bottomRight, Vector2 bottomLeft, Color tint) {
+    Rectangle source = { 0.0f, 0.0f, (float)texture.width, (float)texture.height };
+    DrawTextureQuadRec(texture, source, topLeft, topRight, bottomRight, bottomLeft, tint);
+}
+
+// Draw part of a texture stretched over an arbitrary quad, corners clockwise from the top left of source
+void DrawTextureQuadRec(Texture texture, Rectangle source, Vector2 topLeft, Vector2 topRight, Vector2 bottomRight, Vector2 bottomLeft, Color tint) {
+    float left = source.x/texture.width;
+    float right = (source.x + source.width)/texture.width;
+    float top = source.y/texture.height;
+    float bottom = (source.y + source.height)/texture.height;
+
+    rlSetTexture(texture.id);
+    rlBegin(RL_QUADS);
+
+        rlColor4ub(tint.r, tint.g, tint.b, tint.a);
+        rlNormal3f(0.0, 0.0, 1.0);
+
+        // Top left
+        rlTexCoord2f(left, top);
+        rlVertex2f(topLeft.x, topLeft.y);
+
+        // Bottom left
+        rlTexCoord2f(left, bottom);
+        rlVertex2f(bottomLeft.x, bottomLeft.y);
+
+        // Bottom right
+        rlTexCoord2f(right, bottom);
+        rlVertex2f(bottomRight.x, bottomRight.y);
+
+        // Top right
+        rlTexCoord2f(right, top);
+        rlVertex2f(topRight.x, topRight.y);
+
+    rlEnd();
+    rlSetTexture(0);
+}
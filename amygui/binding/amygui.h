@@ -13,3 +13,12 @@ Vector3 GuiColorPickerHSVWheel(
 
 // Draw texture 1:1 within rec
 void DrawTextureDirect(Texture texture, Rectangle rec);
+
+// Draw texture 1:1 within rec, multiplied by tint
+void DrawTextureDirectTint(Texture texture, Rectangle rec, Color tint);
+
+// Draw texture stretched over an arbitrary quad, corners clockwise from the top left
+void DrawTextureQuad(Texture texture, Vector2 topLeft, Vector2 topRight, Vector2 bottomRight, Vector2 bottomLeft, Color tint);
+
+// Draw part of a texture stretched over an arbitrary quad, corners clockwise from the top left of source
+void DrawTextureQuadRec(Texture texture, Rectangle source, Vector2 topLeft, Vector2 topRight, Vector2 bottomRight, Vector2 bottomLeft, Color tint);
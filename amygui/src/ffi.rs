@@ -16,4 +16,28 @@ unsafe extern "C" {
 
     // Draw texture 1:1 within rec
     pub fn DrawTextureDirect(texture: Texture, rec: Rectangle);
+
+    // Draw texture 1:1 within rec, multiplied by tint
+    pub fn DrawTextureDirectTint(texture: Texture, rec: Rectangle, tint: Color);
+
+    // Draw texture stretched over an arbitrary quad, corners clockwise from the top left
+    pub fn DrawTextureQuad(
+        texture: Texture,
+        topLeft: Vector2,
+        topRight: Vector2,
+        bottomRight: Vector2,
+        bottomLeft: Vector2,
+        tint: Color,
+    );
+
+    // Draw part of a texture stretched over an arbitrary quad, corners clockwise from the top left of source
+    pub fn DrawTextureQuadRec(
+        texture: Texture,
+        source: Rectangle,
+        topLeft: Vector2,
+        topRight: Vector2,
+        bottomRight: Vector2,
+        bottomLeft: Vector2,
+        tint: Color,
+    );
 }
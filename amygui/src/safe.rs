@@ -35,6 +35,50 @@ pub trait RaylibDrawAmyGUI {
             crate::ffi::DrawTextureDirect(*texture.as_ref(), rec.into())
         }
     }
+
+    #[inline]
+    fn draw_texture_direct_tint(&mut self, texture: impl AsRef<ffi::Texture>, rec: Rectangle, tint: Color) {
+        unsafe {
+            crate::ffi::DrawTextureDirectTint(*texture.as_ref(), rec.into(), tint.into())
+        }
+    }
+
+    /// Draw texture stretched over an arbitrary quad, corners clockwise from the top left
+    /// NOTES:
+    /// - Unlike `draw_texture_direct`, rows are not flipped, so this is meant for regular textures
+    #[inline]
+    fn draw_texture_quad(&mut self, texture: impl AsRef<ffi::Texture>, corners: [Vector2; 4], tint: Color) {
+        let [top_left, top_right, bottom_right, bottom_left] = corners;
+        unsafe {
+            crate::ffi::DrawTextureQuad(
+                *texture.as_ref(),
+                top_left.into(),
+                top_right.into(),
+                bottom_right.into(),
+                bottom_left.into(),
+                tint.into(),
+            )
+        }
+    }
+
+    /// Draw the `source` part of a texture stretched over an arbitrary quad, corners clockwise from the top left of `source`
+    /// NOTES:
+    /// - `source` is in pixels; a negative width or height reads it mirrored, which is how to read render textures upright
+    #[inline]
+    fn draw_texture_quad_rec(&mut self, texture: impl AsRef<ffi::Texture>, source: Rectangle, corners: [Vector2; 4], tint: Color) {
+        let [top_left, top_right, bottom_right, bottom_left] = corners;
+        unsafe {
+            crate::ffi::DrawTextureQuadRec(
+                *texture.as_ref(),
+                source.into(),
+                top_left.into(),
+                top_right.into(),
+                bottom_right.into(),
+                bottom_left.into(),
+                tint.into(),
+            )
+        }
+    }
 }
 
 impl<D: RaylibDrawGui> RaylibDrawAmyGUI for D {}
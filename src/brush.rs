@@ -1,6 +1,8 @@
 use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum InterpStyle {
     Space,
     Line,
@@ -14,14 +16,6 @@ pub struct Brush {
 }
 
 impl Brush {
-    pub fn new(radius: f32, color: Color) -> Self {
-        Self {
-            radius,
-            color,
-            interp: InterpStyle::Curve,
-        }
-    }
-
     pub fn paint<D: RaylibDraw + RaylibBlendModeExt>(&self, d: &mut D, points: impl IntoIterator<Item = Vector2>, is_erasing: bool) {
         let (color, blend) = if is_erasing {
             (Color::BLANK, BlendMode::BLEND_CUSTOM_SEPARATE)
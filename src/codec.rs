@@ -0,0 +1,149 @@
+//! Reading and writing file formats that raylib can't handle on its own
+
+use std::{fs::File, io::{self, BufReader}, path::Path};
+use crate::{error::{self, Error}, raster::{Raster, blend::{self, BlendMode}}};
+
+pub mod apng;
+pub mod format;
+pub mod gif;
+pub mod ora;
+pub mod psd;
+pub mod sprite_sheet;
+pub mod tilemap;
+
+/// Widest or tallest image that's read, the same as the largest canvas the editor makes
+pub const MAX_SIZE: usize = 16384;
+
+/// Most memory the frames of one decoded animation may take, since every frame is a whole canvas
+const MAX_ANIMATION_BYTES: usize = 1 << 30;
+
+/// Refuse an image size before anything is allocated for it
+pub fn check_size(width: usize, height: usize) -> io::Result<()> {
+    if width == 0 || height == 0 || width > MAX_SIZE || height > MAX_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{width}x{height} is outside 1x1 to {MAX_SIZE}x{MAX_SIZE}")));
+    }
+    Ok(())
+}
+
+/// Refuse to decode frame number `count` if the frames so far would take too much memory
+fn check_frame_count(count: usize, width: usize, height: usize) -> io::Result<()> {
+    if count.saturating_mul(width*height*4) > MAX_ANIMATION_BYTES {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{count} frames of {width}x{height} are too many to hold")));
+    }
+    Ok(())
+}
+
+/// Equally sized frames, each paired with how long it is shown in milliseconds
+#[derive(Debug, Clone, Default)]
+pub struct Animation {
+    pub frames: Vec<(Raster, u32)>,
+    /// How many times the animation plays; 0 loops forever
+    pub loop_count: u32,
+}
+
+impl Animation {
+    /// Width and height shared by every frame
+    pub fn size(&self) -> Option<(usize, usize)> {
+        self.frames.first().map(|(raster, _)| (raster.width(), raster.height()))
+    }
+}
+
+/// One image in a [`LayerStack`]
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: String,
+    pub raster: Raster,
+    /// Where the layer's top left corner is in the image
+    pub x: i32,
+    pub y: i32,
+    /// From 0 to 1
+    pub opacity: f32,
+    pub is_visible: bool,
+    pub blend_mode: BlendMode,
+}
+
+impl Layer {
+    /// Fully opaque and visible at the top left
+    pub fn new(name: String, raster: Raster) -> Self {
+        Self {
+            name,
+            raster,
+            x: 0,
+            y: 0,
+            opacity: 1.0,
+            is_visible: true,
+            blend_mode: BlendMode::Normal,
+        }
+    }
+}
+
+/// Layers of a layered file format, bottom first
+#[derive(Debug, Clone, Default)]
+pub struct LayerStack {
+    pub width: usize,
+    pub height: usize,
+    pub layers: Vec<Layer>,
+}
+
+impl LayerStack {
+    /// Every visible layer blended together, the way the image is meant to look
+    pub fn flatten(&self) -> Raster {
+        let mut out = Raster::new(self.width, self.height);
+        for layer in self.layers.iter().filter(|layer| layer.is_visible) {
+            blend::composite(&mut out, &layer.raster, layer.x, layer.y, layer.blend_mode, layer.opacity);
+        }
+        out
+    }
+}
+
+/// File format to write an [`Animation`] as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    Gif(gif::GifOptions),
+    Apng,
+}
+
+impl AnimationFormat {
+    pub fn encode(self, writer: impl std::io::Write, animation: &Animation) -> std::io::Result<()> {
+        match self {
+            Self::Gif(options) => gif::encode(writer, animation, options),
+            Self::Apng => apng::encode(writer, animation),
+        }
+    }
+}
+
+/// What [`read_frames`] found
+pub enum FrameSource {
+    Animation(Animation),
+    /// A still image without sprite sheet metadata, which needs slicing by hand
+    Sheet(Raster),
+}
+
+/// Read an animated GIF or PNG, or a sprite sheet in any still [`format`] with a JSON file of the same name next to it
+pub fn read_frames(path: &Path) -> error::Result<FrameSource> {
+    let is_extension = |name: &str| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(name));
+    if is_extension("gif") {
+        return Ok(FrameSource::Animation(gif::decode(BufReader::new(File::open(path)?))?));
+    }
+
+    let sheet = if is_extension("png") {
+        let mut animation = apng::decode(BufReader::new(File::open(path)?))?;
+        if animation.frames.len() > 1 {
+            return Ok(FrameSource::Animation(animation));
+        }
+        let Some((sheet, _)) = animation.frames.pop() else {
+            return Err(Error::Decode("there are no frames".to_string()));
+        };
+        sheet
+    } else {
+        format::read(path)?
+    };
+    match File::open(path.with_extension("json")) {
+        Ok(meta_file) => {
+            let meta: sprite_sheet::SheetMeta = serde_json::from_reader(BufReader::new(meta_file)).map_err(io::Error::from)?;
+            Ok(FrameSource::Animation(sprite_sheet::slice_by_meta(&sheet, &meta).map_err(io::Error::from)?))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(FrameSource::Sheet(sheet)),
+        Err(err) => Err(err.into()),
+    }
+}
@@ -0,0 +1,144 @@
+use std::io::{self, Read, Write};
+use ::png::{BitDepth, BlendOp, ColorType, Decoder, DisposeOp, Encoder, Transformations};
+use crate::raster::{Raster, Rect};
+use super::{Animation, check_frame_count, check_size};
+
+/// Write every frame in full as lossless RGBA
+pub fn encode(writer: impl Write, animation: &Animation) -> io::Result<()> {
+    let Some((width, height)) = animation.size() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "animation has no frames"));
+    };
+    let mut encoder = Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    encoder.set_animated(animation.frames.len() as u32, animation.loop_count).map_err(io::Error::other)?;
+    encoder.set_dispose_op(DisposeOp::None).map_err(io::Error::other)?;
+    encoder.set_blend_op(BlendOp::Source).map_err(io::Error::other)?;
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    for (raster, duration) in &animation.frames {
+        writer.set_frame_delay((*duration).min(u16::MAX as u32) as u16, 1000).map_err(io::Error::other)?;
+        writer.write_image_data(raster.as_bytes()).map_err(io::Error::other)?;
+    }
+    writer.finish().map_err(io::Error::other)
+}
+
+/// Write a single image as a regular PNG
+pub fn encode_still(writer: impl Write, raster: &Raster) -> io::Result<()> {
+    let mut encoder = Encoder::new(writer, raster.width() as u32, raster.height() as u32);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(raster.as_bytes()).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+/// Expand 8-bit decoded rows of any color type to RGBA
+fn to_rgba(bytes: &[u8], color_type: ColorType) -> Vec<[u8; 4]> {
+    match color_type {
+        ColorType::Rgba => bytes.chunks_exact(4).map(|px| [px[0], px[1], px[2], px[3]]).collect(),
+        ColorType::Rgb => bytes.chunks_exact(3).map(|px| [px[0], px[1], px[2], 255]).collect(),
+        ColorType::GrayscaleAlpha => bytes.chunks_exact(2).map(|px| [px[0], px[0], px[0], px[1]]).collect(),
+        ColorType::Grayscale => bytes.iter().map(|&v| [v, v, v, 255]).collect(),
+        ColorType::Indexed => unreachable!("palette should be expanded by the decoder"),
+    }
+}
+
+/// Read every frame, composited the way a viewer would show it.
+/// A still PNG is read as a single frame.
+pub fn decode(reader: impl Read) -> io::Result<Animation> {
+    let mut decoder = Decoder::new(reader);
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let (width, height) = (reader.info().width as usize, reader.info().height as usize);
+    check_size(width, height)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+
+    let Some(control) = reader.info().animation_control() else {
+        let output = reader.next_frame(&mut buf).map_err(io::Error::other)?;
+        let pixels = to_rgba(&buf[..output.buffer_size()], output.color_type);
+        return Ok(Animation { frames: vec![(Raster::from_pixels(width, height, pixels), 100)], loop_count: 0 });
+    };
+    let (frame_count, loop_count) = (control.num_frames, control.num_plays);
+
+    // the default image is only a fallback for viewers without APNG support unless it has its own frame control
+    if reader.info().frame_control().is_none() {
+        reader.next_frame(&mut buf).map_err(io::Error::other)?;
+    }
+
+    let mut canvas = Raster::new(width, height);
+    let mut frames = Vec::new();
+    for i in 0..frame_count {
+        check_frame_count(i as usize + 1, width, height)?;
+        let output = reader.next_frame(&mut buf).map_err(io::Error::other)?;
+        let fc = *reader.info().frame_control().ok_or_else(|| io::Error::other("APNG frame is missing its frame control"))?;
+        let patch = Raster::from_pixels(output.width as usize, output.height as usize, to_rgba(&buf[..output.buffer_size()], output.color_type));
+        let rect = Rect::new(fc.x_offset as i32, fc.y_offset as i32, fc.width as i32, fc.height as i32);
+
+        let previous = (fc.dispose_op == DisposeOp::Previous).then(|| canvas.clone());
+        match fc.blend_op {
+            BlendOp::Source => canvas.blit(&patch, rect.x, rect.y),
+            BlendOp::Over => canvas.draw_over(&patch, rect.x, rect.y),
+        }
+        let denominator = if fc.delay_den == 0 { 100 } else { fc.delay_den as u32 };
+        frames.push((canvas.clone(), fc.delay_num as u32*1000/denominator));
+
+        match fc.dispose_op {
+            DisposeOp::None => {}
+            DisposeOp::Background => canvas.fill_rect(rect, [0; 4]),
+            // there's nothing to go back to before the first frame
+            DisposeOp::Previous if i == 0 => canvas.fill_rect(rect, [0; 4]),
+            DisposeOp::Previous => canvas = previous.unwrap(),
+        }
+    }
+
+    Ok(Animation { frames, loop_count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_delays_and_alpha_survive() {
+        let frame = |seed: u8| {
+            let pixels = (0..12).map(|i: u8| [i*20, seed, 255 - i*20, i*21]).collect();
+            Raster::from_pixels(4, 3, pixels)
+        };
+        let animation = Animation {
+            frames: vec![(frame(0), 100), (frame(90), 16), (frame(200), 1500)],
+            loop_count: 2,
+        };
+        let mut bytes = Vec::new();
+        encode(&mut bytes, &animation).unwrap();
+        let decoded = decode(bytes.as_slice()).unwrap();
+
+        assert_eq!(decoded.loop_count, 2);
+        assert_eq!(decoded.frames.len(), 3);
+        for ((raster, duration), (expected, expected_duration)) in decoded.frames.iter().zip(&animation.frames) {
+            assert_eq!(duration, expected_duration);
+            assert_eq!(raster, expected);
+        }
+    }
+
+    #[test]
+    fn still_png_is_one_frame() {
+        let raster = Raster::from_pixels(2, 1, vec![[1, 2, 3, 4], [5, 6, 7, 255]]);
+        let mut bytes = Vec::new();
+        encode_still(&mut bytes, &raster).unwrap();
+        let decoded = decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded.frames.len(), 1);
+        assert_eq!(decoded.frames[0].0, raster);
+    }
+
+    #[test]
+    fn frame_count_isnt_trusted() {
+        let mut bytes = Vec::new();
+        let mut encoder = Encoder::new(&mut bytes, 2, 2);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_animated(u32::MAX, 0).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0; 16]).unwrap();
+        drop(writer);
+        assert!(decode(bytes.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! Still image formats, opened and saved through [`Format`]
+
+use std::{fs::{self, File}, io::{BufWriter, Cursor, Write}, path::Path};
+use image::{ExtendedColorType, ImageEncoder, ImageFormat, codecs::{bmp::BmpEncoder, jpeg::JpegEncoder, pnm::{PnmEncoder, PnmSubtype, SampleEncoding}, qoi::QoiEncoder, tga::TgaEncoder}};
+use crate::{error::{self, Error}, raster::{Raster, adjust::luma, blend_over}};
+use super::{Animation, Layer, LayerStack, apng, gif::{self, GifOptions}, ora, psd};
+
+/// A choice a format offers when exporting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeOption {
+    /// [`EncodeOptions::quality`]
+    Quality,
+    /// [`EncodeOptions::is_lossless`], which makes the quality not matter
+    Lossless,
+}
+
+/// Everything a format can be asked for when exporting; each only looks at what it lists in [`Format::options`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// From 1 to 100
+    pub quality: u8,
+    pub is_lossless: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            quality: 90,
+            is_lossless: true,
+        }
+    }
+}
+
+/// A still image format. Adding one only takes implementing this and listing it in [`FORMATS`].
+pub trait Format: Sync {
+    /// What file dialogs call it
+    fn name(&self) -> &'static str;
+
+    /// Lowercase, the first being the one new files get
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Whether a file starting with `header` is in this format.
+    /// Formats without a signature are only found by their extension.
+    fn matches(&self, header: &[u8]) -> bool;
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster>;
+
+    /// Like [`Format::decode`], also saying what had to be left out of the image
+    fn decode_with_warnings(&self, bytes: &[u8]) -> error::Result<(Raster, Vec<String>)> {
+        self.decode(bytes).map(|raster| (raster, Vec::new()))
+    }
+
+    /// Formats that can only be opened leave this out, along with [`Format::is_writable`]
+    fn encode(&self, _writer: &mut dyn Write, _raster: &Raster, _options: EncodeOptions) -> error::Result<()> {
+        Err(Error::Encode(format!("{} files can only be opened", self.name())))
+    }
+
+    /// Whether it's offered when saving
+    fn is_writable(&self) -> bool {
+        true
+    }
+
+    /// What to ask about before exporting
+    fn options(&self) -> &'static [EncodeOption] {
+        &[]
+    }
+}
+
+/// Every still image format that can be opened and saved
+pub static FORMATS: &[&dyn Format] = &[&Png, &Gif, &Qoi, &Bmp, &Tga, &Jpeg, &WebP, &Ppm, &Pgm, &Pam, &OpenRaster, &Photoshop];
+
+/// The format a file with `path`'s extension is written in
+pub fn by_extension(path: &Path) -> Option<&'static dyn Format> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    FORMATS.iter().copied().find(|format| format.extensions().contains(&ext.as_str()))
+}
+
+/// The format `bytes` are in by their signature, falling back on `path`'s extension
+pub fn detect(path: &Path, bytes: &[u8]) -> Option<&'static dyn Format> {
+    FORMATS.iter().copied().find(|format| format.matches(bytes)).or_else(|| by_extension(path))
+}
+
+/// Whether `path`'s extension names a format that can be saved
+pub fn can_write(path: &Path) -> bool {
+    by_extension(path).is_some_and(|format| format.is_writable())
+}
+
+/// Read a still image in any format, or the first frame of an animated one
+pub fn read(path: &Path) -> error::Result<Raster> {
+    read_with_warnings(path).map(|(raster, _)| raster)
+}
+
+/// Like [`read`], also saying what had to be left out of the image
+pub fn read_with_warnings(path: &Path) -> error::Result<(Raster, Vec<String>)> {
+    let bytes = fs::read(path)?;
+    let format = detect(path, &bytes).ok_or_else(|| Error::Decode(format!("{} isn't in a known format", path.display())))?;
+    format.decode_with_warnings(&bytes)
+}
+
+/// Write in the format named by `path`'s extension
+pub fn write(path: &Path, raster: &Raster, options: EncodeOptions) -> error::Result<()> {
+    let format = by_extension(path).ok_or_else(|| Error::Encode(format!("{} doesn't have the extension of a known format", path.display())))?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    format.encode(&mut writer, raster, options)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn first_frame(animation: Animation) -> error::Result<Raster> {
+    animation.frames.into_iter().next()
+        .map(|(raster, _)| raster)
+        .ok_or_else(|| Error::Decode("there are no frames".to_string()))
+}
+
+/// Decode with the `image` crate, for formats without their own codec here
+fn decode_with(bytes: &[u8], format: ImageFormat) -> error::Result<Raster> {
+    let image = image::load_from_memory_with_format(bytes, format).map_err(Error::decode)?.into_rgba8();
+    Ok(Raster::from_bytes(image.width() as usize, image.height() as usize, image.as_raw()))
+}
+
+/// Encode with the `image` crate; `bytes` are `raster`'s pixels as `color`
+fn encode_with(encoder: impl ImageEncoder, bytes: &[u8], raster: &Raster, color: ExtendedColorType) -> error::Result<()> {
+    encoder.write_image(bytes, raster.width() as u32, raster.height() as u32, color).map_err(Error::encode)
+}
+
+/// RGB over white, for formats without transparency
+fn flatten(raster: &Raster) -> Vec<u8> {
+    raster.pixels().iter()
+        .flat_map(|&px| {
+            let [r, g, b, _] = blend_over([255; 4], px);
+            [r, g, b]
+        })
+        .collect()
+}
+
+struct Png;
+
+impl Format for Png {
+    fn name(&self) -> &'static str { "PNG" }
+    fn extensions(&self) -> &'static [&'static str] { &["png"] }
+    fn matches(&self, header: &[u8]) -> bool { header.starts_with(b"\x89PNG\r\n\x1a\n") }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        first_frame(apng::decode(bytes).map_err(Error::decode)?)
+    }
+
+    fn encode(&self, writer: &mut dyn Write, raster: &Raster, _options: EncodeOptions) -> error::Result<()> {
+        Ok(apng::encode_still(writer, raster)?)
+    }
+}
+
+struct Gif;
+
+impl Format for Gif {
+    fn name(&self) -> &'static str { "GIF" }
+    fn extensions(&self) -> &'static [&'static str] { &["gif"] }
+    fn matches(&self, header: &[u8]) -> bool { header.starts_with(b"GIF8") }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        first_frame(gif::decode(bytes).map_err(Error::decode)?)
+    }
+
+    fn encode(&self, writer: &mut dyn Write, raster: &Raster, _options: EncodeOptions) -> error::Result<()> {
+        // a loop count of one leaves out the looping extension, as a still image should
+        let animation = Animation { frames: vec![(raster.clone(), 0)], loop_count: 1 };
+        Ok(gif::encode(writer, &animation, GifOptions::default())?)
+    }
+}
+
+struct Qoi;
+
+impl Format for Qoi {
+    fn name(&self) -> &'static str { "QOI" }
+    fn extensions(&self) -> &'static [&'static str] { &["qoi"] }
+    fn matches(&self, header: &[u8]) -> bool { header.starts_with(b"qoif") }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        decode_with(bytes, ImageFormat::Qoi)
+    }
+
+    fn encode(&self, writer: &mut dyn Write, raster: &Raster, _options: EncodeOptions) -> error::Result<()> {
+        encode_with(QoiEncoder::new(writer), raster.as_bytes(), raster, ExtendedColorType::Rgba8)
+    }
+}
+
+struct Bmp;
+
+impl Format for Bmp {
+    fn name(&self) -> &'static str { "BMP" }
+    fn extensions(&self) -> &'static [&'static str] { &["bmp"] }
+    fn matches(&self, header: &[u8]) -> bool { header.starts_with(b"BM") }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        decode_with(bytes, ImageFormat::Bmp)
+    }
+
+    fn encode(&self, mut writer: &mut dyn Write, raster: &Raster, _options: EncodeOptions) -> error::Result<()> {
+        encode_with(BmpEncoder::new(&mut writer), raster.as_bytes(), raster, ExtendedColorType::Rgba8)
+    }
+}
+
+struct Tga;
+
+impl Format for Tga {
+    fn name(&self) -> &'static str { "TGA" }
+    fn extensions(&self) -> &'static [&'static str] { &["tga"] }
+    // there's no signature at the start of a TGA
+    fn matches(&self, _header: &[u8]) -> bool { false }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        decode_with(bytes, ImageFormat::Tga)
+    }
+
+    fn encode(&self, writer: &mut dyn Write, raster: &Raster, _options: EncodeOptions) -> error::Result<()> {
+        encode_with(TgaEncoder::new(writer), raster.as_bytes(), raster, ExtendedColorType::Rgba8)
+    }
+}
+
+struct Jpeg;
+
+impl Format for Jpeg {
+    fn name(&self) -> &'static str { "JPEG" }
+    fn extensions(&self) -> &'static [&'static str] { &["jpg", "jpeg"] }
+    fn matches(&self, header: &[u8]) -> bool { header.starts_with(&[0xFF, 0xD8, 0xFF]) }
+    fn options(&self) -> &'static [EncodeOption] { &[EncodeOption::Quality] }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        decode_with(bytes, ImageFormat::Jpeg)
+    }
+
+    fn encode(&self, writer: &mut dyn Write, raster: &Raster, options: EncodeOptions) -> error::Result<()> {
+        let encoder = JpegEncoder::new_with_quality(writer, options.quality.clamp(1, 100));
+        encode_with(encoder, &flatten(raster), raster, ExtendedColorType::Rgb8)
+    }
+}
+
+struct WebP;
+
+impl Format for WebP {
+    fn name(&self) -> &'static str { "WebP" }
+    fn extensions(&self) -> &'static [&'static str] { &["webp"] }
+    fn matches(&self, header: &[u8]) -> bool { header.starts_with(b"RIFF") && header.get(8..12) == Some(&b"WEBP"[..]) }
+    fn options(&self) -> &'static [EncodeOption] { &[EncodeOption::Lossless, EncodeOption::Quality] }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        decode_with(bytes, ImageFormat::WebP)
+    }
+
+    // lossy encoding needs libwebp, which the `image` crate doesn't have
+    fn encode(&self, writer: &mut dyn Write, raster: &Raster, options: EncodeOptions) -> error::Result<()> {
+        /// Longest side libwebp can encode
+        const MAX_SIZE: usize = 16383;
+        if raster.width() > MAX_SIZE || raster.height() > MAX_SIZE {
+            return Err(Error::Encode(format!("WebP is limited to {MAX_SIZE}x{MAX_SIZE}")));
+        }
+        let encoder = webp::Encoder::from_rgba(raster.as_bytes(), raster.width() as u32, raster.height() as u32);
+        // when lossless, the quality is how hard to try, and libwebp's default is a good trade
+        let quality = if options.is_lossless { 75.0 } else { options.quality.clamp(1, 100) as f32 };
+        let memory = encoder.encode_simple(options.is_lossless, quality)
+            .map_err(|err| Error::Encode(format!("libwebp failed with {err:?}")))?;
+        Ok(writer.write_all(&memory)?)
+    }
+}
+
+struct Ppm;
+
+impl Format for Ppm {
+    fn name(&self) -> &'static str { "PPM" }
+    fn extensions(&self) -> &'static [&'static str] { &["ppm"] }
+    fn matches(&self, header: &[u8]) -> bool { header.starts_with(b"P6") || header.starts_with(b"P3") }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        decode_with(bytes, ImageFormat::Pnm)
+    }
+
+    fn encode(&self, writer: &mut dyn Write, raster: &Raster, _options: EncodeOptions) -> error::Result<()> {
+        let encoder = PnmEncoder::new(writer).with_subtype(PnmSubtype::Pixmap(SampleEncoding::Binary));
+        encode_with(encoder, &flatten(raster), raster, ExtendedColorType::Rgb8)
+    }
+}
+
+struct Pgm;
+
+impl Format for Pgm {
+    fn name(&self) -> &'static str { "PGM" }
+    fn extensions(&self) -> &'static [&'static str] { &["pgm"] }
+    fn matches(&self, header: &[u8]) -> bool { header.starts_with(b"P5") || header.starts_with(b"P2") }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        decode_with(bytes, ImageFormat::Pnm)
+    }
+
+    fn encode(&self, writer: &mut dyn Write, raster: &Raster, _options: EncodeOptions) -> error::Result<()> {
+        let gray: Vec<u8> = flatten(raster).chunks_exact(3).map(|px| luma(px[0], px[1], px[2])).collect();
+        let encoder = PnmEncoder::new(writer).with_subtype(PnmSubtype::Graymap(SampleEncoding::Binary));
+        encode_with(encoder, &gray, raster, ExtendedColorType::L8)
+    }
+}
+
+/// The PNM that keeps transparency
+struct Pam;
+
+impl Format for Pam {
+    fn name(&self) -> &'static str { "PAM" }
+    fn extensions(&self) -> &'static [&'static str] { &["pam"] }
+    fn matches(&self, header: &[u8]) -> bool { header.starts_with(b"P7") }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        decode_with(bytes, ImageFormat::Pnm)
+    }
+
+    fn encode(&self, writer: &mut dyn Write, raster: &Raster, _options: EncodeOptions) -> error::Result<()> {
+        let encoder = PnmEncoder::new(writer).with_subtype(PnmSubtype::ArbitraryMap);
+        encode_with(encoder, raster.as_bytes(), raster, ExtendedColorType::Rgba8)
+    }
+}
+
+/// Layered, for trading work with Krita, GIMP and MyPaint.
+/// Opened flattened, and saved with the canvas as its one layer.
+struct OpenRaster;
+
+impl Format for OpenRaster {
+    fn name(&self) -> &'static str { "OpenRaster" }
+    fn extensions(&self) -> &'static [&'static str] { &["ora"] }
+    fn matches(&self, header: &[u8]) -> bool { ora::matches(header) }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        Ok(ora::decode(Cursor::new(bytes)).map_err(Error::decode)?.flatten())
+    }
+
+    fn encode(&self, writer: &mut dyn Write, raster: &Raster, _options: EncodeOptions) -> error::Result<()> {
+        let stack = LayerStack {
+            width: raster.width(),
+            height: raster.height(),
+            layers: vec![Layer::new("Background".to_string(), raster.clone())],
+        };
+        // the zip's directory is written at the end and points back into the file, which needs seeking
+        let mut buf = Cursor::new(Vec::new());
+        ora::encode(&mut buf, &stack)?;
+        Ok(writer.write_all(buf.get_ref())?)
+    }
+}
+
+/// Read only, as it was last shown in Photoshop, or its layers flattened if it wasn't saved that way
+struct Photoshop;
+
+impl Format for Photoshop {
+    fn name(&self) -> &'static str { "Photoshop" }
+    fn extensions(&self) -> &'static [&'static str] { &["psd", "psb"] }
+    fn matches(&self, header: &[u8]) -> bool { psd::matches(header) }
+    fn is_writable(&self) -> bool { false }
+
+    fn decode(&self, bytes: &[u8]) -> error::Result<Raster> {
+        self.decode_with_warnings(bytes).map(|(raster, _)| raster)
+    }
+
+    fn decode_with_warnings(&self, bytes: &[u8]) -> error::Result<(Raster, Vec<String>)> {
+        Ok(psd::decode(bytes).map_err(Error::decode)?.into_image())
+    }
+}
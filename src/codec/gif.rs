@@ -0,0 +1,173 @@
+use std::{borrow::Cow, io::{self, Read, Write}};
+use ::gif::{ColorOutput, DecodeOptions, DisposalMethod, Encoder, Repeat};
+use crate::raster::{Raster, Rect, quantize::{self, ALPHA_THRESHOLD}};
+use super::{Animation, check_frame_count, check_size};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GifOptions {
+    /// Floyd-Steinberg dither when the frames have more colors than fit in the palette
+    pub is_dithered: bool,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            is_dithered: true,
+        }
+    }
+}
+
+/// Write every frame with one shared palette. Pixels below [`ALPHA_THRESHOLD`] become transparent,
+/// and durations are rounded to the nearest hundredth of a second.
+pub fn encode(writer: impl Write, animation: &Animation, options: GifOptions) -> io::Result<()> {
+    let Some((width, height)) = animation.size() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "animation has no frames"));
+    };
+    let (Ok(gif_width), Ok(gif_height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "GIF is limited to 65535x65535"));
+    };
+
+    let pixels = || animation.frames.iter().flat_map(|(raster, _)| raster.pixels());
+    let has_transparency = pixels().any(|px| px[3] < ALPHA_THRESHOLD);
+    let opaque = pixels().filter(|px| px[3] >= ALPHA_THRESHOLD).map(|&[r, g, b, _]| [r, g, b]);
+    let mut palette = quantize::median_cut(opaque, if has_transparency { 255 } else { 256 });
+    if palette.is_empty() {
+        palette.push([0; 3]);
+    }
+    let transparent = has_transparency.then_some(palette.len() as u8);
+    let mut color_table = palette.as_flattened().to_vec();
+    if has_transparency {
+        color_table.extend([0; 3]);
+    }
+
+    let mut encoder = Encoder::new(writer, gif_width, gif_height, &color_table).map_err(io::Error::other)?;
+    // a loop count of one means no looping extension at all
+    match animation.loop_count {
+        0 => encoder.set_repeat(Repeat::Infinite).map_err(io::Error::other)?,
+        1 => {}
+        n => encoder.set_repeat(Repeat::Finite((n - 1).min(u16::MAX as u32) as u16)).map_err(io::Error::other)?,
+    }
+
+    for (raster, duration) in &animation.frames {
+        let frame = ::gif::Frame {
+            width: gif_width,
+            height: gif_height,
+            buffer: Cow::Owned(quantize::remap(raster, &palette, options.is_dithered)),
+            delay: ((duration + 5)/10).clamp(1, u16::MAX as u32) as u16,
+            transparent,
+            // clear to transparent so pixels that become transparent in the next frame don't linger
+            dispose: DisposalMethod::Background,
+            ..Default::default()
+        };
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+/// Read every frame, composited the way a viewer would show it
+pub fn decode(reader: impl Read) -> io::Result<Animation> {
+    let mut options = DecodeOptions::new();
+    options.set_color_output(ColorOutput::RGBA);
+    let mut decoder = options.read_info(reader).map_err(io::Error::other)?;
+    let (width, height) = (decoder.width() as usize, decoder.height() as usize);
+    check_size(width, height)?;
+    let mut canvas = Raster::new(width, height);
+    let mut frames = Vec::new();
+
+    while let Some(frame) = decoder.read_next_frame().map_err(io::Error::other)? {
+        check_frame_count(frames.len() + 1, width, height)?;
+        let rect = Rect::new(frame.left as i32, frame.top as i32, frame.width as i32, frame.height as i32);
+        let previous = (frame.dispose == DisposalMethod::Previous).then(|| canvas.clone());
+        let patch = Raster::from_bytes(frame.width as usize, frame.height as usize, &frame.buffer);
+        // GIF pixels are either opaque or fully transparent, so this leaves the transparent ones showing what was there
+        canvas.draw_over(&patch, rect.x, rect.y);
+        // browsers treat a zero delay as a tenth of a second
+        let duration = if frame.delay == 0 { 100 } else { frame.delay as u32*10 };
+        frames.push((canvas.clone(), duration));
+
+        match frame.dispose {
+            DisposalMethod::Background => canvas.fill_rect(rect, [0; 4]),
+            DisposalMethod::Previous => canvas = previous.unwrap(),
+            DisposalMethod::Any | DisposalMethod::Keep => {}
+        }
+    }
+
+    let loop_count = match decoder.repeat() {
+        Repeat::Infinite => 0,
+        Repeat::Finite(n) => n as u32 + 1,
+    };
+    Ok(Animation { frames, loop_count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const CLEAR: [u8; 4] = [0; 4];
+
+    fn round_trip(animation: &Animation) -> Animation {
+        let mut bytes = Vec::new();
+        encode(&mut bytes, animation, GifOptions { is_dithered: false }).unwrap();
+        decode(bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn frames_delays_and_transparency_survive() {
+        let animation = Animation {
+            frames: vec![
+                (Raster::from_pixels(2, 2, vec![RED, CLEAR, CLEAR, BLUE]), 100),
+                (Raster::from_pixels(2, 2, vec![CLEAR, BLUE, RED, CLEAR]), 250),
+                (Raster::from_pixels(2, 2, vec![BLUE, BLUE, RED, RED]), 40),
+            ],
+            loop_count: 3,
+        };
+        let decoded = round_trip(&animation);
+        assert_eq!(decoded.loop_count, 3);
+        assert_eq!(decoded.frames.len(), 3);
+        for ((raster, duration), (expected, expected_duration)) in decoded.frames.iter().zip(&animation.frames) {
+            assert_eq!(duration, expected_duration);
+            assert_eq!(raster, expected);
+        }
+    }
+
+    #[test]
+    fn durations_round_to_hundredths() {
+        let animation = Animation { frames: vec![(Raster::filled(1, 1, RED), 33), (Raster::filled(1, 1, BLUE), 1)], loop_count: 0 };
+        let decoded = round_trip(&animation);
+        assert_eq!(decoded.loop_count, 0);
+        let durations: Vec<u32> = decoded.frames.iter().map(|&(_, duration)| duration).collect();
+        assert_eq!(durations, [30, 10]);
+    }
+
+    #[test]
+    fn opaque_frames_use_the_whole_palette() {
+        // 256 colors across two frames, which only fits because none are transparent
+        let frame = |offset: usize| {
+            let pixels = (offset..offset + 128).map(|i| [i as u8, (i*7) as u8, 255 - i as u8, 255]).collect();
+            Raster::from_pixels(16, 8, pixels)
+        };
+        let animation = Animation { frames: vec![(frame(0), 100), (frame(128), 100)], loop_count: 0 };
+        let decoded = round_trip(&animation);
+        assert_eq!(decoded.frames.len(), 2);
+        for ((raster, _), (expected, _)) in decoded.frames.iter().zip(&animation.frames) {
+            assert_eq!(raster, expected);
+        }
+
+        // more than fit still encodes, with every pixel staying opaque
+        let many = Raster::from_pixels(20, 20, (0..400).map(|i| [i as u8, (i/2) as u8, (i*3) as u8, 255]).collect());
+        let decoded = round_trip(&Animation { frames: vec![(many, 100)], loop_count: 0 });
+        assert!(decoded.frames[0].0.pixels().iter().all(|px| px[3] == 255));
+    }
+
+    #[test]
+    fn oversized_screen_is_refused() {
+        let mut bytes = Vec::new();
+        encode(&mut bytes, &Animation { frames: vec![(Raster::filled(1, 1, RED), 100)], loop_count: 0 }, GifOptions::default()).unwrap();
+        // the logical screen size follows the signature
+        bytes[6..10].fill(0xff);
+        let err = decode(bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
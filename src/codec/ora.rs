@@ -0,0 +1,254 @@
+use std::{borrow::Cow, io::{self, Read, Seek, Write}};
+use quick_xml::{Reader, Writer, events::{BytesDecl, BytesEnd, BytesStart, Event}};
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+use crate::raster::{Raster, blend::BlendMode, resample::{self, Filter}};
+use super::{Layer, LayerStack, apng, check_size};
+
+const MIMETYPE: &[u8] = b"image/openraster";
+/// Longest side of the thumbnail other programs show in their file browsers
+const THUMBNAIL_SIZE: usize = 256;
+
+/// Whether a file starting with `header` is OpenRaster, going by the uncompressed mimetype every one starts with
+pub fn matches(header: &[u8]) -> bool {
+    header.starts_with(b"PK\x03\x04") && header.get(30..38) == Some(&b"mimetype"[..]) && header.get(38..54) == Some(MIMETYPE)
+}
+
+/// The `composite-op` attribute for a blend mode
+const fn composite_op(mode: BlendMode) -> &'static str {
+    match mode {
+        BlendMode::Normal => "svg:src-over",
+        BlendMode::Multiply => "svg:multiply",
+        BlendMode::Screen => "svg:screen",
+        BlendMode::Overlay => "svg:overlay",
+        BlendMode::Darken => "svg:darken",
+        BlendMode::Lighten => "svg:lighten",
+        BlendMode::ColorDodge => "svg:color-dodge",
+        BlendMode::ColorBurn => "svg:color-burn",
+        BlendMode::HardLight => "svg:hard-light",
+        BlendMode::SoftLight => "svg:soft-light",
+        BlendMode::Difference => "svg:difference",
+        BlendMode::Exclusion => "svg:exclusion",
+        BlendMode::Add => "svg:plus",
+    }
+}
+
+/// The blend mode for a `composite-op` attribute; those without one, like the hue modes, blend normally
+fn blend_mode(composite_op: &str) -> BlendMode {
+    match composite_op {
+        "svg:multiply" => BlendMode::Multiply,
+        "svg:screen" => BlendMode::Screen,
+        "svg:overlay" => BlendMode::Overlay,
+        "svg:darken" => BlendMode::Darken,
+        "svg:lighten" => BlendMode::Lighten,
+        "svg:color-dodge" => BlendMode::ColorDodge,
+        "svg:color-burn" => BlendMode::ColorBurn,
+        "svg:hard-light" => BlendMode::HardLight,
+        "svg:soft-light" => BlendMode::SoftLight,
+        "svg:difference" => BlendMode::Difference,
+        "svg:exclusion" => BlendMode::Exclusion,
+        "svg:plus" => BlendMode::Add,
+        _ => BlendMode::Normal,
+    }
+}
+
+/// Write every layer as its own PNG, along with the flattened image and a thumbnail
+pub fn encode(writer: impl Write + Seek, stack: &LayerStack) -> io::Result<()> {
+    let mut zip = ZipWriter::new(writer);
+    // PNGs are compressed already
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    // first and uncompressed, so the file can be recognised by its first bytes
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(MIMETYPE)?;
+
+    zip.start_file("stack.xml", SimpleFileOptions::default().compression_method(CompressionMethod::Deflated))?;
+    write_stack(&mut zip, stack)?;
+    for (i, layer) in stack.layers.iter().enumerate() {
+        zip.start_file(format!("data/layer{i}.png"), stored)?;
+        apng::encode_still(&mut zip, &layer.raster)?;
+    }
+
+    let merged = stack.flatten();
+    let scale = (THUMBNAIL_SIZE as f32/merged.width().max(merged.height()).max(1) as f32).min(1.0);
+    let thumbnail_size = |side: usize| ((side as f32*scale).round() as usize).max(1);
+    let thumbnail = resample::resize(&merged, thumbnail_size(merged.width()), thumbnail_size(merged.height()), Filter::Bilinear);
+    zip.start_file("mergedimage.png", stored)?;
+    apng::encode_still(&mut zip, &merged)?;
+    zip.start_file("Thumbnails/thumbnail.png", stored)?;
+    apng::encode_still(&mut zip, &thumbnail)?;
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn write_stack(writer: impl Write, stack: &LayerStack) -> io::Result<()> {
+    let mut xml = Writer::new_with_indent(writer, b' ', 2);
+    xml.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    let (width, height) = (stack.width.to_string(), stack.height.to_string());
+    xml.write_event(Event::Start(BytesStart::new("image").with_attributes([("version", "0.0.6"), ("w", width.as_str()), ("h", height.as_str())])))?;
+    xml.write_event(Event::Start(BytesStart::new("stack")))?;
+    // the first layer listed is the one on top
+    for (i, layer) in stack.layers.iter().enumerate().rev() {
+        let (src, x, y, opacity) = (format!("data/layer{i}.png"), layer.x.to_string(), layer.y.to_string(), format!("{:.3}", layer.opacity));
+        let visibility = if layer.is_visible { "visible" } else { "hidden" };
+        xml.write_event(Event::Empty(BytesStart::new("layer").with_attributes([
+            ("name", layer.name.as_str()),
+            ("src", src.as_str()),
+            ("x", x.as_str()),
+            ("y", y.as_str()),
+            ("opacity", opacity.as_str()),
+            ("visibility", visibility),
+            ("composite-op", composite_op(layer.blend_mode)),
+        ])))?;
+    }
+    xml.write_event(Event::End(BytesEnd::new("stack")))?;
+    xml.write_event(Event::End(BytesEnd::new("image")))?;
+    Ok(())
+}
+
+/// A `<layer>` as listed in `stack.xml`, before its PNG is read
+struct LayerEntry {
+    src: String,
+    layer: Layer,
+}
+
+/// Read every layer, bottom first. Layers in nested stacks are brought up to the top level,
+/// taking on the opacity and visibility of the stacks they were in.
+pub fn decode(reader: impl Read + Seek) -> io::Result<LayerStack> {
+    let mut zip = ZipArchive::new(reader)?;
+    let mut xml = String::new();
+    zip.by_name("stack.xml")?.read_to_string(&mut xml)?;
+    let (width, height, entries) = read_stack(&xml)?;
+
+    let mut layers = Vec::with_capacity(entries.len());
+    for LayerEntry { src, mut layer } in entries.into_iter().rev() {
+        let animation = apng::decode(io::BufReader::new(zip.by_name(&src)?))?;
+        let Some((raster, _)) = animation.frames.into_iter().next() else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{src} has no image")));
+        };
+        layer.raster = raster;
+        layers.push(layer);
+    }
+    Ok(LayerStack { width, height, layers })
+}
+
+/// The image's size and its layers, top first
+fn read_stack(xml: &str) -> io::Result<(usize, usize, Vec<LayerEntry>)> {
+    let mut reader = Reader::from_str(xml);
+    let mut size = None;
+    let mut entries = Vec::new();
+    // opacity and visibility of each stack the next layer is in, innermost last
+    let mut stacks: Vec<(f32, bool)> = Vec::new();
+    loop {
+        let (tag, is_empty) = match reader.read_event().map_err(io::Error::other)? {
+            Event::Start(tag) => (tag, false),
+            Event::Empty(tag) => (tag, true),
+            Event::End(tag) => {
+                if tag.name().as_ref() == b"stack" {
+                    stacks.pop();
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        let (parent_opacity, is_parent_visible) = stacks.last().copied().unwrap_or((1.0, true));
+        let opacity = parse_attribute(&tag, "opacity")?.unwrap_or(1.0_f32);
+        // "NaN" and "inf" parse, but no clamping makes them an opacity
+        if !opacity.is_finite() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("opacity=\"{opacity}\" isn't a number")));
+        }
+        let opacity = opacity.clamp(0.0, 1.0)*parent_opacity;
+        let is_visible = attribute(&tag, "visibility")?.is_none_or(|visibility| visibility != "hidden") && is_parent_visible;
+        match tag.name().as_ref() {
+            b"image" => {
+                let width = parse_attribute(&tag, "w")?;
+                let height = parse_attribute(&tag, "h")?;
+                size = width.zip(height);
+            }
+            b"stack" if !is_empty => stacks.push((opacity, is_visible)),
+            b"layer" => {
+                let Some(src) = attribute(&tag, "src")? else { continue; };
+                let layer = Layer {
+                    name: attribute(&tag, "name")?.unwrap_or_default(),
+                    raster: Raster::new(0, 0),
+                    x: parse_attribute(&tag, "x")?.unwrap_or(0),
+                    y: parse_attribute(&tag, "y")?.unwrap_or(0),
+                    opacity,
+                    is_visible,
+                    blend_mode: attribute(&tag, "composite-op")?.map_or(BlendMode::Normal, |op| blend_mode(&op)),
+                };
+                entries.push(LayerEntry { src, layer });
+            }
+            _ => {}
+        }
+    }
+
+    let Some((width, height)) = size else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "stack.xml doesn't give the image's size"));
+    };
+    check_size(width, height)?;
+    Ok((width, height, entries))
+}
+
+fn attribute(tag: &BytesStart, name: &str) -> io::Result<Option<String>> {
+    let Some(attribute) = tag.try_get_attribute(name).map_err(io::Error::other)? else {
+        return Ok(None);
+    };
+    attribute.unescape_value().map(Cow::into_owned).map(Some).map_err(io::Error::other)
+}
+
+fn parse_attribute<T: std::str::FromStr>(tag: &BytesStart, name: &str) -> io::Result<Option<T>> {
+    attribute(tag, name)?
+        .map(|value| value.trim().parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("{name}=\"{value}\" isn't a number"))))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_survive() {
+        let bottom = Layer::new("Background".to_string(), Raster::filled(4, 3, [10, 20, 30, 255]));
+        let top = Layer {
+            x: 1,
+            y: -1,
+            opacity: 0.5,
+            is_visible: false,
+            blend_mode: BlendMode::Multiply,
+            ..Layer::new("Shading & \"light\"".to_string(), Raster::from_pixels(2, 1, vec![[255, 0, 0, 128], [0, 0, 0, 0]]))
+        };
+        let stack = LayerStack { width: 4, height: 3, layers: vec![bottom, top] };
+        let mut bytes = io::Cursor::new(Vec::new());
+        encode(&mut bytes, &stack).unwrap();
+        assert!(matches(bytes.get_ref()));
+        let decoded = decode(bytes).unwrap();
+
+        assert_eq!((decoded.width, decoded.height), (4, 3));
+        assert_eq!(decoded.layers.len(), 2);
+        for (layer, expected) in decoded.layers.iter().zip(&stack.layers) {
+            assert_eq!(layer.name, expected.name);
+            assert_eq!(layer.raster, expected.raster);
+            assert_eq!((layer.x, layer.y), (expected.x, expected.y));
+            assert_eq!(layer.opacity, expected.opacity);
+            assert_eq!(layer.is_visible, expected.is_visible);
+            assert_eq!(layer.blend_mode, expected.blend_mode);
+        }
+    }
+
+    #[test]
+    fn bad_stacks_are_refused() {
+        let stack = |image: &str, layer: &str| format!(r#"<image {image}><stack><layer src="data/layer0.png" {layer}/></stack></image>"#);
+        assert!(read_stack(&stack(r#"w="4" h="3""#, r#"opacity="0.5""#)).is_ok());
+        for (image, layer) in [
+            (r#"w="100000000" h="3""#, ""),
+            (r#"w="0" h="3""#, ""),
+            (r#"w="4" h="3""#, r#"opacity="NaN""#),
+            (r#"w="4" h="3""#, r#"opacity="inf""#),
+        ] {
+            let err = read_stack(&stack(image, layer)).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,569 @@
+//! Reading Photoshop documents, both PSD and the larger PSB. Only RGB and grayscale at 8 or 16 bits are read.
+
+use std::io::{self, Read};
+use flate2::read::ZlibDecoder;
+use crate::raster::{Raster, blend::BlendMode};
+use super::{Layer, LayerStack};
+
+const RAW: u16 = 0;
+const RLE: u16 = 1;
+const ZIP: u16 = 2;
+const ZIP_PREDICTED: u16 = 3;
+
+/// Additional layer info whose length is 64 bits in a PSB
+const LONG_KEYS: [&[u8; 4]; 13] = [b"LMsk", b"Lr16", b"Lr32", b"Layr", b"Mt16", b"Mt32", b"Mtrn", b"Alph", b"FMsk", b"lnk2", b"FEid", b"FXid", b"PxSD"];
+/// Additional layer info only adjustment and fill layers have, which have no pixels of their own
+const ADJUSTMENT_KEYS: [&[u8; 4]; 20] = [
+    b"levl", b"curv", b"brit", b"blnc", b"hue ", b"hue2", b"selc", b"mixr", b"grdm", b"phfl",
+    b"expA", b"vibA", b"thrs", b"post", b"nvrt", b"blwh", b"clrL", b"SoCo", b"GdFl", b"PtFl",
+];
+
+/// What was read from a Photoshop document
+pub struct Psd {
+    /// The image as Photoshop shows it, if it was saved with one
+    pub merged: Option<Raster>,
+    pub layers: LayerStack,
+    /// What was left out of `layers` and why
+    pub warnings: Vec<String>,
+    /// Why there are no layers, when they couldn't be read but the merged image could
+    pub layers_error: Option<String>,
+}
+
+impl Psd {
+    /// The merged image, or the layers flattened if there isn't one, and what was left out to get it
+    pub fn into_image(self) -> (Raster, Vec<String>) {
+        match self.merged {
+            // anything left out of the layers is in the merged image as Photoshop drew it
+            Some(merged) => (merged, self.layers_error.into_iter().collect()),
+            None => (self.layers.flatten(), self.warnings),
+        }
+    }
+}
+
+pub fn matches(header: &[u8]) -> bool {
+    header.starts_with(b"8BPS") && matches!(header.get(4..6), Some([0, 1] | [0, 2]))
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn unsupported(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, message.into())
+}
+
+/// Big-endian reads through a byte slice, where running out is an error rather than a panic
+struct Bytes<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.pos.min(self.bytes.len())..]
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let end = self.pos.checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| invalid("the file ends too soon"))?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        self.array().map(u16::from_be_bytes)
+    }
+
+    fn i16(&mut self) -> io::Result<i16> {
+        self.array().map(i16::from_be_bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.array().map(u32::from_be_bytes)
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        self.array().map(i32::from_be_bytes)
+    }
+
+    /// A length that's 64 bits in a PSB and 32 in a PSD
+    fn length(&mut self, is_long: bool) -> io::Result<usize> {
+        let len = if is_long { u64::from_be_bytes(self.array()?) } else { self.u32()? as u64 };
+        usize::try_from(len).map_err(|_| invalid("a section is too long"))
+    }
+
+    /// A section starting with its length, to read separately from whatever follows it
+    fn section(&mut self, is_long: bool) -> io::Result<Bytes<'a>> {
+        let len = self.length(is_long)?;
+        self.take(len).map(Bytes::new)
+    }
+}
+
+struct Header {
+    is_psb: bool,
+    channels: usize,
+    width: usize,
+    height: usize,
+    /// Bits per sample
+    depth: u16,
+    /// 1 for grayscale, 3 for RGB
+    color_channels: usize,
+}
+
+/// What the layer and mask section holds
+struct LayerInfo {
+    layers: Vec<Layer>,
+    warnings: Vec<String>,
+    /// Whether the merged image's first extra channel is its transparency rather than a saved selection or spot color
+    has_merged_alpha: bool,
+}
+
+impl LayerInfo {
+    /// A document without layers, whose extra channel is the transparency if it has one
+    fn empty() -> Self {
+        Self { layers: Vec::new(), warnings: Vec::new(), has_merged_alpha: true }
+    }
+}
+
+/// What a layer record turned out to be
+enum Kind {
+    Pixels,
+    /// The top of a group, which comes after everything in it
+    GroupStart,
+    /// The bottom of a group, before everything in it
+    GroupEnd,
+    Adjustment,
+}
+
+struct Record {
+    name: String,
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
+    /// Each channel's id and the length of its data
+    channels: Vec<(i16, usize)>,
+    blend_key: [u8; 4],
+    opacity: u8,
+    is_visible: bool,
+    kind: Kind,
+}
+
+pub fn decode(bytes: &[u8]) -> io::Result<Psd> {
+    let mut data = Bytes::new(bytes);
+    if data.take(4)? != b"8BPS" {
+        return Err(invalid("not a Photoshop document"));
+    }
+    let is_psb = match data.u16()? {
+        1 => false,
+        2 => true,
+        version => return Err(unsupported(format!("Photoshop version {version} documents aren't supported"))),
+    };
+    data.take(6)?;
+    let channels = data.u16()? as usize;
+    let height = data.u32()? as usize;
+    let width = data.u32()? as usize;
+    let depth = data.u16()?;
+    let color_channels = match data.u16()? {
+        // duotone is stored as grayscale
+        1 | 8 => 1,
+        3 => 3,
+        _ => return Err(unsupported("only RGB and grayscale documents can be read")),
+    };
+    if !matches!(depth, 8 | 16) {
+        return Err(unsupported(format!("{depth}-bit documents can't be read")));
+    }
+    let max_size = if is_psb { 300_000 } else { 30_000 };
+    if width > max_size || height > max_size {
+        return Err(invalid(format!("{width}x{height} is larger than a Photoshop document can be")));
+    }
+    if width == 0 || height == 0 {
+        return Err(invalid(format!("{width}x{height} has no pixels")));
+    }
+    let header = Header { is_psb, channels, width, height, depth, color_channels };
+
+    // color mode data, then image resources
+    data.section(false)?;
+    data.section(false)?;
+    let info = read_layer_and_mask(&mut data.section(is_psb)?, &header);
+    // which channel is the merged image's transparency can't be known without the layers
+    let has_merged_alpha = info.as_ref().is_ok_and(|info| info.has_merged_alpha);
+    let merged = if data.is_empty() { None } else { Some(read_merged(&mut data, &header, has_merged_alpha)?) };
+    let (info, layers_error) = match info {
+        Ok(info) => (info, None),
+        // the merged image has everything the layers would have given
+        Err(err) if merged.is_some() => (LayerInfo::empty(), Some(format!("Couldn't read the layers, so only the merged image was opened: {err}"))),
+        Err(err) => return Err(err),
+    };
+    Ok(Psd {
+        merged,
+        layers: LayerStack { width, height, layers: info.layers },
+        warnings: info.warnings,
+        layers_error,
+    })
+}
+
+fn read_layer_and_mask(section: &mut Bytes, header: &Header) -> io::Result<LayerInfo> {
+    if section.is_empty() {
+        return Ok(LayerInfo::empty());
+    }
+    let mut info = section.section(header.is_psb)?;
+    if !info.is_empty() {
+        return read_layer_info(&mut info, header);
+    }
+
+    // deeper documents keep their layers in a block of additional info instead
+    section.section(false)?;
+    while section.rest().len() >= 12 {
+        let signature = section.array::<4>()?;
+        if !matches!(&signature, b"8BIM" | b"8B64") {
+            break;
+        }
+        let key = section.array::<4>()?;
+        let len = section.length(header.is_psb && LONG_KEYS.contains(&&key))?;
+        let block = section.take(len)?;
+        if matches!(&key, b"Lr16" | b"Layr") {
+            return read_layer_info(&mut Bytes::new(block), header);
+        }
+        // these are padded to 4 bytes
+        section.take((4 - len % 4) % 4)?;
+    }
+    Ok(LayerInfo::empty())
+}
+
+/// Every raster layer bottom first, with groups' visibility and opacity passed down to what's in them
+fn read_layer_info(info: &mut Bytes, header: &Header) -> io::Result<LayerInfo> {
+    // negative when the merged image's first extra channel is its transparency
+    let signed_count = info.i16()?;
+    let count = signed_count.unsigned_abs() as usize;
+    let records = (0..count).map(|_| read_record(info, header)).collect::<io::Result<Vec<_>>>()?;
+
+    // every record's pixels follow the records, in the same order
+    let mut layers = Vec::with_capacity(records.len());
+    for record in &records {
+        let mut channels = Vec::with_capacity(record.channels.len());
+        for &(id, len) in &record.channels {
+            channels.push((id, info.take(len)?));
+        }
+        layers.push(match record.kind {
+            Kind::Pixels => Some(read_layer_pixels(record, &channels, header)?),
+            _ => None,
+        });
+    }
+
+    // groups end before they start going bottom up, so they're passed down going top down
+    let mut warnings = Vec::new();
+    let mut groups: Vec<(f32, bool)> = Vec::new();
+    let mut stack = Vec::with_capacity(layers.len());
+    for (record, raster) in records.iter().zip(layers).rev() {
+        let (parent_opacity, is_parent_visible) = groups.last().copied().unwrap_or((1.0, true));
+        let opacity = record.opacity as f32/255.0*parent_opacity;
+        let is_visible = record.is_visible && is_parent_visible;
+        match record.kind {
+            Kind::GroupStart => groups.push((opacity, is_visible)),
+            Kind::GroupEnd => {
+                groups.pop();
+            }
+            Kind::Adjustment => warnings.push(format!("Left out adjustment layer \"{}\"", record.name)),
+            Kind::Pixels => {
+                let Some(raster) = raster else { continue; };
+                let blend_mode = blend_mode(&record.blend_key).unwrap_or_else(|| {
+                    warnings.push(format!("\"{}\" uses a blend mode that isn't supported, so it blends normally", record.name));
+                    BlendMode::Normal
+                });
+                stack.push(Layer {
+                    name: record.name.clone(),
+                    raster,
+                    x: record.left,
+                    y: record.top,
+                    opacity,
+                    is_visible,
+                    blend_mode,
+                });
+            }
+        }
+    }
+    stack.reverse();
+    Ok(LayerInfo {
+        layers: stack,
+        warnings,
+        has_merged_alpha: signed_count <= 0,
+    })
+}
+
+fn read_record(info: &mut Bytes, header: &Header) -> io::Result<Record> {
+    let (top, left, bottom, right) = (info.i32()?, info.i32()?, info.i32()?, info.i32()?);
+    let channel_count = info.u16()?;
+    let channels = (0..channel_count)
+        .map(|_| Ok((info.i16()?, info.length(header.is_psb)?)))
+        .collect::<io::Result<Vec<_>>>()?;
+    if info.take(4)? != b"8BIM" {
+        return Err(invalid("a layer's blend mode is missing its signature"));
+    }
+    let blend_key = info.array()?;
+    let opacity = info.u8()?;
+    let _clipping = info.u8()?;
+    let flags = info.u8()?;
+    let _filler = info.u8()?;
+
+    let mut extra = info.section(false)?;
+    // layer mask, then blending ranges
+    extra.section(false)?;
+    extra.section(false)?;
+    // a Pascal string padded to 4 bytes, length included
+    let name_len = extra.u8()? as usize;
+    let mut name = String::from_utf8_lossy(extra.take(name_len)?).into_owned();
+    extra.take((4 - (name_len + 1) % 4) % 4)?;
+
+    let mut kind = Kind::Pixels;
+    while extra.rest().len() >= 12 {
+        let signature = extra.array::<4>()?;
+        if !matches!(&signature, b"8BIM" | b"8B64") {
+            break;
+        }
+        let key = extra.array::<4>()?;
+        let len = extra.length(header.is_psb && LONG_KEYS.contains(&&key))?;
+        let mut block = Bytes::new(extra.take(len)?);
+        match &key {
+            // the full name, where the Pascal string may be cut short
+            b"luni" => {
+                let len = block.u32()? as usize;
+                let units = (0..len).map(|_| block.u16()).collect::<io::Result<Vec<_>>>()?;
+                name = String::from_utf16_lossy(&units).trim_end_matches('\0').to_string();
+            }
+            b"lsct" | b"lsdk" => match block.u32()? {
+                1 | 2 => kind = Kind::GroupStart,
+                3 => kind = Kind::GroupEnd,
+                _ => {}
+            },
+            key if ADJUSTMENT_KEYS.contains(&key) => kind = Kind::Adjustment,
+            _ => {}
+        }
+    }
+
+    Ok(Record {
+        name,
+        top,
+        left,
+        bottom,
+        right,
+        channels,
+        blend_key,
+        opacity,
+        is_visible: flags & 0b10 == 0,
+        kind,
+    })
+}
+
+/// A layer's own color and transparency channels; its masks are left out
+fn read_layer_pixels(record: &Record, channels: &[(i16, &[u8])], header: &Header) -> io::Result<Raster> {
+    /// How many times the document's size a layer can be, which leaves room for layers moved partly out of view
+    const MAX_SCALE: usize = 4;
+    let side = |start: i32, end: i32| usize::try_from(end as i64 - start as i64).unwrap_or(0);
+    let (width, height) = (side(record.left, record.right), side(record.top, record.bottom));
+    if width > header.width.max(1)*MAX_SCALE || height > header.height.max(1)*MAX_SCALE {
+        return Err(invalid(format!("\"{}\" is {width}x{height}, far larger than the document", record.name)));
+    }
+    if width == 0 || height == 0 {
+        return Ok(Raster::new(width, height));
+    }
+
+    // every plane is read before the layer is made, so short data fails before anything large is allocated
+    let mut planes = Vec::with_capacity(channels.len());
+    for &(id, data) in channels {
+        // -1 is transparency, and masks are -2 and below
+        let index = match id {
+            -1 => 3,
+            0..=2 if (id as usize) < header.color_channels => id as usize,
+            _ => continue,
+        };
+        let mut data = Bytes::new(data);
+        let compression = data.u16()?;
+        if let Some(plane) = read_planes(&mut data, compression, width, height, 1, header)?.pop() {
+            planes.push((index, plane));
+        }
+    }
+    let mut raster = Raster::filled(width, height, [0, 0, 0, 255]);
+    for (index, plane) in planes {
+        copy_plane(&mut raster, &plane, index, header.color_channels);
+    }
+    Ok(raster)
+}
+
+/// The image as Photoshop last showed it, which is blended against white where it's transparent.
+/// Without `has_alpha`, any channels past the colors are saved selections or spot colors, and are left out.
+fn read_merged(data: &mut Bytes, header: &Header, has_alpha: bool) -> io::Result<Raster> {
+    let compression = data.u16()?;
+    let planes = read_planes(data, compression, header.width, header.height, header.channels, header)?;
+    let has_alpha = has_alpha && header.channels > header.color_channels;
+    let mut raster = Raster::filled(header.width, header.height, [0, 0, 0, 255]);
+    for (i, plane) in planes.iter().enumerate().take(header.color_channels + has_alpha as usize) {
+        let index = if i == header.color_channels { 3 } else { i };
+        copy_plane(&mut raster, plane, index, header.color_channels);
+    }
+
+    if has_alpha {
+        for px in raster.pixels_mut() {
+            if let 1..=254 = px[3] {
+                let a = px[3] as f32/255.0;
+                for channel in &mut px[..3] {
+                    *channel = ((*channel as f32 - 255.0*(1.0 - a))/a).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+    }
+    Ok(raster)
+}
+
+/// Put one channel into the raster, spreading gray across red, green and blue
+fn copy_plane(raster: &mut Raster, plane: &[u8], index: usize, color_channels: usize) {
+    for (px, &value) in raster.pixels_mut().iter_mut().zip(plane) {
+        if index == 0 && color_channels == 1 {
+            px[..3].fill(value);
+        } else {
+            px[index] = value;
+        }
+    }
+}
+
+/// `planes` channels of `width` by `height` samples each, brought down to 8 bits.
+/// Compressed rows' lengths all come before any of their data.
+fn read_planes(data: &mut Bytes, compression: u16, width: usize, height: usize, planes: usize, header: &Header) -> io::Result<Vec<Vec<u8>>> {
+    let sample_size = header.depth as usize/8;
+    let row_len = width*sample_size;
+    // the most each compressed byte can expand to, so a corrupt size can't ask for more memory than the data could fill
+    let max_ratio = match compression {
+        RAW => 1,
+        // two bytes for a run of 128
+        RLE => 64,
+        // deflate's limit
+        _ => 1032,
+    };
+    let Some(plane_len) = row_len.checked_mul(height)
+        .filter(|&len| len.checked_mul(planes).is_some_and(|len| len <= data.rest().len().saturating_mul(max_ratio)))
+    else {
+        return Err(invalid("there's too little pixel data for the size"));
+    };
+    let samples = match compression {
+        RAW => data.take(plane_len*planes)?.to_vec(),
+        RLE => {
+            let counts = (0..height*planes)
+                .map(|_| if header.is_psb { data.u32().map(|len| len as usize) } else { data.u16().map(|len| len as usize) })
+                .collect::<io::Result<Vec<_>>>()?;
+            let mut samples = Vec::with_capacity(plane_len*planes);
+            for count in counts {
+                unpack_bits(data.take(count)?, row_len, &mut samples)?;
+            }
+            samples
+        }
+        ZIP | ZIP_PREDICTED => {
+            let mut samples = Vec::with_capacity(plane_len*planes);
+            // inflate no more than is needed, so a small file can't expand without end
+            ZlibDecoder::new(data.rest()).take((plane_len*planes) as u64).read_to_end(&mut samples)?;
+            data.take(data.rest().len())?;
+            if samples.len() < plane_len*planes {
+                return Err(invalid("compressed pixels are cut short"));
+            }
+            if compression == ZIP_PREDICTED {
+                for row in samples.chunks_exact_mut(row_len.max(1)) {
+                    undo_prediction(row, header.depth);
+                }
+            }
+            samples
+        }
+        _ => return Err(unsupported(format!("compression method {compression} isn't supported"))),
+    };
+
+    Ok(samples.chunks_exact(plane_len.max(1))
+        .take(planes)
+        .map(|plane| if sample_size == 2 { plane.iter().step_by(2).copied().collect() } else { plane.to_vec() })
+        .collect())
+}
+
+/// Each sample is stored as the difference from the one before it in the row
+fn undo_prediction(row: &mut [u8], depth: u16) {
+    if depth == 16 {
+        let mut previous = 0u16;
+        for sample in row.chunks_exact_mut(2) {
+            previous = previous.wrapping_add(u16::from_be_bytes([sample[0], sample[1]]));
+            sample.copy_from_slice(&previous.to_be_bytes());
+        }
+    } else {
+        for i in 1..row.len() {
+            row[i] = row[i].wrapping_add(row[i - 1]);
+        }
+    }
+}
+
+/// Expand one PackBits row of `row_len` bytes onto `out`
+fn unpack_bits(mut packed: &[u8], row_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+    let end = out.len() + row_len;
+    while out.len() < end {
+        let Some((&header, rest)) = packed.split_first() else {
+            return Err(invalid("a compressed row is cut short"));
+        };
+        packed = rest;
+        match header as i8 {
+            // a literal run
+            n @ 0.. => {
+                let len = n as usize + 1;
+                let Some((literal, rest)) = packed.split_at_checked(len) else {
+                    return Err(invalid("a compressed row is cut short"));
+                };
+                out.extend_from_slice(literal);
+                packed = rest;
+            }
+            // no-op
+            -128 => {}
+            // a repeated byte
+            n => {
+                let Some((&value, rest)) = packed.split_first() else {
+                    return Err(invalid("a compressed row is cut short"));
+                };
+                out.resize(out.len() + (1 - n as isize) as usize, value);
+                packed = rest;
+            }
+        }
+    }
+    out.truncate(end);
+    Ok(())
+}
+
+/// The blend mode a layer's key names, if it's one that's supported
+fn blend_mode(key: &[u8; 4]) -> Option<BlendMode> {
+    Some(match key {
+        // pass through is for groups, whose layers blend on their own
+        b"norm" | b"pass" => BlendMode::Normal,
+        b"mul " => BlendMode::Multiply,
+        b"scrn" => BlendMode::Screen,
+        b"over" => BlendMode::Overlay,
+        b"dark" => BlendMode::Darken,
+        b"lite" => BlendMode::Lighten,
+        b"div " => BlendMode::ColorDodge,
+        b"idiv" => BlendMode::ColorBurn,
+        b"hLit" => BlendMode::HardLight,
+        b"sLit" => BlendMode::SoftLight,
+        b"diff" => BlendMode::Difference,
+        b"smud" => BlendMode::Exclusion,
+        b"lddg" => BlendMode::Add,
+        _ => return None,
+    })
+}
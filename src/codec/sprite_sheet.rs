@@ -0,0 +1,387 @@
+use serde::{Deserialize, Serialize};
+use crate::raster::{Raster, Rect};
+use super::{Animation, MAX_SIZE, check_frame_count};
+
+/// How frames are arranged on the sheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Left to right, wrapping after this many columns
+    Grid { columns: usize },
+    /// A single horizontal strip
+    Row,
+    /// A single vertical strip
+    Column,
+    /// Trim transparent borders and pack frames into shelves as tightly as possible
+    Packed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetOptions {
+    pub layout: Layout,
+    /// Transparent pixels between frames and around the edge
+    pub padding: usize,
+    /// Origin of each frame as a fraction of its size, (0.5, 0.5) being the center
+    pub pivot: (f32, f32),
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        Self {
+            layout: Layout::Grid { columns: 8 },
+            padding: 0,
+            pivot: (0.5, 0.5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RectMeta {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+impl From<Rect> for RectMeta {
+    fn from(rect: Rect) -> Self {
+        Self { x: rect.x, y: rect.y, w: rect.width, h: rect.height }
+    }
+}
+
+impl From<RectMeta> for Rect {
+    fn from(rect: RectMeta) -> Self {
+        Rect::new(rect.x, rect.y, rect.w, rect.h)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SizeMeta {
+    pub w: i32,
+    pub h: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PointMeta {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// One frame's placement, in the layout used by Aseprite and TexturePacker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameMeta {
+    #[serde(default)]
+    pub filename: String,
+    /// Where the frame is on the sheet
+    pub frame: RectMeta,
+    /// Stored a quarter turn clockwise, taking `frame.h` by `frame.w` pixels on the sheet
+    #[serde(default)]
+    pub rotated: bool,
+    #[serde(default)]
+    pub trimmed: bool,
+    /// Where the trimmed frame goes within the original
+    #[serde(default)]
+    pub sprite_source_size: Option<RectMeta>,
+    /// Size of the original frame before trimming
+    #[serde(default)]
+    pub source_size: Option<SizeMeta>,
+    /// In milliseconds
+    #[serde(default)]
+    pub duration: Option<u32>,
+    #[serde(default)]
+    pub pivot: Option<PointMeta>,
+}
+
+/// A named range of frames
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagMeta {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    /// "forward", "reverse" or "pingpong"
+    #[serde(default = "TagMeta::default_direction")]
+    pub direction: String,
+}
+
+impl TagMeta {
+    fn default_direction() -> String {
+        "forward".to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Meta {
+    #[serde(default)]
+    pub app: String,
+    #[serde(default)]
+    pub version: String,
+    /// File name of the sheet image, relative to the metadata
+    #[serde(default)]
+    pub image: String,
+    #[serde(default)]
+    pub format: String,
+    #[serde(default)]
+    pub size: SizeMeta,
+    #[serde(default)]
+    pub scale: String,
+    #[serde(default)]
+    pub frame_tags: Vec<TagMeta>,
+}
+
+/// Aseprite exports frames either as an array or as an object keyed by file name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FrameList {
+    Array(Vec<FrameMeta>),
+    Hash(serde_json::Map<String, serde_json::Value>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SheetMeta {
+    pub frames: FrameList,
+    pub meta: Meta,
+}
+
+impl SheetMeta {
+    /// Frames in order, taking names from the keys of hash-style lists
+    pub fn frames(&self) -> serde_json::Result<Vec<FrameMeta>> {
+        match &self.frames {
+            FrameList::Array(frames) => Ok(frames.clone()),
+            FrameList::Hash(map) => map.iter()
+                .map(|(name, value)| {
+                    let mut frame: FrameMeta = serde_json::from_value(value.clone())?;
+                    if frame.filename.is_empty() {
+                        frame.filename.clone_from(name);
+                    }
+                    Ok(frame)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Arrange every frame of `animation` on one image, describing where each went.
+/// `name` is used for frame file names and `image` is the sheet's file name.
+pub fn pack(animation: &Animation, options: &SheetOptions, name: &str, image: &str, tags: Vec<TagMeta>) -> (Raster, SheetMeta) {
+    let (width, height) = animation.size().unwrap_or((0, 0));
+    let full = Rect::new(0, 0, width as i32, height as i32);
+    let pad = options.padding as i32;
+
+    // the part of each frame to keep, in frame coordinates
+    let sources: Vec<Rect> = animation.frames.iter()
+        .map(|(raster, _)| if options.layout == Layout::Packed {
+            // keep a pixel of fully transparent frames so every frame has a rect
+            raster.content_bounds().unwrap_or(Rect::new(0, 0, 1, 1))
+        } else {
+            full
+        })
+        .collect();
+
+    let positions: Vec<(i32, i32)> = match options.layout {
+        Layout::Grid { columns } => {
+            let columns = columns.max(1);
+            (0..sources.len())
+                .map(|i| (pad + (i % columns) as i32*(full.width + pad), pad + (i / columns) as i32*(full.height + pad)))
+                .collect()
+        }
+        Layout::Row => (0..sources.len()).map(|i| (pad + i as i32*(full.width + pad), pad)).collect(),
+        Layout::Column => (0..sources.len()).map(|i| (pad, pad + i as i32*(full.height + pad))).collect(),
+        Layout::Packed => shelf_pack(&sources, pad),
+    };
+
+    let sheet_width  = sources.iter().zip(&positions).map(|(src, (x, _))| x + src.width  + pad).max().unwrap_or(0);
+    let sheet_height = sources.iter().zip(&positions).map(|(src, (_, y))| y + src.height + pad).max().unwrap_or(0);
+    let mut sheet = Raster::new(sheet_width as usize, sheet_height as usize);
+
+    let mut frames = Vec::with_capacity(sources.len());
+    for (i, ((raster, duration), (source, &(x, y)))) in animation.frames.iter().zip(sources.iter().zip(&positions)).enumerate() {
+        sheet.blit(&raster.crop(*source), x, y);
+        frames.push(FrameMeta {
+            filename: format!("{name} {i}"),
+            frame: Rect::new(x, y, source.width, source.height).into(),
+            rotated: false,
+            trimmed: *source != full,
+            sprite_source_size: Some((*source).into()),
+            source_size: Some(SizeMeta { w: full.width, h: full.height }),
+            duration: Some(*duration),
+            pivot: Some(PointMeta { x: options.pivot.0, y: options.pivot.1 }),
+        });
+    }
+
+    let meta = SheetMeta {
+        frames: FrameList::Array(frames),
+        meta: Meta {
+            app: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            image: image.to_string(),
+            format: "RGBA8888".to_string(),
+            size: SizeMeta { w: sheet_width, h: sheet_height },
+            scale: "1".to_string(),
+            frame_tags: tags,
+        },
+    };
+    (sheet, meta)
+}
+
+/// Place rects tallest first in rows no wider than a square of their total area would be
+fn shelf_pack(rects: &[Rect], pad: i32) -> Vec<(i32, i32)> {
+    let area: i64 = rects.iter().map(|r| (r.width + pad) as i64*(r.height + pad) as i64).sum();
+    let widest = rects.iter().map(|r| r.width).max().unwrap_or(0);
+    let max_width = ((area as f64).sqrt().ceil() as i32).max(widest + pad) + pad;
+
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(rects[i].height));
+
+    let mut positions = vec![(0, 0); rects.len()];
+    let (mut x, mut y, mut shelf_height) = (pad, pad, 0);
+    for i in order {
+        let rect = rects[i];
+        if x + rect.width + pad > max_width && x > pad {
+            x = pad;
+            y += shelf_height + pad;
+            shelf_height = 0;
+        }
+        positions[i] = (x, y);
+        x += rect.width + pad;
+        shelf_height = shelf_height.max(rect.height);
+    }
+    positions
+}
+
+fn invalid(message: String) -> serde_json::Error {
+    serde::de::Error::custom(message)
+}
+
+/// Whether `rect` has pixels and lies within `width` by `height`, worked out without overflowing
+fn is_within(rect: RectMeta, width: i64, height: i64) -> bool {
+    let (x, y, w, h) = (rect.x as i64, rect.y as i64, rect.w as i64, rect.h as i64);
+    x >= 0 && y >= 0 && w > 0 && h > 0 && x + w <= width && y + h <= height
+}
+
+/// Cut frames out of `sheet` as described by `meta`, restoring trimmed borders.
+/// Frames are placed in a canvas big enough for the largest original size.
+/// Fails if a frame isn't on the sheet or wouldn't fit in its original size.
+pub fn slice_by_meta(sheet: &Raster, meta: &SheetMeta) -> serde_json::Result<Animation> {
+    let frames = meta.frames()?;
+    let source_size = |frame: &FrameMeta| frame.source_size.unwrap_or(SizeMeta { w: frame.frame.w, h: frame.frame.h });
+    for frame in &frames {
+        let size = source_size(frame);
+        if !(1..=MAX_SIZE as i32).contains(&size.w) || !(1..=MAX_SIZE as i32).contains(&size.h) {
+            return Err(invalid(format!("\"{}\" is {}x{}, outside 1x1 to {MAX_SIZE}x{MAX_SIZE}", frame.filename, size.w, size.h)));
+        }
+        let on_sheet = if frame.rotated { RectMeta { w: frame.frame.h, h: frame.frame.w, ..frame.frame } } else { frame.frame };
+        if !is_within(on_sheet, sheet.width() as i64, sheet.height() as i64) {
+            return Err(invalid(format!("\"{}\" isn't on the sheet", frame.filename)));
+        }
+        let offset = frame.sprite_source_size.unwrap_or_default();
+        if !is_within(RectMeta { w: frame.frame.w, h: frame.frame.h, ..offset }, size.w as i64, size.h as i64) {
+            return Err(invalid(format!("\"{}\" doesn't fit in its original size", frame.filename)));
+        }
+    }
+    let width  = frames.iter().map(|f| source_size(f).w).max().unwrap_or(0).max(1) as usize;
+    let height = frames.iter().map(|f| source_size(f).h).max().unwrap_or(0).max(1) as usize;
+    check_frame_count(frames.len(), width, height).map_err(|err| invalid(err.to_string()))?;
+
+    let frames = frames.iter().map(|frame| {
+        let rect = Rect::from(frame.frame);
+        let cell = if frame.rotated {
+            sheet.crop(Rect::new(rect.x, rect.y, rect.height, rect.width)).rotated_ccw()
+        } else {
+            sheet.crop(rect)
+        };
+        let offset = frame.sprite_source_size.map_or((0, 0), |src| (src.x, src.y));
+        let mut raster = Raster::new(width, height);
+        raster.blit(&cell, offset.0, offset.1);
+        (raster, frame.duration.unwrap_or(100))
+    }).collect();
+
+    Ok(Animation { frames, loop_count: 0 })
+}
+
+/// Cut `sheet` into cells of the given size, left to right then top to bottom,
+/// with `spacing` pixels between cells. Partial cells at the edges are dropped.
+pub fn slice_by_grid(sheet: &Raster, cell_width: usize, cell_height: usize, spacing: usize, skip_empty: bool, duration: u32) -> Animation {
+    let (cell_width, cell_height) = (cell_width.max(1), cell_height.max(1));
+    let columns = (sheet.width() + spacing)/(cell_width + spacing);
+    let rows = (sheet.height() + spacing)/(cell_height + spacing);
+    let frames = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (row, column)))
+        .map(|(row, column)| sheet.crop(Rect::new(
+            (column*(cell_width + spacing)) as i32,
+            (row*(cell_height + spacing)) as i32,
+            cell_width as i32,
+            cell_height as i32,
+        )))
+        .filter(|cell| !skip_empty || cell.content_bounds().is_some())
+        .map(|cell| (cell, duration))
+        .collect();
+    Animation { frames, loop_count: 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames with transparent borders of different sizes, so packing trims them differently
+    fn animation() -> Animation {
+        let frames = (0..5)
+            .map(|i| {
+                let mut raster = Raster::new(6, 4);
+                raster.fill_rect(Rect::new(i % 3, i % 2, 1 + i % 4, 2), [40*i as u8, 255, 0, 255]);
+                (raster, 50 + 10*i as u32)
+            })
+            .collect();
+        Animation { frames, loop_count: 0 }
+    }
+
+    #[test]
+    fn sliced_sheets_match_their_frames() {
+        let animation = animation();
+        for layout in [Layout::Grid { columns: 2 }, Layout::Row, Layout::Column, Layout::Packed] {
+            let options = SheetOptions { layout, padding: 1, ..SheetOptions::default() };
+            let (sheet, meta) = pack(&animation, &options, "sprite", "sprite.png", Vec::new());
+            let meta: SheetMeta = serde_json::from_str(&serde_json::to_string(&meta).unwrap()).unwrap();
+            let sliced = slice_by_meta(&sheet, &meta).unwrap();
+            assert_eq!(sliced.frames.len(), animation.frames.len(), "{layout:?}");
+            for ((raster, duration), (expected, expected_duration)) in sliced.frames.iter().zip(&animation.frames) {
+                assert_eq!(duration, expected_duration, "{layout:?}");
+                assert_eq!(raster, expected, "{layout:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn shelves_dont_overlap() {
+        let rects: Vec<Rect> = (0..30).map(|i| Rect::new(0, 0, 1 + (i*7) % 13, 1 + (i*5) % 11)).collect();
+        for pad in [0, 2] {
+            let placed: Vec<Rect> = shelf_pack(&rects, pad).iter().zip(&rects)
+                .map(|(&(x, y), rect)| Rect::new(x, y, rect.width + pad, rect.height + pad))
+                .collect();
+            for (i, a) in placed.iter().enumerate() {
+                assert!(a.x >= pad && a.y >= pad);
+                for b in &placed[i + 1..] {
+                    assert_eq!(a.intersect(b), None, "{a:?} and {b:?} overlap with padding {pad}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn frames_off_the_sheet_are_refused() {
+        let sheet = Raster::new(8, 8);
+        let meta = |frame: &str| -> SheetMeta {
+            serde_json::from_str(&format!(r#"{{"frames": [{{{frame}}}], "meta": {{}}}}"#)).unwrap()
+        };
+        assert!(slice_by_meta(&sheet, &meta(r#""frame": {"x": 2, "y": 2, "w": 6, "h": 6}"#)).is_ok());
+        for frame in [
+            r#""frame": {"x": 2, "y": 2, "w": 7, "h": 6}"#,
+            r#""frame": {"x": 2147483647, "y": 0, "w": 4, "h": 4}"#,
+            r#""frame": {"x": -1, "y": 0, "w": 4, "h": 4}"#,
+            r#""frame": {"x": 0, "y": 0, "w": 4, "h": 4}, "sourceSize": {"w": 1000000, "h": 1000000}"#,
+            r#""frame": {"x": 0, "y": 0, "w": 4, "h": 4}, "sourceSize": {"w": 4, "h": 4}, "spriteSourceSize": {"x": 2, "y": 0, "w": 4, "h": 4}"#,
+        ] {
+            assert!(slice_by_meta(&sheet, &meta(frame)).is_err(), "{frame}");
+        }
+    }
+}
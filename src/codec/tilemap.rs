@@ -0,0 +1,117 @@
+use std::io::{self, Write};
+use serde::{Deserialize, Serialize};
+use crate::tilemap::{Tile, TileLayer, Tilemap};
+
+/// Write one layer as rows of Tiled global tile IDs separated by commas
+pub fn encode_csv(mut writer: impl Write, layer: &TileLayer, width: usize) -> io::Result<()> {
+    for row in layer.cells().chunks(width.max(1)) {
+        let line: Vec<String> = row.iter().map(|&tile| Tile::gid(tile).to_string()).collect();
+        writeln!(writer, "{}", line.join(","))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerJson {
+    pub id: usize,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub width: usize,
+    pub height: usize,
+    pub x: i32,
+    pub y: i32,
+    pub opacity: f32,
+    pub visible: bool,
+    /// Global tile IDs row by row from the top left
+    pub data: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TilesetJson {
+    pub firstgid: u32,
+    pub name: String,
+    /// File name of the tileset image, relative to the map
+    pub image: String,
+    pub imagewidth: usize,
+    pub imageheight: usize,
+    pub tilewidth: usize,
+    pub tileheight: usize,
+    pub tilecount: usize,
+    pub columns: usize,
+    pub margin: usize,
+    pub spacing: usize,
+}
+
+/// A map in the layout Tiled reads and writes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapJson {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub version: String,
+    pub orientation: String,
+    pub renderorder: String,
+    pub infinite: bool,
+    pub width: usize,
+    pub height: usize,
+    pub tilewidth: usize,
+    pub tileheight: usize,
+    pub nextlayerid: usize,
+    pub nextobjectid: usize,
+    pub layers: Vec<LayerJson>,
+    pub tilesets: Vec<TilesetJson>,
+}
+
+impl MapJson {
+    /// `image` is the file name of the tileset and (`image_width`, `image_height`) its size
+    pub fn new(map: &Tilemap, image: &str, image_width: usize, image_height: usize) -> Self {
+        let tileset = map.tileset;
+        let layers: Vec<LayerJson> = map.layers.iter().enumerate()
+            .map(|(i, layer)| LayerJson {
+                id: i + 1,
+                name: layer.name.clone(),
+                kind: "tilelayer".to_string(),
+                width: map.width(),
+                height: map.height(),
+                x: 0,
+                y: 0,
+                opacity: 1.0,
+                visible: layer.is_visible,
+                data: layer.cells().iter().map(|&tile| Tile::gid(tile)).collect(),
+            })
+            .collect();
+        Self {
+            kind: "map".to_string(),
+            version: "1.10".to_string(),
+            orientation: "orthogonal".to_string(),
+            renderorder: "right-down".to_string(),
+            infinite: false,
+            width: map.width(),
+            height: map.height(),
+            tilewidth: tileset.tile_width,
+            tileheight: tileset.tile_height,
+            nextlayerid: layers.len() + 1,
+            nextobjectid: 1,
+            layers,
+            tilesets: vec![TilesetJson {
+                firstgid: 1,
+                name: image.rsplit_once('.').map_or(image, |(stem, _)| stem).to_string(),
+                image: image.to_string(),
+                imagewidth: image_width,
+                imageheight: image_height,
+                tilewidth: tileset.tile_width,
+                tileheight: tileset.tile_height,
+                tilecount: tileset.len(),
+                columns: tileset.columns,
+                margin: 0,
+                spacing: 0,
+            }],
+        }
+    }
+}
+
+/// Write every layer as a Tiled JSON map referring to the tileset image by file name
+pub fn encode_json(writer: impl Write, map: &Tilemap, image: &str, image_width: usize, image_height: usize) -> io::Result<()> {
+    serde_json::to_writer_pretty(writer, &MapJson::new(map, image, image_width, image_height))?;
+    Ok(())
+}
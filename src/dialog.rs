@@ -0,0 +1,377 @@
+use std::path::PathBuf;
+use raylib::prelude::*;
+use crate::{error, codec::{Animation, AnimationFormat, format::EncodeOptions, sprite_sheet::SheetOptions}, editor::{art::{Anchor, ArtEditor, GridSettings}, tilemap::TilemapEditor}, frame::RaylibFrameMode, raster::{adjust::Adjustment, effect::Effect, resample::Scaler}, recovery::Recoverable};
+
+pub mod adjust;
+pub mod canvas_size;
+pub mod effect;
+pub mod export_animation;
+pub mod export_image;
+pub mod grid;
+pub mod image_size;
+pub mod keymap;
+pub mod new_document;
+pub mod recovery;
+pub mod sprite_sheet;
+pub mod tilemap;
+pub mod unsaved;
+
+use adjust::AdjustDialog;
+use canvas_size::CanvasSizeDialog;
+use effect::EffectDialog;
+use export_animation::ExportAnimationDialog;
+use export_image::ExportImageDialog;
+use grid::GridDialog;
+use image_size::ImageSizeDialog;
+use keymap::KeymapDialog;
+use new_document::NewDocumentDialog;
+use recovery::RecoveryDialog;
+use sprite_sheet::{ExportSheetDialog, SliceSheetDialog};
+use tilemap::NewTilemapDialog;
+use unsaved::{Intent, UnsavedChangesDialog};
+
+pub enum DialogResult<T> {
+    /// Still open
+    Pending,
+    Cancel,
+    Confirm(T),
+}
+
+impl<T> DialogResult<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> DialogResult<U> {
+        match self {
+            Self::Pending => DialogResult::Pending,
+            Self::Cancel => DialogResult::Cancel,
+            Self::Confirm(value) => DialogResult::Confirm(f(value)),
+        }
+    }
+}
+
+/// A modal window drawn over the editors
+pub enum Dialog {
+    CanvasSize(CanvasSizeDialog),
+    ImageSize(ImageSizeDialog),
+    Adjust(AdjustDialog),
+    Effect(EffectDialog),
+    ExportAnimation(ExportAnimationDialog),
+    ExportImage(ExportImageDialog),
+    ExportSheet(ExportSheetDialog),
+    SliceSheet(SliceSheetDialog),
+    NewTilemap(NewTilemapDialog),
+    Grid(GridDialog),
+    Keymap(KeymapDialog),
+    NewDocument(NewDocumentDialog),
+    Recovery(RecoveryDialog),
+    UnsavedChanges(UnsavedChangesDialog),
+}
+
+/// What the canvas should show while a dialog is open
+pub enum Preview {
+    Original,
+    Adjusted(Adjustment),
+    Filtered(Effect),
+}
+
+/// What a confirmed dialog asks to be done
+pub enum Action {
+    ResizeCanvas {
+        width: u32,
+        height: u32,
+        anchor: Anchor,
+    },
+    ScaleImage {
+        width: u32,
+        height: u32,
+        scaler: Scaler,
+    },
+    Adjust(Adjustment),
+    Filter(Effect),
+    ExportAnimation {
+        path: PathBuf,
+        format: AnimationFormat,
+        loop_count: u32,
+    },
+    ExportSheet {
+        path: PathBuf,
+        options: SheetOptions,
+    },
+    /// Save the document to `path`, which is where it saves from then on
+    SaveImage {
+        path: PathBuf,
+        options: EncodeOptions,
+    },
+    /// Replace every frame
+    LoadAnimation(Animation),
+    /// Start a new tilemap, cutting the canvas into tiles
+    NewTilemap {
+        tile_width: u32,
+        tile_height: u32,
+        columns: u32,
+        rows: u32,
+    },
+    SetGrid(GridSettings),
+    /// Replace every frame with a copy left behind by a crash, then delete the copy
+    Recover(Recoverable),
+    /// Open a new document in its own tab, remembering the presets as they were left
+    NewDocument {
+        width: u32,
+        height: u32,
+        background: Color,
+        presets: Vec<[u32; 2]>,
+    },
+    /// Go ahead with something that was waiting on unsaved changes, saving them first if asked to
+    Proceed {
+        intent: Intent,
+        is_saving: bool,
+    },
+}
+
+impl Dialog {
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<Action> {
+        match self {
+            Self::CanvasSize(dialog) => dialog.update(d, screen)
+                .map(|(width, height, anchor)| Action::ResizeCanvas { width, height, anchor }),
+            Self::ImageSize(dialog) => dialog.update(d, screen)
+                .map(|(width, height, scaler)| Action::ScaleImage { width, height, scaler }),
+            Self::Adjust(dialog) => dialog.update(d, screen)
+                .map(Action::Adjust),
+            Self::Effect(dialog) => dialog.update(d, screen)
+                .map(Action::Filter),
+            Self::ExportAnimation(dialog) => dialog.update(d, screen)
+                .map(|(path, format, loop_count)| Action::ExportAnimation { path, format, loop_count }),
+            Self::ExportSheet(dialog) => dialog.update(d, screen)
+                .map(|(path, options)| Action::ExportSheet { path, options }),
+            Self::ExportImage(dialog) => dialog.update(d, screen)
+                .map(|(path, options)| Action::SaveImage { path, options }),
+            Self::SliceSheet(dialog) => dialog.update(d, screen)
+                .map(Action::LoadAnimation),
+            Self::NewTilemap(dialog) => dialog.update(d, screen)
+                .map(|(tile_width, tile_height, columns, rows)| Action::NewTilemap { tile_width, tile_height, columns, rows }),
+            Self::Grid(dialog) => dialog.update(d, screen)
+                .map(Action::SetGrid),
+            Self::Keymap(dialog) => dialog.update(d, screen)
+                .map(|never| match never {}),
+            Self::NewDocument(dialog) => dialog.update(d, screen)
+                .map(|(width, height, background, presets)| Action::NewDocument { width, height, background, presets }),
+            Self::Recovery(dialog) => dialog.update(d, screen)
+                .map(Action::Recover),
+            Self::UnsavedChanges(dialog) => dialog.update(d, screen)
+                .map(|(intent, is_saving)| Action::Proceed { intent, is_saving }),
+        }
+    }
+
+    /// Returns how the canvas preview should change, if it needs to since the last call
+    pub fn take_preview(&mut self) -> Option<Preview> {
+        match self {
+            Self::Adjust(dialog) => dialog.take_preview(),
+            Self::Effect(dialog) => dialog.take_preview(),
+            Self::CanvasSize(_) | Self::ImageSize(_) | Self::ExportAnimation(_) | Self::ExportImage(_) | Self::ExportSheet(_) | Self::SliceSheet(_) | Self::NewTilemap(_) | Self::Grid(_) | Self::Keymap(_) | Self::NewDocument(_) | Self::Recovery(_) | Self::UnsavedChanges(_) => None,
+        }
+    }
+}
+
+impl Preview {
+    pub fn show(self, rl: &mut RaylibHandle, thread: &RaylibThread, art_editor: &mut ArtEditor) -> error::Result<()> {
+        match self {
+            Self::Original => art_editor.cancel_preview(rl, thread),
+            Self::Adjusted(adjustment) => art_editor.preview(rl, thread, |raster| adjustment.apply(raster)),
+            Self::Filtered(effect) => art_editor.preview(rl, thread, |raster| effect.apply(raster)),
+        }
+    }
+}
+
+impl Action {
+    /// What it does, for saying what couldn't be done
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Self::ResizeCanvas { .. } => "resize the canvas",
+            Self::ScaleImage { .. } => "scale the image",
+            Self::Adjust(_) => "adjust the colors",
+            Self::Filter(_) => "apply the filter",
+            Self::ExportAnimation { .. } => "export the animation",
+            Self::ExportSheet { .. } => "export the sprite sheet",
+            Self::SaveImage { .. } => "save the image",
+            Self::LoadAnimation(_) => "load the frames",
+            Self::NewTilemap { .. } => "start the tilemap",
+            Self::SetGrid(_) => "change the grid",
+            Self::Recover(_) => "recover the copy",
+            Self::NewDocument { .. } => "create the document",
+            Self::Proceed { .. } => "save the document",
+        }
+    }
+
+    pub fn apply(self, rl: &mut RaylibHandle, thread: &RaylibThread, art_editor: &mut ArtEditor, tilemap_editor: &mut TilemapEditor) -> error::Result<()> {
+        match self {
+            Self::ResizeCanvas { width, height, anchor } => art_editor.resize(rl, thread, width, height, anchor)?,
+            Self::ScaleImage { width, height, scaler } => art_editor.scale(rl, thread, width, height, scaler)?,
+            Self::Adjust(adjustment) => art_editor.apply_effect(rl, thread, |raster| adjustment.apply(raster))?,
+            Self::Filter(effect) => art_editor.apply_effect(rl, thread, |raster| effect.apply(raster))?,
+            Self::ExportAnimation { path, format, loop_count } => art_editor.export_animation(&path, format, loop_count)?,
+            Self::ExportSheet { path, options } => art_editor.export_sheet(&path, &options)?,
+            Self::LoadAnimation(animation) => art_editor.load_animation(rl, thread, animation)?,
+            Self::NewTilemap { tile_width, tile_height, columns, rows } => {
+                tilemap_editor.new_map(tile_width, tile_height, columns, rows, art_editor.width(), art_editor.height());
+            }
+            Self::SetGrid(grid) => art_editor.set_grid(grid),
+            Self::Recover(recoverable) => {
+                // a copy that can't be read is kept, in case it can be rescued some other way
+                art_editor.load_animation(rl, thread, recoverable.load()?)?;
+                recoverable.discard();
+            }
+            // documents, their paths and the file dialogs saving leads to are up to the caller
+            Self::SaveImage { .. } | Self::NewDocument { .. } | Self::Proceed { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+/// Draw a window of the given size centered on `screen` and return its client area,
+/// or `None` if it was closed by its close button or by pressing escape
+fn window(d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle, width: f32, height: f32, title: &str) -> Option<Rectangle> {
+    const TITLE_HEIGHT: f32 = 24.0;
+    const PADDING: f32 = 8.0;
+
+    let bounds = Rectangle::new(
+        (screen.x + (screen.width  - width )*0.5).round(),
+        (screen.y + (screen.height - height)*0.5).round(),
+        width,
+        height,
+    );
+    let is_closed = d.gui_window_box(bounds, title) || d.is_key_pressed(KeyboardKey::KEY_ESCAPE);
+    (!is_closed).then(|| Rectangle::new(
+        bounds.x + PADDING,
+        bounds.y + TITLE_HEIGHT + PADDING,
+        bounds.width - PADDING*2.0,
+        bounds.height - TITLE_HEIGHT - PADDING*2.0,
+    ))
+}
+
+/// Draw the confirm and cancel buttons along the bottom of the client area
+fn confirm_buttons(d: &mut RaylibFrameMode<RaylibHandle>, client: Rectangle, confirm: &str) -> DialogResult<()> {
+    const WIDTH: f32 = 80.0;
+    const HEIGHT: f32 = 24.0;
+    const GAP: f32 = 8.0;
+
+    let y = client.y + client.height - HEIGHT;
+    let x = client.x + client.width - WIDTH;
+    if d.gui_button(Rectangle::new(x - WIDTH - GAP, y, WIDTH, HEIGHT), "Cancel") {
+        DialogResult::Cancel
+    } else if d.gui_button(Rectangle::new(x, y, WIDTH, HEIGHT), confirm) {
+        DialogResult::Confirm(())
+    } else {
+        DialogResult::Pending
+    }
+}
+
+/// Labelled slider spanning the client area, showing its value on the right; returns whether the value changed
+fn slider(d: &mut RaylibFrameMode<RaylibHandle>, client: Rectangle, y: f32, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+    const LABEL_WIDTH: f32 = 100.0;
+    const VALUE_WIDTH: f32 = 40.0;
+
+    let old_value = *value;
+    let bounds = Rectangle::new(client.x + LABEL_WIDTH, y, client.width - LABEL_WIDTH - VALUE_WIDTH, 20.0);
+    let value_text = if max - min > 20.0 { format!("{value:.0}") } else { format!("{value:.2}") };
+    d.gui_label(Rectangle::new(client.x, y, LABEL_WIDTH, 20.0), label);
+    d.gui_slider_bar(bounds, "", &value_text, value, min, max);
+    *value != old_value
+}
+
+/// Toggle `editing` between `field` and nothing, matching raygui's edit mode convention
+fn toggle_edit<T: PartialEq>(editing: &mut Option<T>, field: T) {
+    *editing = if editing.as_ref() == Some(&field) { None } else { Some(field) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SizeField {
+    Width,
+    Height,
+}
+
+/// Width and height inputs, in either pixels or a percentage of the original size
+struct SizeFields {
+    old_width: i32,
+    old_height: i32,
+    width: i32,
+    height: i32,
+    is_percent: bool,
+    editing: Option<SizeField>,
+}
+
+impl SizeFields {
+    const MAX_SIZE: i32 = 16384;
+    const MAX_PERCENT: i32 = 1000;
+    /// Vertical space taken by [`SizeFields::update`]
+    const HEIGHT: f32 = 90.0;
+
+    fn new(old_width: i32, old_height: i32) -> Self {
+        Self {
+            old_width,
+            old_height,
+            width: old_width,
+            height: old_height,
+            is_percent: false,
+            editing: None,
+        }
+    }
+
+    #[inline]
+    fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    /// The requested size in pixels, never more than [`Self::MAX_SIZE`]
+    fn pixel_size(&self) -> (u32, u32) {
+        let (width, height) = if self.is_percent {
+            (self.old_width*self.width/100, self.old_height*self.height/100)
+        } else {
+            (self.width, self.height)
+        };
+        (width.clamp(1, Self::MAX_SIZE) as u32, height.clamp(1, Self::MAX_SIZE) as u32)
+    }
+
+    /// Draw the width, height and unit rows with their labels left of `x`.
+    /// If `keep_aspect`, editing one dimension updates the other to match.
+    fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, x: f32, y: f32, keep_aspect: bool) {
+        // a percentage can't take a side past the largest size either
+        let (max_width, max_height) = if self.is_percent {
+            (Self::MAX_PERCENT.min(Self::MAX_SIZE*100/self.old_width.max(1)), Self::MAX_PERCENT.min(Self::MAX_SIZE*100/self.old_height.max(1)))
+        } else {
+            (Self::MAX_SIZE, Self::MAX_SIZE)
+        };
+        let field_rec = |row: f32| Rectangle::new(x, y + row*30.0, 100.0, 24.0);
+        let (old_width, old_height) = (self.width, self.height);
+
+        if d.gui_value_box(field_rec(0.0), "Width ", &mut self.width, 1, max_width, self.editing == Some(SizeField::Width)) {
+            toggle_edit(&mut self.editing, SizeField::Width);
+        }
+        if d.gui_value_box(field_rec(1.0), "Height ", &mut self.height, 1, max_height, self.editing == Some(SizeField::Height)) {
+            toggle_edit(&mut self.editing, SizeField::Height);
+        }
+
+        if keep_aspect {
+            if self.is_percent {
+                if self.width != old_width {
+                    self.height = self.width;
+                } else if self.height != old_height {
+                    self.width = self.height;
+                }
+            } else if self.width != old_width {
+                self.height = (self.width*self.old_height/self.old_width).max(1);
+            } else if self.height != old_height {
+                self.width = (self.height*self.old_width/self.old_height).max(1);
+            }
+        }
+
+        let mut units = self.is_percent as i32;
+        d.gui_toggle_group(Rectangle::new(x, y + 60.0, 50.0, 24.0), "px;%", &mut units);
+        if (units != 0) != self.is_percent {
+            let (width, height) = self.pixel_size();
+            self.is_percent = units != 0;
+            (self.width, self.height) = if self.is_percent {
+                (width as i32*100/self.old_width, height as i32*100/self.old_height)
+            } else {
+                (width as i32, height as i32)
+            };
+        }
+    }
+}
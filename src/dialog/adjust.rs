@@ -0,0 +1,204 @@
+use raylib::prelude::*;
+use crate::{frame::RaylibFrameMode, raster::adjust::{Adjustment, curve_lut}};
+use super::{DialogResult, Preview, confirm_buttons, slider, window};
+
+/// Editor for the parameters of an [`Adjustment`], with a live preview on the canvas
+pub struct AdjustDialog {
+    adjustment: Adjustment,
+    is_preview: bool,
+    is_preview_stale: bool,
+    /// Which tonal range the color balance sliders are editing
+    tonal_range: i32,
+    /// Index of the curve point being dragged
+    dragging: Option<usize>,
+}
+
+impl AdjustDialog {
+    const GRAPH_SIZE: f32 = 200.0;
+    const POINT_RADIUS: f32 = 4.0;
+
+    pub fn new(adjustment: Adjustment) -> Self {
+        Self {
+            adjustment,
+            is_preview: true,
+            is_preview_stale: true,
+            tonal_range: 1,
+            dragging: None,
+        }
+    }
+
+    /// Adjustments with their parameters at the neutral defaults
+    pub fn hue_saturation() -> Self {
+        Self::new(Adjustment::HueSaturation { hue: 0.0, saturation: 0.0, lightness: 0.0 })
+    }
+
+    pub fn brightness_contrast() -> Self {
+        Self::new(Adjustment::BrightnessContrast { brightness: 0.0, contrast: 0.0 })
+    }
+
+    pub fn levels() -> Self {
+        Self::new(Adjustment::Levels { in_black: 0, in_white: 255, gamma: 1.0, out_black: 0, out_white: 255 })
+    }
+
+    pub fn curves() -> Self {
+        Self::new(Adjustment::Curves { points: vec![(0, 0), (255, 255)] })
+    }
+
+    pub fn posterize() -> Self {
+        Self::new(Adjustment::Posterize { levels: 4 })
+    }
+
+    pub fn color_balance() -> Self {
+        Self::new(Adjustment::ColorBalance { shadows: [0.0; 3], midtones: [0.0; 3], highlights: [0.0; 3] })
+    }
+
+    pub fn take_preview(&mut self) -> Option<Preview> {
+        std::mem::take(&mut self.is_preview_stale).then(|| if self.is_preview {
+            Preview::Adjusted(self.adjustment.clone())
+        } else {
+            Preview::Original
+        })
+    }
+
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<Adjustment> {
+        let height = if matches!(self.adjustment, Adjustment::Curves { .. }) { 330.0 } else { 270.0 };
+        let Some(client) = window(d, screen, 320.0, height, self.adjustment.name()) else {
+            return DialogResult::Cancel;
+        };
+
+        let row = |i: f32| client.y + i*26.0;
+        let mut is_changed = false;
+        match &mut self.adjustment {
+            Adjustment::HueSaturation { hue, saturation, lightness } => {
+                is_changed |= slider(d, client, row(0.0), "Hue", hue, -180.0, 180.0);
+                is_changed |= slider(d, client, row(1.0), "Saturation", saturation, -1.0, 1.0);
+                is_changed |= slider(d, client, row(2.0), "Lightness", lightness, -1.0, 1.0);
+            }
+
+            Adjustment::BrightnessContrast { brightness, contrast } => {
+                is_changed |= slider(d, client, row(0.0), "Brightness", brightness, -1.0, 1.0);
+                is_changed |= slider(d, client, row(1.0), "Contrast", contrast, -1.0, 1.0);
+            }
+
+            Adjustment::Levels { in_black, in_white, gamma, out_black, out_white } => {
+                let mut byte_slider = |i: f32, label: &str, value: &mut u8| {
+                    let mut v = *value as f32;
+                    let is_changed = slider(d, client, row(i), label, &mut v, 0.0, 255.0);
+                    *value = v.round() as u8;
+                    is_changed
+                };
+                is_changed |= byte_slider(0.0, "Input black", in_black);
+                is_changed |= byte_slider(1.0, "Input white", in_white);
+                is_changed |= byte_slider(3.0, "Output black", out_black);
+                is_changed |= byte_slider(4.0, "Output white", out_white);
+                is_changed |= slider(d, client, row(2.0), "Gamma", gamma, 0.1, 10.0);
+            }
+
+            Adjustment::Curves { points } => {
+                let graph = Rectangle::new(client.x + (client.width - Self::GRAPH_SIZE)*0.5, row(0.0), Self::GRAPH_SIZE, Self::GRAPH_SIZE);
+                is_changed |= Self::curve_editor(d, graph, points, &mut self.dragging);
+            }
+
+            Adjustment::Posterize { levels } => {
+                let mut v = *levels as f32;
+                is_changed |= slider(d, client, row(0.0), "Levels", &mut v, 2.0, 32.0);
+                *levels = v.round() as u8;
+            }
+
+            Adjustment::ColorBalance { shadows, midtones, highlights } => {
+                d.gui_toggle_group(Rectangle::new(client.x, row(0.0), 96.0, 24.0), "Shadows;Midtones;Highlights", &mut self.tonal_range);
+                let range = match self.tonal_range {
+                    0 => shadows,
+                    1 => midtones,
+                    _ => highlights,
+                };
+                is_changed |= slider(d, client, row(1.0), "Cyan/Red", &mut range[0], -1.0, 1.0);
+                is_changed |= slider(d, client, row(2.0), "Magenta/Green", &mut range[1], -1.0, 1.0);
+                is_changed |= slider(d, client, row(3.0), "Yellow/Blue", &mut range[2], -1.0, 1.0);
+            }
+
+            Adjustment::Invert | Adjustment::Desaturate => {}
+        }
+
+        let was_preview = self.is_preview;
+        d.gui_check_box(Rectangle::new(client.x, client.y + client.height - 20.0, 16.0, 16.0), "Preview", &mut self.is_preview);
+        self.is_preview_stale |= (is_changed && self.is_preview) || was_preview != self.is_preview;
+
+        match confirm_buttons(d, client, "Apply") {
+            DialogResult::Confirm(()) => DialogResult::Confirm(self.adjustment.clone()),
+            DialogResult::Cancel => DialogResult::Cancel,
+            DialogResult::Pending => DialogResult::Pending,
+        }
+    }
+
+    /// Draggable control points over a plot of the curve; left click adds or moves, right click removes.
+    /// Returns whether the curve changed.
+    fn curve_editor(d: &mut RaylibFrameMode<RaylibHandle>, graph: Rectangle, points: &mut Vec<(u8, u8)>, dragging: &mut Option<usize>) -> bool {
+        let scale = graph.width/255.0;
+        let to_screen = |(x, y): (u8, u8)| Vector2::new(graph.x + x as f32*scale, graph.y + graph.height - y as f32*scale);
+        let to_curve = |p: Vector2| (
+            ((p.x - graph.x)/scale).round().clamp(0.0, 255.0) as u8,
+            ((graph.y + graph.height - p.y)/scale).round().clamp(0.0, 255.0) as u8,
+        );
+
+        let mouse_pos = d.get_mouse_position();
+        let hovered = points.iter().position(|&p| to_screen(p).distance_to(mouse_pos) <= Self::POINT_RADIUS*2.0);
+        let mut is_changed = false;
+
+        if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            if hovered.is_some() {
+                *dragging = hovered;
+            } else if graph.check_collision_point_rec(mouse_pos) {
+                let point = to_curve(mouse_pos);
+                let i = points.partition_point(|p| p.0 < point.0);
+                if points.get(i).is_none_or(|p| p.0 != point.0) {
+                    points.insert(i, point);
+                    *dragging = Some(i);
+                    is_changed = true;
+                }
+            }
+        } else if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) && points.len() > 2 && let Some(i) = hovered {
+            points.remove(i);
+            *dragging = None;
+            is_changed = true;
+        }
+
+        if let Some(i) = *dragging {
+            if d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+                let (mut x, y) = to_curve(mouse_pos);
+                // keep points in order without overlapping
+                if i > 0 {
+                    x = x.max(points[i - 1].0.saturating_add(1));
+                }
+                if i + 1 < points.len() {
+                    x = x.min(points[i + 1].0.saturating_sub(1));
+                }
+                if points[i] != (x, y) {
+                    points[i] = (x, y);
+                    is_changed = true;
+                }
+            } else {
+                *dragging = None;
+            }
+        }
+
+        d.draw_rectangle_rec(graph, Color::new(24, 24, 24, 255));
+        for i in 1..4 {
+            let t = i as f32*0.25;
+            d.draw_line_v(Vector2::new(graph.x + graph.width*t, graph.y), Vector2::new(graph.x + graph.width*t, graph.y + graph.height), Color::new(48, 48, 48, 255));
+            d.draw_line_v(Vector2::new(graph.x, graph.y + graph.height*t), Vector2::new(graph.x + graph.width, graph.y + graph.height*t), Color::new(48, 48, 48, 255));
+        }
+        d.draw_line_v(to_screen((0, 0)), to_screen((255, 255)), Color::new(64, 64, 64, 255));
+        let lut = curve_lut(points);
+        for x in 0..255u8 {
+            d.draw_line_v(to_screen((x, lut[x as usize])), to_screen((x + 1, lut[x as usize + 1])), Color::WHITE);
+        }
+        for (i, &p) in points.iter().enumerate() {
+            let color = if Some(i) == *dragging || Some(i) == hovered { Color::SKYBLUE } else { Color::WHITE };
+            d.draw_circle_v(to_screen(p), Self::POINT_RADIUS, color);
+        }
+        d.draw_rectangle_lines_ex(graph, 1.0, Color::GRAY);
+
+        is_changed
+    }
+}
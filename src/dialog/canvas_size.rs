@@ -0,0 +1,38 @@
+use raylib::prelude::*;
+use crate::{editor::art::Anchor, frame::RaylibFrameMode};
+use super::{DialogResult, SizeFields, confirm_buttons, window};
+
+pub struct CanvasSizeDialog {
+    size: SizeFields,
+    anchor: Anchor,
+}
+
+impl CanvasSizeDialog {
+    pub fn new(old_width: i32, old_height: i32) -> Self {
+        Self {
+            size: SizeFields::new(old_width, old_height),
+            anchor: Anchor::default(),
+        }
+    }
+
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<(u32, u32, Anchor)> {
+        let Some(client) = window(d, screen, 260.0, 250.0, "Canvas Size") else {
+            return DialogResult::Cancel;
+        };
+
+        self.size.update(d, client.x + 60.0, client.y, false);
+
+        let y = client.y + SizeFields::HEIGHT;
+        d.gui_label(Rectangle::new(client.x, y, 60.0, 24.0), "Anchor");
+        let mut anchor = self.anchor as i32;
+        d.gui_toggle_group(Rectangle::new(client.x + 60.0, y, 24.0, 24.0), " ; ; \n ; ; \n ; ; ", &mut anchor);
+        self.anchor = Anchor::ALL[anchor.clamp(0, 8) as usize];
+
+        let (width, height) = self.size.pixel_size();
+        match confirm_buttons(d, client, "Resize") {
+            DialogResult::Confirm(()) if !self.size.is_editing() => DialogResult::Confirm((width, height, self.anchor)),
+            DialogResult::Cancel => DialogResult::Cancel,
+            _ => DialogResult::Pending,
+        }
+    }
+}
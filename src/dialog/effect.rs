@@ -0,0 +1,120 @@
+use raylib::prelude::*;
+use crate::{frame::RaylibFrameMode, raster::effect::{Connectivity, Effect}};
+use super::{DialogResult, Preview, confirm_buttons, slider, window};
+
+/// Editor for the parameters of an [`Effect`], with a live preview on the canvas
+pub struct EffectDialog {
+    effect: Effect,
+    is_preview: bool,
+    is_preview_stale: bool,
+}
+
+impl EffectDialog {
+    pub fn new(effect: Effect) -> Self {
+        Self {
+            effect,
+            is_preview: true,
+            is_preview_stale: true,
+        }
+    }
+
+    pub fn box_blur() -> Self {
+        Self::new(Effect::BoxBlur { radius: 1 })
+    }
+
+    pub fn gaussian_blur() -> Self {
+        Self::new(Effect::GaussianBlur { sigma: 1.0 })
+    }
+
+    pub fn unsharp_mask() -> Self {
+        Self::new(Effect::UnsharpMask { sigma: 1.0, amount: 0.5, threshold: 0 })
+    }
+
+    pub fn outline(color: Color) -> Self {
+        Self::new(Effect::Outline { color: [color.r, color.g, color.b, color.a], is_inner: false, connectivity: Connectivity::Four })
+    }
+
+    pub fn drop_shadow(color: Color) -> Self {
+        Self::new(Effect::DropShadow { offset: (1, 1), color: [color.r, color.g, color.b, color.a], sigma: 0.0 })
+    }
+
+    pub fn remove_orphans() -> Self {
+        Self::new(Effect::RemoveOrphans { connectivity: Connectivity::Eight })
+    }
+
+    pub fn take_preview(&mut self) -> Option<Preview> {
+        std::mem::take(&mut self.is_preview_stale).then(|| if self.is_preview {
+            Preview::Filtered(self.effect.clone())
+        } else {
+            Preview::Original
+        })
+    }
+
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<Effect> {
+        let Some(client) = window(d, screen, 320.0, 230.0, self.effect.name()) else {
+            return DialogResult::Cancel;
+        };
+
+        let row = |i: f32| client.y + i*26.0;
+        let connectivity_toggle = |d: &mut RaylibFrameMode<RaylibHandle>, y: f32, connectivity: &mut Connectivity| {
+            let mut active = (*connectivity == Connectivity::Eight) as i32;
+            d.gui_toggle_group(Rectangle::new(client.x, y, 96.0, 24.0), "4-connected;8-connected", &mut active);
+            let new = if active == 0 { Connectivity::Four } else { Connectivity::Eight };
+            std::mem::replace(connectivity, new) != new
+        };
+
+        let mut is_changed = false;
+        match &mut self.effect {
+            Effect::BoxBlur { radius } => {
+                let mut v = *radius as f32;
+                is_changed |= slider(d, client, row(0.0), "Radius", &mut v, 1.0, 32.0);
+                *radius = v.round() as u32;
+            }
+
+            Effect::GaussianBlur { sigma } => {
+                is_changed |= slider(d, client, row(0.0), "Radius", sigma, 0.5, 32.0);
+            }
+
+            Effect::UnsharpMask { sigma, amount, threshold } => {
+                is_changed |= slider(d, client, row(0.0), "Radius", sigma, 0.5, 32.0);
+                is_changed |= slider(d, client, row(1.0), "Amount", amount, 0.0, 5.0);
+                let mut v = *threshold as f32;
+                is_changed |= slider(d, client, row(2.0), "Threshold", &mut v, 0.0, 255.0);
+                *threshold = v.round() as u8;
+            }
+
+            Effect::Outline { is_inner, connectivity, .. } => {
+                let mut placement = *is_inner as i32;
+                d.gui_toggle_group(Rectangle::new(client.x, row(0.0), 96.0, 24.0), "Outer;Inner", &mut placement);
+                is_changed |= (placement != 0) != *is_inner;
+                *is_inner = placement != 0;
+                is_changed |= connectivity_toggle(d, row(1.0), connectivity);
+            }
+
+            Effect::DropShadow { offset: (x, y), color, sigma } => {
+                let (mut fx, mut fy, mut opacity) = (*x as f32, *y as f32, color[3] as f32/255.0);
+                is_changed |= slider(d, client, row(0.0), "Offset X", &mut fx, -32.0, 32.0);
+                is_changed |= slider(d, client, row(1.0), "Offset Y", &mut fy, -32.0, 32.0);
+                is_changed |= slider(d, client, row(2.0), "Blur", sigma, 0.0, 16.0);
+                is_changed |= slider(d, client, row(3.0), "Opacity", &mut opacity, 0.0, 1.0);
+                (*x, *y, color[3]) = (fx.round() as i32, fy.round() as i32, (opacity*255.0).round() as u8);
+            }
+
+            Effect::RemoveOrphans { connectivity } => {
+                is_changed |= connectivity_toggle(d, row(0.0), connectivity);
+            }
+
+            Effect::EdgeDetect => {}
+        }
+
+        let was_preview = self.is_preview;
+        d.gui_check_box(Rectangle::new(client.x, client.y + client.height - 20.0, 16.0, 16.0), "Preview", &mut self.is_preview);
+        self.is_preview_stale |= (is_changed && self.is_preview) || was_preview != self.is_preview;
+
+        match confirm_buttons(d, client, "Apply") {
+            DialogResult::Confirm(()) => DialogResult::Confirm(self.effect.clone()),
+            DialogResult::Cancel => DialogResult::Cancel,
+            DialogResult::Pending => DialogResult::Pending,
+        }
+    }
+}
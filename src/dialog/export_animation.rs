@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+use raylib::prelude::*;
+use crate::{codec::{AnimationFormat, gif::GifOptions}, frame::RaylibFrameMode};
+use super::{DialogResult, confirm_buttons, window};
+
+/// Options for writing every frame to an animated GIF or PNG
+pub struct ExportAnimationDialog {
+    path: PathBuf,
+    is_gif: bool,
+    is_dithered: bool,
+    loop_count: i32,
+    is_editing: bool,
+}
+
+impl ExportAnimationDialog {
+    /// The format is chosen by the extension of `path`
+    pub fn new(path: PathBuf) -> Self {
+        let is_gif = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+        Self {
+            path,
+            is_gif,
+            is_dithered: GifOptions::default().is_dithered,
+            loop_count: 0,
+            is_editing: false,
+        }
+    }
+
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<(PathBuf, AnimationFormat, u32)> {
+        let title = if self.is_gif { "Export GIF" } else { "Export APNG" };
+        let Some(client) = window(d, screen, 260.0, 150.0, title) else {
+            return DialogResult::Cancel;
+        };
+
+        if d.gui_value_box(Rectangle::new(client.x + 60.0, client.y, 80.0, 24.0), "Loops ", &mut self.loop_count, 0, 65535, self.is_editing) {
+            self.is_editing = !self.is_editing;
+        }
+        d.gui_label(Rectangle::new(client.x + 148.0, client.y, 100.0, 24.0), "(0 is forever)");
+
+        if self.is_gif {
+            d.gui_check_box(Rectangle::new(client.x + 60.0, client.y + 34.0, 16.0, 16.0), "Dither", &mut self.is_dithered);
+        }
+
+        match confirm_buttons(d, client, "Export") {
+            DialogResult::Confirm(()) if !self.is_editing => {
+                let format = if self.is_gif {
+                    AnimationFormat::Gif(GifOptions { is_dithered: self.is_dithered })
+                } else {
+                    AnimationFormat::Apng
+                };
+                DialogResult::Confirm((self.path.clone(), format, self.loop_count.max(0) as u32))
+            }
+            DialogResult::Cancel => DialogResult::Cancel,
+            _ => DialogResult::Pending,
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+use raylib::prelude::*;
+use crate::{codec::format::{EncodeOption, EncodeOptions, Format}, frame::RaylibFrameMode};
+use super::{DialogResult, confirm_buttons, slider, window};
+
+/// Options for saving in a format that has any, like JPEG's quality
+pub struct ExportImageDialog {
+    path: PathBuf,
+    format: &'static dyn Format,
+    quality: f32,
+    is_lossless: bool,
+}
+
+impl ExportImageDialog {
+    /// Starts from the options the document was last saved with
+    pub fn new(path: PathBuf, format: &'static dyn Format, options: EncodeOptions) -> Self {
+        Self {
+            path,
+            format,
+            quality: options.quality as f32,
+            is_lossless: options.is_lossless,
+        }
+    }
+
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<(PathBuf, EncodeOptions)> {
+        let Some(client) = window(d, screen, 300.0, 120.0, &format!("Export {}", self.format.name())) else {
+            return DialogResult::Cancel;
+        };
+
+        let options = self.format.options();
+        let mut y = client.y;
+        if options.contains(&EncodeOption::Lossless) {
+            d.gui_check_box(Rectangle::new(client.x, y + 2.0, 16.0, 16.0), "Lossless", &mut self.is_lossless);
+            y += 30.0;
+        }
+        // the quality goes unused when lossless
+        let is_lossy = !options.contains(&EncodeOption::Lossless) || !self.is_lossless;
+        if options.contains(&EncodeOption::Quality) && is_lossy {
+            slider(d, client, y, "Quality", &mut self.quality, 1.0, 100.0);
+        }
+
+        confirm_buttons(d, client, "Export").map(|()| {
+            let options = EncodeOptions {
+                quality: self.quality.round() as u8,
+                is_lossless: self.is_lossless,
+            };
+            (self.path.clone(), options)
+        })
+    }
+}
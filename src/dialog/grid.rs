@@ -0,0 +1,70 @@
+use raylib::prelude::*;
+use crate::{editor::art::GridSettings, frame::RaylibFrameMode};
+use super::{DialogResult, confirm_buttons, slider, toggle_edit, window};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Width,
+    Height,
+    OffsetX,
+    OffsetY,
+}
+
+/// Pixel grid threshold and the size, offset and color of the custom grid
+pub struct GridDialog {
+    grid: GridSettings,
+    editing: Option<Field>,
+}
+
+impl GridDialog {
+    const MAX_SIZE: i32 = 4096;
+
+    pub fn new(grid: GridSettings) -> Self {
+        Self {
+            grid,
+            editing: None,
+        }
+    }
+
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<GridSettings> {
+        let Some(client) = window(d, screen, 320.0, 380.0, "Grid") else {
+            return DialogResult::Cancel;
+        };
+
+        let row = |i: f32| client.y + i*28.0;
+        let grid = &mut self.grid;
+
+        d.gui_check_box(Rectangle::new(client.x, row(0.0) + 4.0, 16.0, 16.0), "Pixel grid", &mut grid.is_pixel_grid_enabled);
+        slider(d, client, row(1.0), "From zoom", &mut grid.pixel_grid_min_zoom, 2.0, 32.0);
+        grid.pixel_grid_min_zoom = grid.pixel_grid_min_zoom.round();
+
+        d.gui_check_box(Rectangle::new(client.x, row(2.0) + 4.0, 16.0, 16.0), "Grid", &mut grid.is_grid_enabled);
+        let fields = [
+            (Field::Width, "Width ", &mut grid.width, 1, Self::MAX_SIZE),
+            (Field::Height, "Height ", &mut grid.height, 1, Self::MAX_SIZE),
+            (Field::OffsetX, "Offset X ", &mut grid.offset_x, -Self::MAX_SIZE, Self::MAX_SIZE),
+            (Field::OffsetY, "Offset Y ", &mut grid.offset_y, -Self::MAX_SIZE, Self::MAX_SIZE),
+        ];
+        for (i, (field, label, value, min, max)) in fields.into_iter().enumerate() {
+            let bounds = Rectangle::new(client.x + 100.0, row(3.0 + i as f32), 100.0, 24.0);
+            if d.gui_value_box(bounds, label, value, min, max, self.editing == Some(field)) {
+                toggle_edit(&mut self.editing, field);
+            }
+        }
+
+        let channels = [("Red", &mut grid.color.r), ("Green", &mut grid.color.g), ("Blue", &mut grid.color.b), ("Opacity", &mut grid.color.a)];
+        for (i, (label, channel)) in channels.into_iter().enumerate() {
+            let mut value = *channel as f32;
+            if slider(d, client, row(7.0 + i as f32), label, &mut value, 0.0, 255.0) {
+                *channel = value.round() as u8;
+            }
+        }
+        d.draw_rectangle_rec(Rectangle::new(client.x + client.width - 24.0, row(2.0), 24.0, 24.0), grid.color);
+
+        match confirm_buttons(d, client, "OK") {
+            DialogResult::Confirm(()) if self.editing.is_none() => DialogResult::Confirm(self.grid),
+            DialogResult::Cancel => DialogResult::Cancel,
+            _ => DialogResult::Pending,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use raylib::prelude::*;
+use crate::{frame::RaylibFrameMode, raster::resample::{Filter, Scaler}};
+use super::{DialogResult, SizeFields, confirm_buttons, window};
+
+pub struct ImageSizeDialog {
+    size: SizeFields,
+    keep_aspect: bool,
+    scaler: i32,
+}
+
+impl ImageSizeDialog {
+    /// Matches the order of the toggle group labels
+    const SCALERS: [Scaler; 6] = [
+        Scaler::Filter(Filter::Nearest),
+        Scaler::Filter(Filter::Bilinear),
+        Scaler::Filter(Filter::Bicubic),
+        Scaler::Filter(Filter::Lanczos),
+        Scaler::Scale2x,
+        Scaler::Hq2x,
+    ];
+
+    pub fn new(old_width: i32, old_height: i32) -> Self {
+        Self {
+            size: SizeFields::new(old_width, old_height),
+            keep_aspect: true,
+            scaler: 0,
+        }
+    }
+
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<(u32, u32, Scaler)> {
+        let Some(client) = window(d, screen, 300.0, 250.0, "Image Size") else {
+            return DialogResult::Cancel;
+        };
+
+        self.size.update(d, client.x + 60.0, client.y, self.keep_aspect);
+
+        let y = client.y + SizeFields::HEIGHT;
+        d.gui_check_box(Rectangle::new(client.x + 60.0, y, 16.0, 16.0), "Keep aspect ratio", &mut self.keep_aspect);
+
+        d.gui_label(Rectangle::new(client.x, y + 26.0, 60.0, 24.0), "Filter");
+        d.gui_toggle_group(Rectangle::new(client.x + 60.0, y + 26.0, 70.0, 24.0), "Nearest;Bilinear;Bicubic\nLanczos;Scale2x;hq2x", &mut self.scaler);
+
+        let (width, height) = self.size.pixel_size();
+        match confirm_buttons(d, client, "Scale") {
+            DialogResult::Confirm(()) if !self.size.is_editing() => {
+                let scaler = Self::SCALERS[self.scaler.clamp(0, Self::SCALERS.len() as i32 - 1) as usize];
+                DialogResult::Confirm((width, height, scaler))
+            }
+            DialogResult::Cancel => DialogResult::Cancel,
+            _ => DialogResult::Pending,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::convert::Infallible;
+use raylib::prelude::*;
+use crate::{frame::RaylibFrameMode, keymap::{Command, Keymap}};
+use super::{DialogResult, window};
+
+/// Every command and what it's bound to, with conflicting ones in red and anything wrong with the
+/// config file listed underneath
+pub struct KeymapDialog {
+    /// Name, bindings and whether it conflicts with another command
+    rows: Vec<(&'static str, String, bool)>,
+    problems: Vec<String>,
+    path: String,
+    scroll: f32,
+}
+
+impl KeymapDialog {
+    const ROW_HEIGHT: f32 = 18.0;
+    const FONT_SIZE: i32 = 10;
+    /// Most problems listed before the rest are cut off
+    const MAX_PROBLEMS: usize = 6;
+
+    pub fn new(keymap: &Keymap) -> Self {
+        let rows = Command::ALL.iter()
+            .map(|&command| {
+                let bindings = keymap.bindings(command).map(|binding| binding.to_string()).collect::<Vec<_>>();
+                let bindings = if bindings.is_empty() { "(unbound)".to_string() } else { bindings.join(", ") };
+                (command.name(), bindings, keymap.is_conflicting(command))
+            })
+            .collect();
+        Self {
+            rows,
+            problems: keymap.problems().to_vec(),
+            path: keymap.path().map_or_else(|| "no config directory".to_string(), |path| path.display().to_string()),
+            scroll: 0.0,
+        }
+    }
+
+    /// Never confirms; it's only there to be read
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<Infallible> {
+        let height = (screen.height - 80.0).clamp(200.0, 640.0);
+        let Some(client) = window(d, screen, 480.0, height, "Keymap") else {
+            return DialogResult::Cancel;
+        };
+
+        d.draw_text(&self.path, client.x as i32, client.y as i32, Self::FONT_SIZE, Color::GRAY);
+
+        let problem_count = self.problems.len().min(Self::MAX_PROBLEMS);
+        let list = Rectangle {
+            y: client.y + Self::ROW_HEIGHT,
+            height: client.height - Self::ROW_HEIGHT*(1.0 + problem_count as f32),
+            ..client
+        };
+
+        let max_scroll = (self.rows.len() as f32*Self::ROW_HEIGHT - list.height).max(0.0);
+        if list.check_collision_point_rec(d.get_mouse_position()) {
+            self.scroll -= d.get_mouse_wheel_move()*Self::ROW_HEIGHT*3.0;
+        }
+        self.scroll = self.scroll.clamp(0.0, max_scroll);
+
+        // only rows entirely inside the list are drawn, so nothing spills over the window
+        let first = (self.scroll/Self::ROW_HEIGHT).ceil() as usize;
+        for (i, (name, bindings, is_conflicting)) in self.rows.iter().enumerate().skip(first) {
+            let y = list.y + i as f32*Self::ROW_HEIGHT - self.scroll;
+            if y + Self::ROW_HEIGHT > list.y + list.height { break; }
+            let color = if *is_conflicting { Color::RED } else { Color::DARKGRAY };
+            d.draw_text(name, list.x as i32, y as i32 + 4, Self::FONT_SIZE, color);
+            d.draw_text(bindings, (list.x + list.width*0.45) as i32, y as i32 + 4, Self::FONT_SIZE, color);
+        }
+
+        for (i, problem) in self.problems.iter().take(problem_count).enumerate() {
+            let text = if i + 1 == Self::MAX_PROBLEMS && self.problems.len() > Self::MAX_PROBLEMS {
+                format!("...and {} more", self.problems.len() - i)
+            } else {
+                problem.clone()
+            };
+            let y = list.y + list.height + i as f32*Self::ROW_HEIGHT;
+            d.draw_text(&text, client.x as i32, y as i32 + 4, Self::FONT_SIZE, Color::RED);
+        }
+
+        DialogResult::Pending
+    }
+}
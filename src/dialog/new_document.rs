@@ -0,0 +1,95 @@
+use raylib::prelude::*;
+use crate::frame::RaylibFrameMode;
+use super::{DialogResult, SizeField, confirm_buttons, toggle_edit, window};
+
+/// Size, background and saved sizes for a new document
+pub struct NewDocumentDialog {
+    width: i32,
+    height: i32,
+    /// Transparent, white, or `color`
+    background: i32,
+    /// The brush color when the dialog was opened
+    color: Color,
+    presets: Vec<[u32; 2]>,
+    editing: Option<SizeField>,
+}
+
+impl NewDocumentDialog {
+    const MAX_SIZE: i32 = 16384;
+    const PRESET_COLUMNS: usize = 4;
+    const PRESET_WIDTH: f32 = 80.0;
+    /// Most presets shown; saving more drops the oldest
+    const MAX_PRESETS: usize = 12;
+
+    pub fn new(width: i32, height: i32, color: Color, presets: Vec<[u32; 2]>) -> Self {
+        Self {
+            width,
+            height,
+            background: 0,
+            color,
+            presets,
+            editing: None,
+        }
+    }
+
+    /// Confirms with the size, the background color and the presets as they were left
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<(u32, u32, Color, Vec<[u32; 2]>)> {
+        let preset_rows = Self::MAX_PRESETS.div_ceil(Self::PRESET_COLUMNS);
+        let Some(client) = window(d, screen, 360.0, 200.0 + preset_rows as f32*28.0, "New Document") else {
+            return DialogResult::Cancel;
+        };
+
+        let fields = [
+            (SizeField::Width, "Width ", &mut self.width),
+            (SizeField::Height, "Height ", &mut self.height),
+        ];
+        for (i, (field, label, value)) in fields.into_iter().enumerate() {
+            let bounds = Rectangle::new(client.x + 60.0, client.y + i as f32*30.0, 100.0, 24.0);
+            if d.gui_value_box(bounds, label, value, 1, Self::MAX_SIZE, self.editing == Some(field)) {
+                toggle_edit(&mut self.editing, field);
+            }
+        }
+
+        let y = client.y + 66.0;
+        d.gui_label(Rectangle::new(client.x, y, 60.0, 24.0), "Background");
+        d.gui_toggle_group(Rectangle::new(client.x + 70.0, y, 80.0, 24.0), "Transparent;White;Color", &mut self.background);
+        d.draw_rectangle_rec(Rectangle::new(client.x + 70.0 + 3.0*82.0, y + 2.0, 20.0, 20.0), self.color);
+
+        // a preset fills in the size; right clicking one forgets it
+        let y = y + 36.0;
+        d.gui_label(Rectangle::new(client.x, y, 60.0, 24.0), "Presets");
+        let mut removed = None;
+        for (i, &[width, height]) in self.presets.iter().enumerate().take(Self::MAX_PRESETS) {
+            let (row, col) = (i/Self::PRESET_COLUMNS, i % Self::PRESET_COLUMNS);
+            let bounds = Rectangle::new(client.x + col as f32*(Self::PRESET_WIDTH + 4.0), y + 28.0 + row as f32*28.0, Self::PRESET_WIDTH, 24.0);
+            if d.gui_button(bounds, &format!("{width}x{height}")) {
+                (self.width, self.height) = (width as i32, height as i32);
+                self.editing = None;
+            } else if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) && bounds.check_collision_point_rec(d.get_mouse_position()) {
+                removed = Some(i);
+            }
+        }
+        if let Some(i) = removed {
+            self.presets.remove(i);
+        }
+
+        let size = [self.width.max(1) as u32, self.height.max(1) as u32];
+        if d.gui_button(Rectangle::new(client.x + client.width - 100.0, y, 100.0, 24.0), "Save Preset") && !self.presets.contains(&size) {
+            if self.presets.len() == Self::MAX_PRESETS {
+                self.presets.remove(0);
+            }
+            self.presets.push(size);
+        }
+
+        let background = match self.background {
+            0 => Color::BLANK,
+            1 => Color::WHITE,
+            _ => self.color,
+        };
+        match confirm_buttons(d, client, "Create") {
+            DialogResult::Confirm(()) if self.editing.is_none() => DialogResult::Confirm((size[0], size[1], background, std::mem::take(&mut self.presets))),
+            DialogResult::Cancel => DialogResult::Cancel,
+            _ => DialogResult::Pending,
+        }
+    }
+}
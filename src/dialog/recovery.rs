@@ -0,0 +1,70 @@
+use std::time::SystemTime;
+use raylib::prelude::*;
+use crate::{frame::RaylibFrameMode, recovery::Recoverable};
+use super::{DialogResult, window};
+
+/// How long ago `time` was, roughly
+fn time_ago(time: SystemTime) -> String {
+    let seconds = SystemTime::now().duration_since(time).map_or(0, |elapsed| elapsed.as_secs());
+    match seconds {
+        ..60 => "just now".to_string(),
+        ..3600 => format!("{} min ago", seconds/60),
+        ..86400 => format!("{} h ago", seconds/3600),
+        _ => format!("{} days ago", seconds/86400),
+    }
+}
+
+/// Offered at launch when copies were left behind by a crash
+pub struct RecoveryDialog {
+    list: Vec<Recoverable>,
+}
+
+impl RecoveryDialog {
+    const ROW_HEIGHT: f32 = 28.0;
+    /// Most copies shown at once; the rest show up after dealing with these
+    const MAX_ROWS: usize = 8;
+
+    pub fn new(list: Vec<Recoverable>) -> Self {
+        Self {
+            list,
+        }
+    }
+
+    /// Confirms with the copy to restore; closing leaves the rest for next time
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<Recoverable> {
+        let rows = self.list.len().min(Self::MAX_ROWS);
+        let Some(client) = window(d, screen, 420.0, 110.0 + rows as f32*Self::ROW_HEIGHT, "Recover Unsaved Work") else {
+            return DialogResult::Cancel;
+        };
+
+        d.gui_label(Rectangle::new(client.x, client.y, client.width, 20.0), "The program didn't close properly. These copies were kept:");
+
+        let mut restored = None;
+        let mut discarded = None;
+        for (i, recoverable) in self.list.iter().take(rows).enumerate() {
+            let y = client.y + 28.0 + i as f32*Self::ROW_HEIGHT;
+            d.gui_label(Rectangle::new(client.x, y, 200.0, 24.0), &recoverable.name());
+            d.gui_label(Rectangle::new(client.x + 200.0, y, 80.0, 24.0), &time_ago(recoverable.modified));
+            if d.gui_button(Rectangle::new(client.x + client.width - 140.0, y, 66.0, 24.0), "Restore") {
+                restored = Some(i);
+            } else if d.gui_button(Rectangle::new(client.x + client.width - 66.0, y, 66.0, 24.0), "Discard") {
+                discarded = Some(i);
+            }
+        }
+
+        if let Some(i) = discarded {
+            self.list.remove(i).discard();
+            if self.list.is_empty() {
+                return DialogResult::Cancel;
+            }
+        }
+
+        if let Some(i) = restored {
+            DialogResult::Confirm(self.list.remove(i))
+        } else if d.gui_button(Rectangle::new(client.x + client.width - 80.0, client.y + client.height - 24.0, 80.0, 24.0), "Later") {
+            DialogResult::Cancel
+        } else {
+            DialogResult::Pending
+        }
+    }
+}
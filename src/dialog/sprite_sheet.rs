@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+use raylib::prelude::*;
+use crate::{codec::{Animation, sprite_sheet::{self, Layout, SheetOptions}}, frame::RaylibFrameMode, raster::Raster};
+use super::{DialogResult, confirm_buttons, slider, toggle_edit, window};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Columns,
+    Padding,
+    CellWidth,
+    CellHeight,
+    Spacing,
+    Duration,
+}
+
+/// Layout options for writing every frame to one image with JSON metadata
+pub struct ExportSheetDialog {
+    path: PathBuf,
+    layout: i32,
+    columns: i32,
+    padding: i32,
+    pivot: (f32, f32),
+    editing: Option<Field>,
+}
+
+impl ExportSheetDialog {
+    pub fn new(path: PathBuf, frame_count: usize) -> Self {
+        let options = SheetOptions::default();
+        Self {
+            path,
+            layout: 0,
+            columns: (frame_count as f32).sqrt().ceil().max(1.0) as i32,
+            padding: options.padding as i32,
+            pivot: options.pivot,
+            editing: None,
+        }
+    }
+
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<(PathBuf, SheetOptions)> {
+        let Some(client) = window(d, screen, 320.0, 250.0, "Export Sprite Sheet") else {
+            return DialogResult::Cancel;
+        };
+
+        let row = |i: f32| client.y + i*30.0;
+        d.gui_label(Rectangle::new(client.x, row(0.0), 70.0, 24.0), "Layout");
+        d.gui_toggle_group(Rectangle::new(client.x + 70.0, row(0.0), 56.0, 24.0), "Grid;Row;Column;Packed", &mut self.layout);
+
+        if self.layout == 0 && d.gui_value_box(Rectangle::new(client.x + 70.0, row(1.0), 80.0, 24.0), "Columns ", &mut self.columns, 1, 1024, self.editing == Some(Field::Columns)) {
+            toggle_edit(&mut self.editing, Field::Columns);
+        }
+        if d.gui_value_box(Rectangle::new(client.x + 70.0, row(2.0), 80.0, 24.0), "Padding ", &mut self.padding, 0, 256, self.editing == Some(Field::Padding)) {
+            toggle_edit(&mut self.editing, Field::Padding);
+        }
+        slider(d, client, row(3.0), "Pivot X", &mut self.pivot.0, 0.0, 1.0);
+        slider(d, client, row(4.0), "Pivot Y", &mut self.pivot.1, 0.0, 1.0);
+
+        match confirm_buttons(d, client, "Export") {
+            DialogResult::Confirm(()) if self.editing.is_none() => {
+                let layout = match self.layout {
+                    0 => Layout::Grid { columns: self.columns.max(1) as usize },
+                    1 => Layout::Row,
+                    2 => Layout::Column,
+                    _ => Layout::Packed,
+                };
+                let options = SheetOptions { layout, padding: self.padding.max(0) as usize, pivot: self.pivot };
+                DialogResult::Confirm((self.path.clone(), options))
+            }
+            DialogResult::Cancel => DialogResult::Cancel,
+            _ => DialogResult::Pending,
+        }
+    }
+}
+
+/// Cut an image without metadata into frames by a grid of cells
+pub struct SliceSheetDialog {
+    sheet: Raster,
+    cell_width: i32,
+    cell_height: i32,
+    spacing: i32,
+    duration: i32,
+    skip_empty: bool,
+    editing: Option<Field>,
+}
+
+impl SliceSheetDialog {
+    pub fn new(sheet: Raster, cell_width: i32, cell_height: i32) -> Self {
+        Self {
+            cell_width: cell_width.clamp(1, sheet.width().max(1) as i32),
+            cell_height: cell_height.clamp(1, sheet.height().max(1) as i32),
+            sheet,
+            spacing: 0,
+            duration: 100,
+            skip_empty: true,
+            editing: None,
+        }
+    }
+
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<Animation> {
+        let Some(client) = window(d, screen, 280.0, 260.0, "Slice Sprite Sheet") else {
+            return DialogResult::Cancel;
+        };
+
+        let fields = [
+            ("Cell width ", Field::CellWidth, &mut self.cell_width, 1, self.sheet.width() as i32),
+            ("Cell height ", Field::CellHeight, &mut self.cell_height, 1, self.sheet.height() as i32),
+            ("Spacing ", Field::Spacing, &mut self.spacing, 0, 256),
+            ("Duration (ms) ", Field::Duration, &mut self.duration, 1, 10000),
+        ];
+        for (i, (label, field, value, min, max)) in fields.into_iter().enumerate() {
+            let bounds = Rectangle::new(client.x + 110.0, client.y + i as f32*30.0, 80.0, 24.0);
+            if d.gui_value_box(bounds, label, value, min, max.max(min), self.editing == Some(field)) {
+                toggle_edit(&mut self.editing, field);
+            }
+        }
+        d.gui_check_box(Rectangle::new(client.x + 110.0, client.y + 124.0, 16.0, 16.0), "Skip empty cells", &mut self.skip_empty);
+
+        match confirm_buttons(d, client, "Slice") {
+            DialogResult::Confirm(()) if self.editing.is_none() => {
+                let animation = sprite_sheet::slice_by_grid(
+                    &self.sheet,
+                    self.cell_width.max(1) as usize,
+                    self.cell_height.max(1) as usize,
+                    self.spacing.max(0) as usize,
+                    self.skip_empty,
+                    self.duration.max(1) as u32,
+                );
+                if animation.frames.is_empty() {
+                    DialogResult::Pending
+                } else {
+                    DialogResult::Confirm(animation)
+                }
+            }
+            DialogResult::Cancel => DialogResult::Cancel,
+            _ => DialogResult::Pending,
+        }
+    }
+}
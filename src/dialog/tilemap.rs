@@ -0,0 +1,72 @@
+use raylib::prelude::*;
+use crate::frame::RaylibFrameMode;
+use super::{DialogResult, confirm_buttons, toggle_edit, window};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    TileWidth,
+    TileHeight,
+    Columns,
+    Rows,
+}
+
+/// Tile size to cut the canvas into, and how many tiles across and down the new map is
+pub struct NewTilemapDialog {
+    tile_width: i32,
+    tile_height: i32,
+    columns: i32,
+    rows: i32,
+    editing: Option<Field>,
+}
+
+impl NewTilemapDialog {
+    const MAX_TILE_SIZE: i32 = 1024;
+    const MAX_TILES: i32 = 1024;
+    /// Widest or tallest the map can be as an image, the same as the largest canvas
+    const MAX_SIZE: i32 = 16384;
+
+    pub fn new() -> Self {
+        Self {
+            tile_width: 16,
+            tile_height: 16,
+            columns: 32,
+            rows: 32,
+            editing: None,
+        }
+    }
+
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<(u32, u32, u32, u32)> {
+        let Some(client) = window(d, screen, 260.0, 190.0, "New Tilemap") else {
+            return DialogResult::Cancel;
+        };
+
+        let max_columns = Self::MAX_TILES.min(Self::MAX_SIZE/self.tile_width.max(1));
+        let max_rows = Self::MAX_TILES.min(Self::MAX_SIZE/self.tile_height.max(1));
+        let fields = [
+            (Field::TileWidth, "Tile width ", &mut self.tile_width, Self::MAX_TILE_SIZE),
+            (Field::TileHeight, "Tile height ", &mut self.tile_height, Self::MAX_TILE_SIZE),
+            (Field::Columns, "Columns ", &mut self.columns, max_columns),
+            (Field::Rows, "Rows ", &mut self.rows, max_rows),
+        ];
+        for (i, (field, label, value, max)) in fields.into_iter().enumerate() {
+            let bounds = Rectangle::new(client.x + 90.0, client.y + i as f32*30.0, 100.0, 24.0);
+            if d.gui_value_box(bounds, label, value, 1, max, self.editing == Some(field)) {
+                toggle_edit(&mut self.editing, field);
+            }
+        }
+        // bigger tiles may have left too many of them
+        self.columns = self.columns.min(Self::MAX_SIZE/self.tile_width.max(1));
+        self.rows = self.rows.min(Self::MAX_SIZE/self.tile_height.max(1));
+
+        match confirm_buttons(d, client, "Create") {
+            DialogResult::Confirm(()) if self.editing.is_none() => DialogResult::Confirm((
+                self.tile_width.max(1) as u32,
+                self.tile_height.max(1) as u32,
+                self.columns.max(1) as u32,
+                self.rows.max(1) as u32,
+            )),
+            DialogResult::Cancel => DialogResult::Cancel,
+            _ => DialogResult::Pending,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use raylib::prelude::*;
+use crate::frame::RaylibFrameMode;
+use super::{DialogResult, window};
+
+/// Something that would throw away the open document, held back until its changes are dealt with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intent {
+    /// Close every document and quit, asking about each in turn
+    Quit,
+    /// Close the active document
+    Close,
+    ImportFrames,
+}
+
+impl Intent {
+    /// Finishes "Save changes to ... before ..."
+    const fn description(self) -> &'static str {
+        match self {
+            Self::Quit => "quitting",
+            Self::Close => "closing",
+            Self::ImportFrames => "replacing its frames",
+        }
+    }
+}
+
+/// Asked before doing anything that would lose unsaved changes
+pub struct UnsavedChangesDialog {
+    intent: Intent,
+    /// The document's file name
+    name: String,
+}
+
+impl UnsavedChangesDialog {
+    pub fn new(intent: Intent, name: String) -> Self {
+        Self {
+            intent,
+            name,
+        }
+    }
+
+    /// Confirms with what was asked for and whether to save first; cancelling drops it
+    pub fn update(&mut self, d: &mut RaylibFrameMode<RaylibHandle>, screen: Rectangle) -> DialogResult<(Intent, bool)> {
+        const WIDTH: f32 = 80.0;
+        const HEIGHT: f32 = 24.0;
+        const GAP: f32 = 8.0;
+
+        let Some(client) = window(d, screen, 360.0, 100.0, "Unsaved Changes") else {
+            return DialogResult::Cancel;
+        };
+
+        d.gui_label(Rectangle::new(client.x, client.y, client.width, 20.0), &format!("Save changes to {} before {}?", self.name, self.intent.description()));
+
+        let y = client.y + client.height - HEIGHT;
+        let x = client.x + client.width - WIDTH;
+        if d.gui_button(Rectangle::new(x - (WIDTH + GAP)*2.0, y, WIDTH, HEIGHT), "Cancel") {
+            DialogResult::Cancel
+        } else if d.gui_button(Rectangle::new(x - WIDTH - GAP, y, WIDTH, HEIGHT), "Discard") {
+            DialogResult::Confirm((self.intent, false))
+        } else if d.gui_button(Rectangle::new(x, y, WIDTH, HEIGHT), "Save") {
+            DialogResult::Confirm((self.intent, true))
+        } else {
+            DialogResult::Pending
+        }
+    }
+}
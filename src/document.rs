@@ -0,0 +1,51 @@
+use std::path::{Path, PathBuf};
+use crate::{codec::format::EncodeOptions, editor::{art::ArtEditor, tilemap::TilemapEditor}, recovery::Autosave};
+
+/// One open file and everything being edited in it, shown in its own tab
+pub struct Document {
+    pub art_editor: ArtEditor,
+    /// The tilemap made from this document's tiles, if any
+    pub tilemap_editor: TilemapEditor,
+    pub is_tilemap_mode: bool,
+    /// Where it was last saved or opened from
+    pub path: Option<PathBuf>,
+    /// What it was last saved with, to save the same way again
+    pub encode_options: EncodeOptions,
+    /// Its own copy for recovery, so a crash loses no document
+    pub autosave: Autosave,
+}
+
+impl Document {
+    pub fn new(art_editor: ArtEditor, path: Option<PathBuf>) -> Self {
+        Self {
+            art_editor,
+            tilemap_editor: TilemapEditor::new(),
+            is_tilemap_mode: false,
+            path,
+            encode_options: EncodeOptions::default(),
+            autosave: Autosave::new(),
+        }
+    }
+
+    /// What to call it in its tab and when asking about it
+    pub fn name(&self) -> String {
+        self.path.as_deref()
+            .and_then(Path::file_name)
+            .map_or_else(|| "untitled".to_string(), |name| name.to_string_lossy().into_owned())
+    }
+
+    /// Whether closing it would lose changes to either the art or the tilemap
+    pub const fn is_modified(&self) -> bool {
+        self.art_editor.is_modified() || self.tilemap_editor.is_modified()
+    }
+
+    /// The tab's label, marked when there are unsaved changes
+    pub fn title(&self) -> String {
+        if self.is_modified() { format!("{}*", self.name()) } else { self.name() }
+    }
+
+    /// Let go of the document for good, along with its copy for recovery
+    pub fn close(self) {
+        self.autosave.clear();
+    }
+}
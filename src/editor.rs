@@ -1,13 +1,29 @@
 use raylib::prelude::*;
-use crate::{brush::Brush, frame::Frame};
+use crate::{brush::Brush, error, frame::Frame, keymap::Keymap};
 
 pub mod art;
 pub mod color;
+pub mod tilemap;
+
+/// Divide `area` into `count` side by side columns, as near to the same width as whole pixels allow
+pub fn columns(area: Rectangle, count: usize) -> Vec<Rectangle> {
+    let edge = |i: usize| area.x + (area.width*i as f32/count as f32).round();
+    (0..count)
+        .map(|i| Rectangle {
+            x: edge(i),
+            width: edge(i + 1) - edge(i),
+            ..area
+        })
+        .collect()
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EditorID {
     Art,
     Color,
+    Tilemap,
+    /// The strip of open documents, which isn't an editor but takes the mouse like one
+    Tabs,
 }
 
 pub trait Editor {
@@ -20,6 +36,7 @@ pub trait Editor {
     /// The editor is performing an action that still belongs to it even if the mouse exits its container.
     fn is_focused(&self) -> bool;
 
-    /// When awake, tick the editor as if it is either focused or can be
-    fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, brush: &mut Brush, viewport: Rectangle, frame: &mut Frame, is_awake: bool);
+    /// When awake, tick the editor as if it is either focused or can be.
+    /// Fails if the GPU can't hold or give back what the editor works on.
+    fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, keymap: &Keymap, brush: &mut Brush, viewport: Rectangle, frame: &mut Frame, is_awake: bool) -> error::Result<()>;
 }
@@ -1,80 +1,791 @@
-use std::path::Path;
+use std::{fs::File, io::{self, BufWriter}, path::Path};
 
-use crate::{brush::Brush, editor::Editor, frame::Frame};
+use crate::{brush::Brush, error::{self, Error}, codec::{Animation, AnimationFormat, apng, format::{self, EncodeOptions}, sprite_sheet::{self, SheetOptions, TagMeta}}, editor::{self, Editor}, frame::Frame, keymap::{Command, Keymap}, raster::{Raster, Rect, resample::{self, Scaler}}};
 use raylib::prelude::*;
 use amygui::prelude::*;
 
 mod flood_fill;
-use flood_fill::flood_fill;
+use flood_fill::{flood_fill, flood_fill_wrapping};
+
+mod transform;
+use transform::FreeTransform;
+
+mod animation;
+use animation::{AnimFrame, PlaybackMode, Timeline, TimelineCommand};
+
+mod tile_mode;
+use tile_mode::TileMode;
+
+mod overlay;
+use overlay::Overlay;
+pub use overlay::GridSettings;
+
+mod status_bar;
+
+mod view;
+pub use view::View;
+
+mod navigator;
+use navigator::Navigator;
+
+mod reference;
+use reference::{Reference, Response};
 
 enum Tool {
     Pen {
         pen_pos_prev: Option<(Vector2, Option<Vector2>)>,
     },
     Fill,
+    Select {
+        drag_start: Option<(i32, i32)>,
+    },
+    Transform(Box<FreeTransform>),
+}
+
+impl Tool {
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Pen { .. } => "Pen",
+            Self::Fill => "Fill",
+            Self::Select { .. } => "Select",
+            Self::Transform(_) => "Transform",
+        }
+    }
+}
+
+/// Which part of the old canvas stays fixed when changing the canvas size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// In reading order, matching a 3x3 grid
+    pub const ALL: [Anchor; 9] = [
+        Anchor::TopLeft,    Anchor::Top,    Anchor::TopRight,
+        Anchor::Left,       Anchor::Center, Anchor::Right,
+        Anchor::BottomLeft, Anchor::Bottom, Anchor::BottomRight,
+    ];
+
+    /// Position of the old canvas within the new one when the size changes by (`dw`, `dh`)
+    pub const fn offset(self, dw: i32, dh: i32) -> (i32, i32) {
+        let i = self as i32;
+        (dw*(i % 3)/2, dh*(i / 3)/2)
+    }
+}
+
+/// Copy a raster into a new RGBA8 image
+fn raster_to_image(raster: &Raster) -> Image {
+    let image = Image::gen_image_color(raster.width() as i32, raster.height() as i32, Color::BLANK);
+    let bytes = raster.as_bytes();
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), image.data.cast::<u8>(), bytes.len());
+    }
+    image
+}
+
+/// Copy an RGBA image's pixels
+fn image_to_raster(image: &Image) -> Raster {
+    let len = get_pixel_data_size(image.width, image.height, image.format()).try_into().unwrap();
+    let bytes = unsafe { std::slice::from_raw_parts(image.data.cast(), len) };
+    Raster::from_bytes(image.width as usize, image.height as usize, bytes)
+}
+
+/// Copy a canvas to the CPU, top row first
+fn download_canvas(canvas: &RenderTexture2D) -> error::Result<Raster> {
+    let mut image = canvas.load_image().map_err(Error::gpu)?;
+    image.flip_vertical();
+    Ok(image_to_raster(&image))
+}
+
+/// Read one pixel of a canvas back from the GPU, drawing it into `probe` so only that pixel is transferred
+fn read_pixel(rl: &mut RaylibHandle, thread: &RaylibThread, probe: &mut RenderTexture2D, canvas: &RenderTexture2D, x: i32, y: i32) -> error::Result<Color> {
+    {
+        let mut d = rl.begin_texture_mode(thread, probe);
+        d.clear_background(Color::BLANK);
+        // copy alpha as-is instead of blending it with the blank background
+        let mut d = d.begin_blend_mode(BlendMode::BLEND_ALPHA_PREMULTIPLY);
+        let source = Rectangle::new(x as f32, (canvas.texture.height - y) as f32, 1.0, -1.0);
+        let corners = [Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), Vector2::new(1.0, 1.0), Vector2::new(0.0, 1.0)];
+        d.draw_texture_quad_rec(canvas, source, corners, Color::WHITE);
+    }
+    Ok(probe.load_image().map_err(Error::gpu)?.get_color(0, 0))
+}
+
+/// Replace a canvas' contents, reallocating it if the dimensions differ
+fn upload_canvas(rl: &mut RaylibHandle, thread: &RaylibThread, canvas: &mut RenderTexture2D, raster: &Raster) -> error::Result<()> {
+    let (width, height) = (raster.width() as u32, raster.height() as u32);
+    if width != canvas.texture.width as u32 || height != canvas.texture.height as u32 {
+        *canvas = rl.load_render_texture(thread, width, height).map_err(Error::gpu)?;
+    }
+    let mut flipped = raster.clone();
+    flipped.flip_vertical();
+    canvas.update_texture(flipped.as_bytes()).map_err(Error::gpu)
+}
+
+/// A new canvas holding `raster`
+fn canvas_from_raster(rl: &mut RaylibHandle, thread: &RaylibThread, raster: &Raster) -> error::Result<RenderTexture2D> {
+    let mut canvas = rl.load_render_texture(thread, raster.width() as u32, raster.height() as u32).map_err(Error::gpu)?;
+    upload_canvas(rl, thread, &mut canvas, raster)?;
+    Ok(canvas)
+}
+
+/// Which keys do what, in parentheses after a label, leaving out unbound commands
+fn key_hint(keymap: &Keymap, actions: &[(Command, &str)]) -> String {
+    let hints = actions.iter()
+        .filter_map(|&(command, what)| keymap.bindings(command).next().map(|binding| format!("{binding} to {what}")))
+        .collect::<Vec<_>>();
+    if hints.is_empty() { String::new() } else { format!(" ({})", hints.join(", ")) }
+}
+
+/// One of the side by side views of the canvas, each with its own pan, zoom and rotation
+struct CanvasView {
+    view: View,
+    /// Where the canvas was last drawn, for commands that fit the view to it
+    viewport: Rectangle,
+    is_drag_panning: bool,
+    /// Angle of the mouse around the middle of the viewport and the view's rotation when an R+drag started
+    rotate_start: Option<(f32, f32)>,
+    /// Distance between two touches last update, while pinching
+    pinch_distance: Option<f32>,
+    /// Thumbnail of the whole canvas for finding the way around it
+    navigator: Navigator,
+    /// Center the canvas once it's known where the view goes
+    is_centering: bool,
+}
+
+impl CanvasView {
+    fn new(view: View) -> Self {
+        Self {
+            view,
+            viewport: Rectangle::new(0.0, 0.0, 0.0, 0.0),
+            is_drag_panning: false,
+            rotate_start: None,
+            pinch_distance: None,
+            navigator: Navigator::new(),
+            is_centering: false,
+        }
+    }
+
+    #[inline]
+    fn is_focused(&self) -> bool {
+        self.is_drag_panning || self.rotate_start.is_some() || self.navigator.is_dragging()
+    }
 }
 
 pub struct ArtEditor {
+    /// The canvas of the frame being edited
     canvas: RenderTexture2D,
     is_canvas_dirty: bool,
-    zoom_pow: i32,
-    pan: Vector2,
+    /// Whether the last update redrew the canvas, which may mean its contents changed
+    was_redrawn: bool,
+    /// Whether the document has changed since it was last saved or opened
+    is_modified: bool,
+    /// Every frame of the animation, in order
+    frames: Vec<AnimFrame>,
+    current_frame: usize,
+    timeline: Timeline,
+    is_timeline_dirty: bool,
+    /// Side by side views of the canvas, left to right
+    views: Vec<CanvasView>,
+    /// The view that gets input, which is the last one the mouse was over
+    active_view: usize,
     tool: Tool,
     is_erasing: bool,
-    is_drag_panning: bool,
+    /// Which edges strokes and fills wrap across
+    tile_mode: TileMode,
+    /// Grids and guides drawn over the canvas
+    overlay: Overlay,
+    /// Images to look at while painting, which are never saved or exported; the last is on top
+    references: Vec<Reference>,
+    is_status_dirty: bool,
+    /// The pixel the status bar was last drawn for
+    status_cursor: Option<(i32, i32)>,
+    /// 1x1 target for [`read_pixel`]
+    probe: Option<RenderTexture2D>,
+    selection: Option<Rect>,
+    /// The canvas before the effect currently being previewed
+    preview_original: Option<Raster>,
 }
 
 impl ArtEditor {
-    pub const fn new(canvas: RenderTexture2D) -> Self {
+    pub const MAX_VIEWS: usize = 4;
+
+    pub fn new(canvas: RenderTexture2D) -> Self {
         Self {
             canvas,
             is_canvas_dirty: true,
-            zoom_pow: 0,
-            pan: Vector2::zero(),
+            was_redrawn: false,
+            is_modified: false,
+            frames: vec![AnimFrame { canvas: None, duration: AnimFrame::DEFAULT_DURATION }],
+            current_frame: 0,
+            timeline: Timeline::default(),
+            is_timeline_dirty: true,
+            views: vec![CanvasView::new(View::default())],
+            active_view: 0,
             tool: Tool::Pen {
                 pen_pos_prev: None,
             },
             is_erasing: false,
-            is_drag_panning: false,
+            tile_mode: TileMode::Off,
+            overlay: Overlay::new(),
+            references: Vec::new(),
+            is_status_dirty: true,
+            status_cursor: None,
+            probe: None,
+            selection: None,
+            preview_original: None,
         }
     }
 
-    pub const fn set_pan(&mut self, pan: Vector2) {
-        self.pan = pan;
+    #[inline]
+    pub fn width(&self) -> i32 {
+        self.canvas.texture.width
     }
 
-    pub fn resize(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, new_width: u32, new_height: u32) {
-        if new_width == self.canvas.width() as u32 && new_height == self.canvas.height() as u32 { return; }
-        let old_canvas = std::mem::replace(&mut self.canvas, rl.load_render_texture(&thread, new_width, new_height).unwrap());
-        {
-            let mut d = rl.begin_texture_mode(thread, &mut self.canvas);
-            d.clear_background(Color::BLANK);
-            d.draw_texture_direct(&old_canvas, rrect(0, 0, old_canvas.width(), old_canvas.height()));
+    #[inline]
+    pub fn height(&self) -> i32 {
+        self.canvas.texture.height
+    }
+
+    /// The canvas of the frame being edited
+    #[inline]
+    pub const fn canvas(&self) -> &RenderTexture2D {
+        &self.canvas
+    }
+
+    #[inline]
+    pub const fn was_redrawn(&self) -> bool {
+        self.was_redrawn
+    }
+
+    /// Whether there are changes that would be lost without saving
+    #[inline]
+    pub const fn is_modified(&self) -> bool {
+        self.is_modified
+    }
+
+    #[inline]
+    pub const fn selection(&self) -> Option<Rect> {
+        self.selection
+    }
+
+    #[inline]
+    pub const fn grid(&self) -> &GridSettings {
+        &self.overlay.grid
+    }
+
+    pub fn set_grid(&mut self, grid: GridSettings) {
+        self.overlay.grid = grid;
+        self.is_canvas_dirty = true;
+    }
+
+    pub fn toggle_grid(&mut self) {
+        self.overlay.grid.is_grid_enabled = !self.overlay.grid.is_grid_enabled;
+        self.is_canvas_dirty = true;
+    }
+
+    pub fn toggle_guides(&mut self) {
+        self.overlay.is_showing_guides = !self.overlay.is_showing_guides;
+        self.is_canvas_dirty = true;
+    }
+
+    pub fn toggle_snapping(&mut self) {
+        self.overlay.is_snapping = !self.overlay.is_snapping;
+    }
+
+    pub fn clear_guides(&mut self) {
+        self.overlay.guides.clear();
+        self.is_canvas_dirty = true;
+    }
+
+    pub fn clear_selection(&mut self) {
+        if self.selection.is_some() {
+            self.selection = None;
             self.is_canvas_dirty = true;
         }
     }
 
-    pub fn save(&self, _rl: &mut RaylibHandle, _thread: &RaylibThread, path: &Path) -> Option<()> {
-        let mut image = self.canvas.load_image().unwrap();
-        image.flip_vertical();
-        image.export_image(path.to_str()?);
-        Some(())
+    /// Where the canvas is in the active view
+    #[inline]
+    pub fn view(&self) -> View {
+        self.views[self.active_view].view
     }
 
-    pub fn import(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &Path) -> Option<()> {
-        let imported = rl.load_texture(thread, path.to_str()?).ok()?;
-        if imported.width > self.canvas.width() || imported.height > self.canvas.height() {
-            let new_width  = imported.width .max(self.canvas.width ()).try_into().unwrap();
-            let new_height = imported.height.max(self.canvas.height()).try_into().unwrap();
-            self.resize(rl, thread, new_width, new_height);
+    pub fn set_view(&mut self, view: View) {
+        self.views[self.active_view].view = view;
+        self.is_canvas_dirty = true;
+        self.is_status_dirty = true;
+    }
+
+    /// Zoom and center the active view so the whole canvas is in it
+    pub fn zoom_to_fit(&mut self) {
+        let (width, height) = (self.width() as f32, self.height() as f32);
+        let canvas_view = &mut self.views[self.active_view];
+        canvas_view.view.fit(width, height, canvas_view.viewport);
+        self.is_canvas_dirty = true;
+        self.is_status_dirty = true;
+    }
+
+    /// Show one canvas pixel per screen pixel in the active view, keeping the middle of it where it is
+    pub fn zoom_to_actual_size(&mut self) {
+        let canvas_view = &mut self.views[self.active_view];
+        let pivot = view::pivot(canvas_view.viewport);
+        canvas_view.view.zoom_about(1.0, pivot, pivot);
+        self.is_canvas_dirty = true;
+        self.is_status_dirty = true;
+    }
+
+    pub fn reset_rotation(&mut self) {
+        self.views[self.active_view].view.rotation = 0.0;
+        self.is_canvas_dirty = true;
+    }
+
+    /// Open an image as a reference, in a panel over the top left of the active view
+    pub fn add_reference(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &Path) -> error::Result<()> {
+        let viewport = self.views[self.active_view].viewport;
+        // staggered so they don't hide each other
+        let offset = Overlay::RULER_SIZE + 8.0 + 20.0*(self.references.len() % 8) as f32;
+        let reference = Reference::load(rl, thread, path, Vector2::new(viewport.x + offset, viewport.y + offset))?;
+        self.references.push(reference);
+        self.is_canvas_dirty = true;
+        Ok(())
+    }
+
+    /// Take every reference pinned to the canvas back into its panel
+    pub fn unpin_references(&mut self) {
+        for reference in &mut self.references {
+            reference.unpin();
         }
-        {
-            let mut d = rl.begin_texture_mode(thread, &mut self.canvas);
-            let rec = rrect(0, 0, imported.width, imported.height);
-            d.draw_texture_direct(imported, rec);
-            self.is_canvas_dirty = true;
+        self.is_canvas_dirty = true;
+    }
+
+    /// Add a view of the canvas at 1:1 to the right of the others, up to [`Self::MAX_VIEWS`]
+    pub fn split_view(&mut self) {
+        if self.views.len() >= Self::MAX_VIEWS { return; }
+        let mut canvas_view = CanvasView::new(View::default());
+        canvas_view.is_centering = true;
+        // a second navigator in every view would only be clutter
+        canvas_view.navigator.is_visible = false;
+        self.views.push(canvas_view);
+        self.is_canvas_dirty = true;
+    }
+
+    /// Remove the active view, unless it's the only one
+    pub fn close_view(&mut self) {
+        if self.views.len() <= 1 { return; }
+        self.views.remove(self.active_view);
+        self.active_view = self.active_view.min(self.views.len() - 1);
+        self.is_canvas_dirty = true;
+        self.is_status_dirty = true;
+    }
+
+    pub fn resize(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, new_width: u32, new_height: u32, anchor: Anchor) -> error::Result<()> {
+        let (x, y) = anchor.offset(new_width as i32 - self.width(), new_height as i32 - self.height());
+        self.reframe(rl, thread, new_width, new_height, x, y)
+    }
+
+    /// Shrink the canvas to `rect`, filling any part outside the old canvas with transparency
+    pub fn crop(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, rect: Rect) -> error::Result<()> {
+        if rect.is_empty() { return Ok(()); }
+        self.reframe(rl, thread, rect.width as u32, rect.height as u32, -rect.x, -rect.y)
+    }
+
+    pub fn crop_to_selection(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> error::Result<()> {
+        match self.selection {
+            Some(rect) => {
+                self.crop(rl, thread, rect)?;
+                self.selection = None;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Crop away borders that are fully transparent in every frame
+    pub fn trim(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> error::Result<()> {
+        let content = self.canvases()
+            .map(download_canvas)
+            .collect::<error::Result<Vec<_>>>()?
+            .iter()
+            .filter_map(Raster::content_bounds)
+            .reduce(|a, b| a.union(&b));
+        match content {
+            Some(rect) => self.crop(rl, thread, rect),
+            None => Ok(()),
+        }
+    }
+
+    /// Resample the artwork to the new size
+    pub fn scale(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, new_width: u32, new_height: u32, scaler: Scaler) -> error::Result<()> {
+        if new_width == self.width() as u32 && new_height == self.height() as u32 { return Ok(()); }
+        let (old_width, old_height) = (self.width(), self.height());
+        let selection = self.selection.map(|sel| Rect::new(
+            sel.x*new_width as i32/old_width,
+            sel.y*new_height as i32/old_height,
+            (sel.width *new_width  as i32/old_width ).max(1),
+            (sel.height*new_height as i32/old_height).max(1),
+        ));
+        self.map_frames(rl, thread, |raster| resample::scale(&raster, new_width as usize, new_height as usize, scaler))?;
+        self.selection = selection;
+        Ok(())
+    }
+
+    pub fn flip(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, is_horizontal: bool) -> error::Result<()> {
+        let (width, height) = (self.width(), self.height());
+        let selection = self.selection.map(|sel| if is_horizontal {
+            Rect::new(width - sel.right(), sel.y, sel.width, sel.height)
+        } else {
+            Rect::new(sel.x, height - sel.bottom(), sel.width, sel.height)
+        });
+        self.map_frames(rl, thread, |mut raster| {
+            if is_horizontal {
+                raster.flip_horizontal();
+            } else {
+                raster.flip_vertical();
+            }
+            raster
+        })?;
+        self.selection = selection;
+        Ok(())
+    }
+
+    /// Rotate the whole canvas clockwise by a multiple of 90 degrees, swapping its dimensions for odd turns
+    pub fn rotate(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, quarter_turns: i32) -> error::Result<()> {
+        let (width, height) = (self.width(), self.height());
+        let quarter_turns = quarter_turns.rem_euclid(4);
+        if quarter_turns == 0 { return Ok(()); }
+        let selection = self.selection.map(|sel| match quarter_turns {
+            1 => Rect::new(height - sel.bottom(), sel.x, sel.height, sel.width),
+            2 => Rect::new(width - sel.right(), height - sel.bottom(), sel.width, sel.height),
+            _ => Rect::new(sel.y, width - sel.right(), sel.height, sel.width),
+        });
+        self.map_frames(rl, thread, |mut raster| match quarter_turns {
+            1 => raster.rotated_cw(),
+            2 => { raster.rotate_180(); raster }
+            _ => raster.rotated_ccw(),
+        })?;
+        self.selection = selection;
+        Ok(())
+    }
+
+    /// `original` with `effect` applied, limited to the selection
+    fn with_effect(&self, original: &Raster, effect: impl FnOnce(&mut Raster)) -> Raster {
+        let mut result = original.clone();
+        effect(&mut result);
+        if let Some(sel) = self.selection {
+            let mut masked = original.clone();
+            masked.blit(&result.crop(sel), sel.x, sel.y);
+            masked
+        } else {
+            result
+        }
+    }
+
+    /// Show the canvas with `effect` applied to the selection, without committing it
+    pub fn preview(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, effect: impl FnOnce(&mut Raster)) -> error::Result<()> {
+        let original = match self.preview_original.take() {
+            Some(original) => original,
+            None => self.download()?,
+        };
+        let result = self.with_effect(&original, effect);
+        // kept even if the upload fails, so cancelling still puts the canvas back
+        self.preview_original = Some(original);
+        self.upload(rl, thread, &result)
+    }
+
+    /// Restore the canvas from before [`ArtEditor::preview`], if previewing
+    pub fn cancel_preview(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> error::Result<()> {
+        match self.preview_original.take() {
+            Some(original) => self.upload(rl, thread, &original),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply `effect` to the selection, replacing any preview
+    pub fn apply_effect(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, effect: impl FnOnce(&mut Raster)) -> error::Result<()> {
+        let original = match self.preview_original.take() {
+            Some(original) => original,
+            None => self.download()?,
+        };
+        let result = self.with_effect(&original, effect);
+        self.upload(rl, thread, &result)?;
+        self.is_modified = true;
+        Ok(())
+    }
+
+    /// Whether a free transform is in progress, during which the canvas holds only its background
+    #[inline]
+    pub fn is_transforming(&self) -> bool {
+        matches!(self.tool, Tool::Transform(_))
+    }
+
+    /// Start freely transforming the selection, or the whole canvas if nothing is selected
+    pub fn begin_transform(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> error::Result<()> {
+        if self.is_transforming() { return Ok(()); }
+        let area = self.selection.unwrap_or(Rect::new(0, 0, self.width(), self.height()));
+        if let Some(transform) = FreeTransform::new(rl, thread, self.download()?, area)? {
+            self.upload(rl, thread, transform.base())?;
+            self.tool = Tool::Transform(Box::new(transform));
+        }
+        Ok(())
+    }
+
+    /// Replace the canvas with one of the new size, with the old canvas drawn at (`x`, `y`)
+    fn reframe(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, new_width: u32, new_height: u32, x: i32, y: i32) -> error::Result<()> {
+        if new_width == self.width() as u32 && new_height == self.height() as u32 && x == 0 && y == 0 { return Ok(()); }
+        // every texture is made before any is replaced, so running out partway leaves the frames as they were
+        let count = 1 + self.frames.iter().filter(|frame| frame.canvas.is_some()).count();
+        let new_canvases = (0..count)
+            .map(|_| rl.load_render_texture(thread, new_width, new_height).map_err(Error::gpu))
+            .collect::<error::Result<Vec<_>>>()?;
+        let canvases = std::iter::once(&mut self.canvas)
+            .chain(self.frames.iter_mut().filter_map(|frame| frame.canvas.as_mut()));
+        for (canvas, new_canvas) in canvases.zip(new_canvases) {
+            let old_canvas = std::mem::replace(canvas, new_canvas);
+            let mut d = rl.begin_texture_mode(thread, canvas);
+            d.clear_background(Color::BLANK);
+            d.draw_texture_direct(&old_canvas, rrect(x, y, old_canvas.width(), old_canvas.height()));
+        }
+        self.is_canvas_dirty = true;
+        self.is_modified = true;
+        let bounds = Rect::new(0, 0, new_width as i32, new_height as i32);
+        self.selection = self.selection.and_then(|sel| sel.translated(x, y).intersect(&bounds));
+        Ok(())
+    }
+
+    /// Replace every frame with `f` applied to it, resizing the canvas to match
+    fn map_frames(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, mut f: impl FnMut(Raster) -> Raster) -> error::Result<()> {
+        // all or nothing, like reframing
+        let new_canvases = std::iter::once(&self.canvas)
+            .chain(self.frames.iter().filter_map(|frame| frame.canvas.as_ref()))
+            .map(|canvas| canvas_from_raster(rl, thread, &f(download_canvas(canvas)?)))
+            .collect::<error::Result<Vec<_>>>()?;
+        let canvases = std::iter::once(&mut self.canvas)
+            .chain(self.frames.iter_mut().filter_map(|frame| frame.canvas.as_mut()));
+        for (canvas, new_canvas) in canvases.zip(new_canvases) {
+            *canvas = new_canvas;
+        }
+        let bounds = Rect::new(0, 0, self.width(), self.height());
+        self.selection = self.selection.and_then(|sel| sel.intersect(&bounds));
+        self.is_canvas_dirty = true;
+        self.is_modified = true;
+        Ok(())
+    }
+
+    /// Copy the current frame to the CPU, top row first
+    #[inline]
+    pub fn download(&self) -> error::Result<Raster> {
+        download_canvas(&self.canvas)
+    }
+
+    /// Replace the current frame's contents, which must stay the same size as the other frames
+    pub fn upload(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, raster: &Raster) -> error::Result<()> {
+        debug_assert!(raster.width() == self.width() as usize && raster.height() == self.height() as usize, "frames should all be the same size");
+        upload_canvas(rl, thread, &mut self.canvas, raster)?;
+        self.is_canvas_dirty = true;
+        Ok(())
+    }
+
+    /// Every frame's canvas, in order
+    fn canvases(&self) -> impl Iterator<Item = &RenderTexture2D> {
+        self.frames.iter().map(|frame| frame.canvas.as_ref().unwrap_or(&self.canvas))
+    }
+
+    #[inline]
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[inline]
+    pub const fn current_frame(&self) -> usize {
+        self.current_frame
+    }
+
+    #[inline]
+    pub const fn is_playing(&self) -> bool {
+        self.timeline.playback.is_playing()
+    }
+
+    /// Switch to editing frame `index`
+    pub fn select_frame(&mut self, index: usize) {
+        if index == self.current_frame || index >= self.frames.len() || self.is_transforming() { return; }
+        let canvas = self.frames[index].canvas.take().unwrap();
+        let old_canvas = std::mem::replace(&mut self.canvas, canvas);
+        self.frames[self.current_frame].canvas = Some(old_canvas);
+        self.current_frame = index;
+        self.is_canvas_dirty = true;
+        self.is_timeline_dirty = true;
+    }
+
+    /// Insert `canvas` after the current frame and switch to it
+    fn insert_frame(&mut self, canvas: RenderTexture2D) {
+        let duration = self.frames[self.current_frame].duration;
+        self.frames.insert(self.current_frame + 1, AnimFrame { canvas: Some(canvas), duration });
+        self.select_frame(self.current_frame + 1);
+        self.is_modified = true;
+    }
+
+    /// Add a blank frame after the current one
+    pub fn add_frame(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> error::Result<()> {
+        if self.is_transforming() { return Ok(()); }
+        let mut canvas = rl.load_render_texture(thread, self.width() as u32, self.height() as u32).map_err(Error::gpu)?;
+        rl.begin_texture_mode(thread, &mut canvas).clear_background(Color::BLANK);
+        self.insert_frame(canvas);
+        Ok(())
+    }
+
+    /// Add a copy of the current frame after it
+    pub fn duplicate_frame(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> error::Result<()> {
+        if self.is_transforming() { return Ok(()); }
+        let canvas = canvas_from_raster(rl, thread, &self.download()?)?;
+        self.insert_frame(canvas);
+        Ok(())
+    }
+
+    /// Remove the current frame, unless it is the only one
+    pub fn delete_frame(&mut self) {
+        if self.frames.len() < 2 || self.is_transforming() { return; }
+        let deleted = self.current_frame;
+        self.select_frame(if deleted + 1 < self.frames.len() { deleted + 1 } else { deleted - 1 });
+        self.frames.remove(deleted);
+        if self.current_frame > deleted {
+            self.current_frame -= 1;
+        }
+        self.is_modified = true;
+    }
+
+    /// Swap the current frame with the one `offset` frames away
+    pub fn move_frame(&mut self, offset: isize) {
+        if let Some(target) = self.current_frame.checked_add_signed(offset) && target < self.frames.len() {
+            self.frames.swap(self.current_frame, target);
+            self.current_frame = target;
+            self.is_timeline_dirty = true;
+            self.is_modified = true;
         }
-        Some(())
+    }
+
+    pub fn toggle_playback(&mut self, rl: &RaylibHandle) {
+        if self.is_transforming() { return; }
+        self.timeline.playback.toggle(rl.get_time());
+        self.is_canvas_dirty = true;
+        self.is_timeline_dirty = true;
+    }
+
+    fn run_timeline_command(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, command: TimelineCommand) -> error::Result<()> {
+        match command {
+            TimelineCommand::Select(index) => self.select_frame(index),
+            TimelineCommand::Add => self.add_frame(rl, thread)?,
+            TimelineCommand::Duplicate => self.duplicate_frame(rl, thread)?,
+            TimelineCommand::Delete => self.delete_frame(),
+            TimelineCommand::Move(offset) => self.move_frame(offset),
+            TimelineCommand::TogglePlayback => self.toggle_playback(rl),
+            TimelineCommand::SetMode(mode) => self.timeline.playback.mode = mode,
+            TimelineCommand::SetOnionSkin(is_enabled) => {
+                self.timeline.onion_skin.is_enabled = is_enabled;
+                self.is_canvas_dirty = true;
+            }
+            TimelineCommand::SetOnionOpacity(opacity) => {
+                self.timeline.onion_skin.opacity = opacity;
+                self.is_canvas_dirty = true;
+            }
+            TimelineCommand::SetDuration(duration) => {
+                self.frames[self.current_frame].duration = duration;
+                self.is_modified = true;
+            }
+            TimelineCommand::SetFps(fps) => {
+                for frame in &mut self.frames {
+                    frame.duration = (1000/fps).max(1);
+                }
+                self.is_modified = true;
+            }
+        }
+        self.is_timeline_dirty = true;
+        Ok(())
+    }
+
+    /// Write the current frame in the format named by `path`'s extension.
+    /// The other frames aren't in the file, so with more than one the document still counts as modified.
+    pub fn save(&mut self, path: &Path, options: EncodeOptions) -> error::Result<()> {
+        format::write(path, &self.download()?, options)?;
+        self.is_modified = self.frames.len() > 1;
+        Ok(())
+    }
+
+    /// Every frame with its duration, playing forever
+    pub fn animation(&self) -> error::Result<Animation> {
+        let frames = self.canvases().map(download_canvas).collect::<error::Result<Vec<_>>>()?;
+        Ok(Animation {
+            frames: frames.into_iter().zip(self.frames.iter().map(|frame| frame.duration)).collect(),
+            loop_count: 0,
+        })
+    }
+
+    /// Replace every frame with those of `animation`, resizing the canvas to match
+    pub fn load_animation(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, animation: Animation) -> error::Result<()> {
+        if self.is_transforming() {
+            return Err(io::Error::other("finish or cancel the transform first").into());
+        }
+        if animation.size().is_none() {
+            return Err(Error::Decode("there are no frames".to_string()));
+        }
+        // the current frames stay until every new one has made it to the GPU
+        let mut canvases = animation.frames.iter()
+            .map(|(raster, _)| canvas_from_raster(rl, thread, raster))
+            .collect::<error::Result<Vec<_>>>()?
+            .into_iter();
+        self.timeline.playback.stop();
+        self.canvas = canvases.next().unwrap();
+        self.frames = std::iter::once(None).chain(canvases.map(Some))
+            .zip(animation.frames.iter().map(|&(_, duration)| duration))
+            .map(|(canvas, duration)| AnimFrame { canvas, duration })
+            .collect();
+        self.current_frame = 0;
+        self.selection = None;
+        self.is_canvas_dirty = true;
+        self.is_timeline_dirty = true;
+        self.is_modified = true;
+        Ok(())
+    }
+
+    pub fn export_animation(&self, path: &Path, format: AnimationFormat, loop_count: u32) -> error::Result<()> {
+        let animation = Animation { loop_count, ..self.animation()? };
+        format.encode(BufWriter::new(File::create(path)?), &animation)?;
+        Ok(())
+    }
+
+    /// Write every frame to one PNG, with a JSON file of the same name describing where each frame is
+    pub fn export_sheet(&self, path: &Path, options: &SheetOptions) -> error::Result<()> {
+        let name = path.file_stem().map_or_else(|| "sprite".into(), |stem| stem.to_string_lossy());
+        let image = path.file_name().map_or_else(|| "sprite.png".into(), |name| name.to_string_lossy());
+        let direction = match self.timeline.playback.mode {
+            PlaybackMode::Loop => "forward",
+            PlaybackMode::PingPong => "pingpong",
+        };
+        let tag = TagMeta { name: name.to_string(), from: 0, to: self.frames.len() - 1, direction: direction.to_string() };
+        let (sheet, meta) = sprite_sheet::pack(&self.animation()?, options, &name, &image, vec![tag]);
+        apng::encode_still(BufWriter::new(File::create(path)?), &sheet)?;
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path.with_extension("json"))?), &meta).map_err(io::Error::from)?;
+        Ok(())
+    }
+
+    /// Replace the document with the image at `path` as its only frame, sizing the canvas to fit it.
+    /// Returns anything that had to be left out of the image, like a Photoshop document's adjustment layers.
+    pub fn open(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &Path) -> error::Result<Vec<String>> {
+        let (raster, warnings) = format::read_with_warnings(path)?;
+        self.load_animation(rl, thread, Animation { frames: vec![(raster, AnimFrame::DEFAULT_DURATION)], loop_count: 0 })?;
+        // it matches the file now
+        self.is_modified = false;
+        Ok(warnings)
     }
 }
 
@@ -82,59 +793,150 @@ impl Editor for ArtEditor {
     #[inline]
     fn mark_dirty(&mut self) {
         self.is_canvas_dirty = true;
+        self.is_timeline_dirty = true;
+        self.is_status_dirty = true;
     }
 
     #[inline]
     fn is_dirty(&self) -> bool {
-        self.is_canvas_dirty
+        self.is_canvas_dirty || self.is_timeline_dirty || self.is_status_dirty
     }
 
     #[inline]
     fn is_focused(&self) -> bool {
-        self.is_drag_panning || matches!(self.tool, Tool::Pen { pen_pos_prev: Some(_) })
+        self.views[self.active_view].is_focused() || self.overlay.is_dragging() || self.references.iter().any(Reference::is_dragging) || match &self.tool {
+            Tool::Pen { pen_pos_prev } => pen_pos_prev.is_some(),
+            Tool::Fill => false,
+            Tool::Select { drag_start } => drag_start.is_some(),
+            Tool::Transform(transform) => transform.is_dragging(),
+        }
     }
 
-    fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, brush: &mut Brush, viewport: Rectangle, frame: &mut Frame, is_awake: bool) {
+    fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, keymap: &Keymap, brush: &mut Brush, viewport: Rectangle, frame: &mut Frame, is_awake: bool) -> error::Result<()> {
         let mouse_pos = rl.get_mouse_position();
+        self.was_redrawn = false;
+
+        let timeline_rec = Rectangle {
+            y: viewport.y + viewport.height - Timeline::HEIGHT,
+            height: Timeline::HEIGHT,
+            ..viewport
+        };
+        let status_rec = Rectangle {
+            y: timeline_rec.y - status_bar::HEIGHT,
+            height: status_bar::HEIGHT,
+            ..viewport
+        };
+        let canvas_area = Rectangle {
+            height: viewport.height - Timeline::HEIGHT - status_bar::HEIGHT,
+            ..viewport
+        };
+        let canvas_size = Vector2::new(self.width() as f32, self.height() as f32);
+        for (canvas_view, view_rec) in self.views.iter_mut().zip(editor::columns(canvas_area, self.views.len())) {
+            if canvas_view.viewport != view_rec {
+                canvas_view.viewport = view_rec;
+                if std::mem::take(&mut canvas_view.is_centering) {
+                    canvas_view.view.center(canvas_size.x, canvas_size.y, view_rec);
+                }
+                self.is_canvas_dirty = true;
+            }
+        }
+        // input goes to the view under the mouse, unless something started in another one is still going
+        if !self.is_focused() && let Some(i) = self.views.iter().position(|canvas_view| canvas_view.viewport.check_collision_point_rec(mouse_pos)) && i != self.active_view {
+            self.active_view = i;
+            self.is_status_dirty = true;
+        }
+        let active = self.active_view;
+        let canvas_viewport = self.views[active].viewport;
+        let pivot = view::pivot(canvas_viewport);
+        let top_ruler = Rectangle { height: Overlay::RULER_SIZE, ..canvas_viewport };
+        let left_ruler = Rectangle { width: Overlay::RULER_SIZE, ..canvas_viewport };
+        let is_over_timeline = timeline_rec.check_collision_point_rec(mouse_pos);
+        // rulers measure the unrotated view, so they're hidden while it's rotated
+        let is_over_rulers = !self.views[active].view.is_rotated() && (top_ruler.check_collision_point_rec(mouse_pos) || left_ruler.check_collision_point_rec(mouse_pos));
+        let is_over_navigator = self.views[active].navigator.is_visible
+            && Navigator::panel_rec(canvas_viewport, canvas_size.x, canvas_size.y).check_collision_point_rec(mouse_pos);
+        let is_over_reference = self.references.iter().any(|reference| reference.contains(mouse_pos));
+        // the timeline gets the mouse unless a stroke started on the canvas, and keys while typing into it
+        let is_canvas_awake = is_awake && !self.timeline.is_editing() && (self.is_focused() || !is_over_timeline);
+        if is_awake && (is_over_timeline || self.timeline.is_editing()) {
+            self.is_timeline_dirty = true;
+        }
+
+        // Playback
+        if self.timeline.playback.is_playing() && self.preview_original.is_none() {
+            let durations: Vec<u32> = self.frames.iter().map(|frame| frame.duration).collect();
+            let next = self.timeline.playback.advance(rl.get_time(), self.current_frame, &durations);
+            self.select_frame(next);
+        }
+
+        // References
+        if is_canvas_awake && (self.references.iter().any(Reference::is_dragging) || !self.is_focused()) {
+            let view = self.views[active].view;
+            let canvas_mouse_pos = view.to_canvas(mouse_pos, pivot);
+            let is_pressed = rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT);
+            // the topmost one the mouse is on gets the input, and comes to the front when clicked
+            for i in (0..self.references.len()).rev() {
+                match self.references[i].update(rl, brush, &view, pivot, canvas_mouse_pos) {
+                    Response::Outside => continue,
+                    Response::Inside { is_changed } => {
+                        if is_pressed && i + 1 != self.references.len() {
+                            let reference = self.references.remove(i);
+                            self.references.push(reference);
+                            self.is_canvas_dirty = true;
+                        }
+                        if is_changed {
+                            self.is_canvas_dirty = true;
+                        }
+                    }
+                    Response::Closed => {
+                        self.references.remove(i);
+                        self.is_canvas_dirty = true;
+                    }
+                }
+                break;
+            }
+        }
 
         // Zoom + pan
-        if is_awake {
-            let scroll = rl.get_mouse_wheel_move();
+        if is_canvas_awake {
+            if self.views[active].navigator.is_dragging() || !self.is_focused() {
+                let canvas_view = &mut self.views[active];
+                if canvas_view.navigator.update(rl, &mut canvas_view.view, canvas_viewport, canvas_size.x, canvas_size.y) {
+                    self.is_canvas_dirty = true;
+                }
+            }
 
             let mut new_brush_radius = brush.radius;
 
-            if scroll != 0.0 {
-                if rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL) {
-                    // zoom
-                    let zoom_pow_old = self.zoom_pow;
-                    self.zoom_pow = (self.zoom_pow + scroll.round() as i32).clamp(-4, 4);
-                    self.pan = self.pan + mouse_pos*(2.0f32.powi(-self.zoom_pow) - 2.0f32.powi(-zoom_pow_old));
-                    self.is_canvas_dirty = true;
-                } else if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT) {
-                    // horizontal pan
-                    self.pan.x += scroll * 20.0;
+            // the navigator and references zoom when scrolled over
+            if !is_over_navigator && !is_over_reference {
+                let view = &mut self.views[active].view;
+                let zoom_scroll = keymap.scroll(Command::Zoom);
+                if zoom_scroll != 0.0 {
+                    view.zoom_about(view.scrolled_zoom(zoom_scroll), mouse_pos, pivot);
                     self.is_canvas_dirty = true;
-                } else if rl.is_key_down(KeyboardKey::KEY_LEFT_ALT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_ALT) {
-                    // pen size
-                    new_brush_radius = (new_brush_radius + scroll * 0.5).max(0.5);
-                } else {
-                    // vertical pan
-                    self.pan.y += scroll * 20.0;
+                }
+                let pan_scroll = Vector2::new(keymap.scroll(Command::PanHorizontal), keymap.scroll(Command::PanVertical));
+                if pan_scroll != Vector2::zero() {
+                    view.pan = view.pan + pan_scroll*20.0;
                     self.is_canvas_dirty = true;
                 }
+                let size_scroll = keymap.scroll(Command::BrushSize);
+                if size_scroll != 0.0 {
+                    new_brush_radius = (new_brush_radius + size_scroll*0.5).max(0.5);
+                }
             }
 
             // pen size
-            if rl.is_key_pressed(KeyboardKey::KEY_ONE) {
-                new_brush_radius = 0.5;
-            } else if rl.is_key_pressed(KeyboardKey::KEY_TWO) {
-                new_brush_radius = 1.0;
-            } else if rl.is_key_pressed(KeyboardKey::KEY_THREE) {
-                new_brush_radius = 2.0;
-            } else if rl.is_key_pressed(KeyboardKey::KEY_FOUR) {
-                new_brush_radius = 3.0;
-            } else if rl.is_key_pressed(KeyboardKey::KEY_FIVE) {
-                new_brush_radius = 4.0;
+            let sizes = [
+                (Command::BrushSize1, 0.5),
+                (Command::BrushSize2, 1.0),
+                (Command::BrushSize3, 2.0),
+                (Command::BrushSize4, 3.0),
+                (Command::BrushSize5, 4.0),
+            ];
+            if let Some(&(_, radius)) = sizes.iter().find(|(command, _)| keymap.is_triggered(*command)) {
+                new_brush_radius = radius;
             }
 
             if brush.radius != new_brush_radius {
@@ -143,40 +945,116 @@ impl Editor for ArtEditor {
             }
         }
 
-        let zoom = 2.0f32.powi(self.zoom_pow);
-        let zoom_inv = zoom.recip();
+        if is_canvas_awake {
+            let is_focused = self.is_focused();
+            let canvas_view = &mut self.views[active];
 
-        if is_awake {
-            {
-                let is_pressed = rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_MIDDLE);
-                if is_pressed || rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_MIDDLE) {
-                    self.is_drag_panning = is_pressed;
+            // pinch zoom on touch screens
+            if rl.get_touch_point_count() == 2 {
+                let (a, b) = (rl.get_touch_position(0), rl.get_touch_position(1));
+                let distance = a.distance_to(b);
+                if let Some(distance_prev) = canvas_view.pinch_distance && distance_prev > 0.0 && distance != distance_prev {
+                    canvas_view.view.zoom_about(canvas_view.view.zoom*distance/distance_prev, (a + b)*0.5, pivot);
+                    self.is_canvas_dirty = true;
                 }
+                canvas_view.pinch_distance = Some(distance);
+            } else {
+                canvas_view.pinch_distance = None;
+            }
+
+            if keymap.is_triggered(Command::DragPan) {
+                canvas_view.is_drag_panning = true;
+            } else if !keymap.is_down(rl, Command::DragPan) {
+                canvas_view.is_drag_panning = false;
             }
 
-            if self.is_drag_panning {
+            if canvas_view.is_drag_panning {
                 let movement = rl.get_mouse_delta();
                 if movement.length_sqr() > 0.0 {
-                    self.pan = self.pan + movement * zoom_inv;
+                    canvas_view.view.pan = canvas_view.view.pan + canvas_view.view.to_canvas_delta(movement);
+                    self.is_canvas_dirty = true;
+                }
+            }
+
+            // rotate the view by dragging, in steps of 15 degrees while holding shift
+            let mouse_angle = (mouse_pos.y - pivot.y).atan2(mouse_pos.x - pivot.x).to_degrees();
+            if keymap.is_triggered(Command::RotateView) && !is_focused && !is_over_navigator && !is_over_reference {
+                canvas_view.rotate_start = Some((mouse_angle, canvas_view.view.rotation));
+            } else if !keymap.is_down(rl, Command::RotateView) {
+                canvas_view.rotate_start = None;
+            }
+            if let Some((start_angle, start_rotation)) = canvas_view.rotate_start {
+                let mut rotation = start_rotation + mouse_angle - start_angle;
+                if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT) {
+                    rotation = (rotation/15.0).round()*15.0;
+                }
+                // keep within -180..=180
+                rotation = 180.0 - (180.0 - rotation).rem_euclid(360.0);
+                if rotation != canvas_view.view.rotation {
+                    canvas_view.view.rotation = rotation;
                     self.is_canvas_dirty = true;
                 }
             }
         }
 
+        let view = self.views[active].view;
+        let zoom = view.zoom;
+
+        let canvas_mouse_pos = view.to_canvas(mouse_pos, pivot);
         let pen_pos = Vector2 {
-            x: (mouse_pos.x*zoom_inv - self.pan.x).floor(),
-            y: (mouse_pos.y*zoom_inv - self.pan.y).floor(),
+            x: canvas_mouse_pos.x.floor(),
+            y: canvas_mouse_pos.y.floor(),
         };
 
-        if is_awake {
-            if rl.is_key_pressed(KeyboardKey::KEY_G) {
+        if is_canvas_awake {
+            if self.is_transforming() {
+                // finish the transform before switching tools
+            } else if keymap.is_triggered(Command::PreviousFrame) {
+                self.select_frame(self.current_frame.saturating_sub(1));
+            } else if keymap.is_triggered(Command::NextFrame) {
+                self.select_frame(self.current_frame + 1);
+            } else if keymap.is_triggered(Command::TogglePlayback) {
+                self.toggle_playback(rl);
+            } else if keymap.is_triggered(Command::ToggleOnionSkin) {
+                self.timeline.onion_skin.is_enabled = !self.timeline.onion_skin.is_enabled;
+                self.is_canvas_dirty = true;
+                self.is_timeline_dirty = true;
+            } else if keymap.is_triggered(Command::CycleTileMode) {
+                self.tile_mode = self.tile_mode.next();
+                self.is_canvas_dirty = true;
+            } else if keymap.is_triggered(Command::ToggleNavigator) {
+                let navigator = &mut self.views[active].navigator;
+                navigator.is_visible = !navigator.is_visible;
+                self.is_canvas_dirty = true;
+            } else if keymap.is_triggered(Command::FillTool) {
                 self.tool = Tool::Fill;
-            } else if rl.is_key_pressed(KeyboardKey::KEY_B) {
+                self.is_status_dirty = true;
+            } else if keymap.is_triggered(Command::PenTool) {
                 if !matches!(self.tool, Tool::Pen { .. }) {
                     self.tool = Tool::Pen { pen_pos_prev: None };
+                    self.is_status_dirty = true;
+                }
+            } else if keymap.is_triggered(Command::SelectTool) {
+                if !matches!(self.tool, Tool::Select { .. }) {
+                    self.tool = Tool::Select { drag_start: None };
+                    self.is_status_dirty = true;
                 }
             }
 
+            // Guides
+            if !view.is_rotated() && (self.overlay.is_dragging() || !self.is_focused()) {
+                let pan = view.pan;
+                if self.overlay.update_guides(rl, top_ruler, left_ruler, canvas_mouse_pos, |p| (pan + p)*zoom) {
+                    self.is_canvas_dirty = true;
+                }
+            }
+        }
+
+        // the rulers, navigator and references only belong to tools that started a drag on the canvas
+        let is_over_panel = is_over_rulers || is_over_navigator || is_over_reference;
+        let is_view_busy = self.overlay.is_dragging() || self.views[active].navigator.is_dragging() || self.views[active].rotate_start.is_some()
+            || self.references.iter().any(Reference::is_dragging);
+        if is_canvas_awake && !is_view_busy && (self.is_focused() || !is_over_panel) {
             // Paint
             match &mut self.tool {
                 Tool::Pen { pen_pos_prev } => {
@@ -184,8 +1062,12 @@ impl Editor for ArtEditor {
                         let mut d = rl.begin_texture_mode(&thread, &mut self.canvas);
                         let pos_pprev = std::mem::replace(pos_pprev, Some(*pos_prev));
                         let pos_prev = std::mem::replace(pos_prev, pen_pos);
-                        brush.paint(&mut d, pos_pprev.into_iter().chain([pos_prev, pen_pos].into_iter()), self.is_erasing);
+                        let points: Vec<Vector2> = pos_pprev.into_iter().chain([pos_prev, pen_pos]).collect();
+                        for offset in self.tile_mode.offsets(&points, brush.radius, canvas_size) {
+                            brush.paint(&mut d, points.iter().map(|&p| p + offset), self.is_erasing);
+                        }
                         self.is_canvas_dirty = true;
+                        self.is_modified = true;
                     }
 
                     if  rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT) ||
@@ -200,8 +1082,11 @@ impl Editor for ArtEditor {
                             *pen_pos_prev = Some((pen_pos, None));
                             self.is_erasing = is_right_pressed;
                             let mut d = rl.begin_texture_mode(&thread, &mut self.canvas);
-                            brush.paint(&mut d, [pen_pos], self.is_erasing);
+                            for offset in self.tile_mode.offsets(&[pen_pos], brush.radius, canvas_size) {
+                                brush.paint(&mut d, [pen_pos + offset], self.is_erasing);
+                            }
                             self.is_canvas_dirty = true;
+                            self.is_modified = true;
                         }
                     }
                 }
@@ -209,42 +1094,256 @@ impl Editor for ArtEditor {
                 Tool::Fill => {
                     let is_erasing = rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT);
                     if is_erasing || rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
-                        let mut img = self.canvas.load_image().unwrap();
+                        let mut img = self.canvas.load_image().map_err(Error::gpu)?;
+                        // clicking a repeat of the canvas fills the canvas itself
+                        let pen_pos = self.tile_mode.wrap(pen_pos, canvas_size);
                         let (x, y) = (pen_pos.x as i32, img.height - pen_pos.y as i32);
-                        flood_fill(&mut img, x, y, if is_erasing { Color::BLANK } else { brush.color });
+                        let color = if is_erasing { Color::BLANK } else { brush.color };
+                        if self.tile_mode == TileMode::Off {
+                            flood_fill(&mut img, x, y, color);
+                        } else {
+                            flood_fill_wrapping(&mut img, x, y, color, self.tile_mode.wraps_x(), self.tile_mode.wraps_y());
+                        }
                         let len = get_pixel_data_size(img.width, img.height, img.format()).try_into().unwrap();
                         let pixels = unsafe { std::slice::from_raw_parts(img.data.cast(), len) };
-                        self.canvas.update_texture(pixels).unwrap();
+                        self.canvas.update_texture(pixels).map_err(Error::gpu)?;
                         self.is_canvas_dirty = true;
+                        self.is_modified = true;
+                    }
+                }
+
+                Tool::Select { drag_start } => {
+                    // snapped lines are pixel edges, so a drag towards higher coordinates stops at the pixel before one
+                    let snap = |value: f32, is_vertical: bool, start: Option<i32>| match self.overlay.snap(value, is_vertical, zoom) {
+                        Some(line) if start.is_some_and(|start| line > start) => line - 1,
+                        Some(line) => line,
+                        None => value.floor() as i32,
+                    };
+                    let pen_pos = (
+                        snap(canvas_mouse_pos.x, true,  drag_start.map(|start| start.0)),
+                        snap(canvas_mouse_pos.y, false, drag_start.map(|start| start.1)),
+                    );
+                    if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                        *drag_start = Some(pen_pos);
+                    } else if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
+                        self.selection = None;
+                        self.is_canvas_dirty = true;
+                    }
+
+                    if let Some(start) = *drag_start {
+                        let bounds = Rect::new(0, 0, self.canvas.texture.width, self.canvas.texture.height);
+                        let selection = Rect::from_corners(start, pen_pos).intersect(&bounds);
+                        if selection != self.selection {
+                            self.selection = selection;
+                            self.is_canvas_dirty = true;
+                        }
+                        if rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT) {
+                            *drag_start = None;
+                        }
+                    }
+                }
+
+                Tool::Transform(transform) => {
+                    if keymap.is_triggered(Command::CycleSampler) {
+                        transform.cycle_sampler();
+                        self.is_canvas_dirty = true;
+                    }
+                    if transform.update(rl, canvas_mouse_pos, zoom) {
+                        self.is_canvas_dirty = true;
+                    }
+
+                    let is_committing = keymap.is_triggered(Command::ApplyTransform);
+                    if is_committing || keymap.is_triggered(Command::CancelTransform) {
+                        let Tool::Transform(transform) = std::mem::replace(&mut self.tool, Tool::Select { drag_start: None }) else { unreachable!() };
+                        let raster = if is_committing {
+                            let (raster, covered) = transform.commit();
+                            self.selection = covered;
+                            self.is_modified = true;
+                            raster
+                        } else {
+                            transform.cancel()
+                        };
+                        self.upload(rl, thread, &raster)?;
                     }
                 }
             }
         }
 
+        // Status
+        let (pen_x, pen_y) = (pen_pos.x as i32, pen_pos.y as i32);
+        let is_over_canvas = canvas_viewport.check_collision_point_rec(mouse_pos) && !is_over_panel
+            && Rect::new(0, 0, self.width(), self.height()).contains(pen_x, pen_y);
+        let cursor = (is_awake && is_over_canvas).then_some((pen_x, pen_y));
+        if cursor != self.status_cursor {
+            self.status_cursor = cursor;
+            self.is_status_dirty = true;
+        }
+
         // Render
         if self.is_canvas_dirty {
             self.is_canvas_dirty = false;
+            self.was_redrawn = true;
 
-            let pan = Vector2 {
-                x: self.pan.x.round(),
-                y: self.pan.y.round(),
-            };
+            for (i, canvas_view) in self.views.iter().enumerate() {
+                let view = &canvas_view.view;
+                let zoom = view.zoom;
+                let canvas_viewport = canvas_view.viewport;
+                let pivot = view::pivot(canvas_viewport);
+                let top_ruler = Rectangle { height: Overlay::RULER_SIZE, ..canvas_viewport };
+                let left_ruler = Rectangle { width: Overlay::RULER_SIZE, ..canvas_viewport };
+
+                let pan = view.snapped_pan();
+
+                let canvas_rec = Rectangle {
+                    x: pan.x * zoom,
+                    y: pan.y * zoom,
+                    width:  self.canvas.texture.width  as f32 * zoom,
+                    height: self.canvas.texture.height as f32 * zoom,
+                };
+
+                {
+                    let mut d = frame.begin_drawing(rl, thread);
+                    let mut d = d.begin_scissor_mode(canvas_viewport.x as i32, canvas_viewport.y as i32, canvas_viewport.width as i32, canvas_viewport.height as i32);
+                    d.clear_background(Color::BLACK);
 
-            let canvas_rec = Rectangle {
-                x: pan.x * zoom,
-                y: pan.y * zoom,
-                width:  self.canvas.texture.width  as f32 * zoom,
-                height: self.canvas.texture.height as f32 * zoom,
+                    {
+                        // everything on the canvas turns with the view
+                        let mut d = d.begin_mode2D(view.camera(pivot));
+
+                        // repeats around the canvas so seams can be checked while painting
+                        for (i, j) in self.tile_mode.repeats().filter(|&offset| offset != (0, 0)) {
+                            let repeat_rec = Rectangle {
+                                x: canvas_rec.x + i as f32*canvas_rec.width,
+                                y: canvas_rec.y + j as f32*canvas_rec.height,
+                                ..canvas_rec
+                            };
+                            d.draw_rectangle_rec(repeat_rec, Color::new(42, 42, 42, 255));
+                            d.draw_texture_direct(&self.canvas, repeat_rec);
+                        }
+
+                        d.draw_rectangle_rec(canvas_rec, Color::new(42, 42, 42, 255));
+
+                        if !self.timeline.playback.is_playing() {
+                            for (i, tint) in self.timeline.onion_skin.layers(self.current_frame, self.frames.len()) {
+                                if let Some(canvas) = &self.frames[i].canvas {
+                                    d.draw_texture_direct_tint(canvas, canvas_rec, tint);
+                                }
+                            }
+                        }
+
+                        d.draw_texture_direct(&self.canvas, canvas_rec);
+
+                        for reference in &self.references {
+                            reference.draw_pinned(&mut d, pan, zoom);
+                        }
+
+                        if self.tile_mode != TileMode::Off {
+                            d.draw_rectangle_lines_ex(canvas_rec, 1.0, Color::new(255, 255, 255, 64));
+                        }
+
+                        self.overlay.draw(&mut d, view.visible_area(canvas_viewport, pivot), canvas_rec, zoom);
+
+                        if let Some(sel) = self.selection {
+                            let sel_rec = Rectangle {
+                                x: (pan.x + sel.x as f32) * zoom,
+                                y: (pan.y + sel.y as f32) * zoom,
+                                width:  sel.width  as f32 * zoom,
+                                height: sel.height as f32 * zoom,
+                            };
+                            d.draw_rectangle_lines_ex(sel_rec, 1.0, Color::WHITE);
+                        }
+
+                        if let Tool::Transform(transform) = &self.tool {
+                            transform.draw(&mut d, |p| (pan + p)*zoom, zoom);
+                        }
+                    }
+
+                    if self.tile_mode != TileMode::Off {
+                        let text = format!("Tile mode: {}{}", self.tile_mode.name(), key_hint(keymap, &[(Command::CycleTileMode, "change")]));
+                        d.draw_text(&text, canvas_viewport.x as i32 + 5, (canvas_viewport.y + canvas_viewport.height) as i32 - 15, 10, Color::WHITE);
+                    }
+
+                    if let Tool::Transform(transform) = &self.tool && i == active {
+                        let text = format!("{}{}", transform.sampler().name(), key_hint(keymap, &[(Command::CycleSampler, "change"), (Command::ApplyTransform, "apply"), (Command::CancelTransform, "cancel")]));
+                        d.draw_text(&text, (canvas_viewport.x + Overlay::RULER_SIZE) as i32 + 5, (canvas_viewport.y + Overlay::RULER_SIZE) as i32 + 5, 10, Color::WHITE);
+                    }
+
+                    if view.is_rotated() {
+                        let text = format!("Rotated {:.0}\u{b0}{}", view.rotation, key_hint(keymap, &[(Command::ResetRotation, "reset")]));
+                        let x = (canvas_viewport.x + canvas_viewport.width) as i32 - measure_text(&text, 10) - 5;
+                        d.draw_text(&text, x, canvas_viewport.y as i32 + 5, 10, Color::WHITE);
+                    } else {
+                        self.overlay.draw_rulers(&mut d, top_ruler, left_ruler, canvas_rec, zoom);
+                    }
+
+                    for reference in &self.references {
+                        reference.draw_panel(&mut d);
+                    }
+
+                    if i > 0 {
+                        d.draw_line_ex(Vector2::new(canvas_viewport.x + 0.5, canvas_viewport.y), Vector2::new(canvas_viewport.x + 0.5, canvas_viewport.y + canvas_viewport.height), 1.0, Color::new(64, 64, 64, 255));
+                    }
+
+                    // last, as it ends the scissor mode
+                    canvas_view.navigator.draw(&mut d, canvas_viewport, &self.canvas, view);
+                }
+            }
+
+            // thumbnails show the canvas, and the status bar what's under the cursor
+            self.is_timeline_dirty = true;
+            self.is_status_dirty = true;
+        }
+
+        if self.is_status_dirty {
+            self.is_status_dirty = false;
+
+            let color = match self.status_cursor {
+                Some((x, y)) => {
+                    let probe = match self.probe.take() {
+                        Some(probe) => probe,
+                        None => rl.load_render_texture(thread, 1, 1).map_err(Error::gpu)?,
+                    };
+                    let probe = self.probe.insert(probe);
+                    Some(read_pixel(rl, thread, probe, &self.canvas, x, y)?)
+                }
+                None => None,
+            };
+            let status = status_bar::Status {
+                cursor: self.status_cursor,
+                color,
+                width: self.width(),
+                height: self.height(),
+                zoom,
+                tool: self.tool.name(),
+                selection: self.selection,
             };
+            let mut d = frame.begin_drawing(rl, thread);
+            let mut d = d.begin_scissor_mode(status_rec.x as i32, status_rec.y as i32, status_rec.width as i32, status_rec.height as i32);
+            status_bar::draw(&mut d, status_rec, &status);
+        }
 
-            {
-                let mut d = frame.begin_drawing(rl, thread);
-                let mut d = d.begin_scissor_mode(viewport.x as i32, viewport.y as i32, viewport.width as i32, viewport.height as i32);
-                d.clear_background(Color::BLACK);
-                d.draw_rectangle_rec(canvas_rec, Color::new(42, 42, 42, 255));
+        if self.is_timeline_dirty {
+            self.is_timeline_dirty = false;
 
-                d.draw_texture_direct(&self.canvas, canvas_rec);
+            let is_clicked = is_awake && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT);
+            let current_duration = self.frames[self.current_frame].duration;
+            let command = {
+                let mut d = frame.begin_drawing(rl, thread);
+                let mut d = d.begin_scissor_mode(timeline_rec.x as i32, timeline_rec.y as i32, timeline_rec.width as i32, timeline_rec.height as i32);
+                if !is_awake {
+                    d.gui_lock();
+                }
+                let canvases: Vec<&RenderTexture2D> = self.frames.iter()
+                    .map(|frame| frame.canvas.as_ref().unwrap_or(&self.canvas))
+                    .collect();
+                let command = self.timeline.draw(&mut d, timeline_rec, mouse_pos, is_clicked, &canvases, self.current_frame, current_duration);
+                d.gui_unlock();
+                command
+            };
+            if let Some(command) = command {
+                self.run_timeline_command(rl, thread, command)?;
             }
         }
+        Ok(())
     }
 }
@@ -0,0 +1,287 @@
+use raylib::prelude::*;
+use amygui::prelude::*;
+
+/// One frame of the animation
+pub struct AnimFrame {
+    /// `None` while this is the frame being edited, whose canvas is checked out into the [`ArtEditor`](super::ArtEditor)
+    pub canvas: Option<RenderTexture2D>,
+    /// How long the frame is shown for, in milliseconds
+    pub duration: u32,
+}
+
+impl AnimFrame {
+    pub const DEFAULT_DURATION: u32 = 100;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PlaybackMode {
+    #[default]
+    Loop,
+    PingPong,
+}
+
+#[derive(Debug, Default)]
+pub struct Playback {
+    pub mode: PlaybackMode,
+    is_playing: bool,
+    is_reversed: bool,
+    /// [`RaylibHandle::get_time`] when the current frame started showing
+    frame_start: f64,
+}
+
+impl Playback {
+    #[inline]
+    pub const fn is_playing(&self) -> bool {
+        self.is_playing
+    }
+
+    pub fn toggle(&mut self, now: f64) {
+        self.is_playing = !self.is_playing;
+        self.is_reversed = false;
+        self.frame_start = now;
+    }
+
+    pub fn stop(&mut self) {
+        self.is_playing = false;
+    }
+
+    /// The frame that should be showing at `now`, given the frame currently showing and every frame's duration
+    pub fn advance(&mut self, now: f64, mut current: usize, durations: &[u32]) -> usize {
+        if !self.is_playing || durations.len() < 2 {
+            return current;
+        }
+        // don't try to catch up after a stall
+        if now - self.frame_start > 1.0 {
+            self.frame_start = now;
+        }
+        loop {
+            let duration = durations[current].max(1) as f64/1000.0;
+            if now - self.frame_start < duration {
+                return current;
+            }
+            self.frame_start += duration;
+            current = self.next(current, durations.len());
+        }
+    }
+
+    fn next(&mut self, current: usize, len: usize) -> usize {
+        match self.mode {
+            PlaybackMode::Loop => (current + 1) % len,
+            PlaybackMode::PingPong => {
+                if self.is_reversed && current == 0 || !self.is_reversed && current + 1 == len {
+                    self.is_reversed = !self.is_reversed;
+                }
+                if self.is_reversed { current - 1 } else { current + 1 }
+            }
+        }
+    }
+}
+
+/// Neighbouring frames drawn faintly behind the one being edited
+#[derive(Debug)]
+pub struct OnionSkin {
+    pub is_enabled: bool,
+    pub before: usize,
+    pub after: usize,
+    /// Of the nearest frames; further ones fade out
+    pub opacity: f32,
+}
+
+impl Default for OnionSkin {
+    fn default() -> Self {
+        Self {
+            is_enabled: false,
+            before: 1,
+            after: 1,
+            opacity: 0.4,
+        }
+    }
+}
+
+impl OnionSkin {
+    const BEFORE_TINT: Color = Color::new(255, 64, 64, 255);
+    const AFTER_TINT: Color = Color::new(64, 160, 255, 255);
+
+    /// Frames to draw around `current` with their tints, furthest first
+    pub fn layers(&self, current: usize, len: usize) -> Vec<(usize, Color)> {
+        if !self.is_enabled {
+            return Vec::new();
+        }
+        let tint = |base: Color, distance: usize| {
+            let alpha = self.opacity/distance as f32;
+            Color::new(base.r, base.g, base.b, (alpha.clamp(0.0, 1.0)*255.0) as u8)
+        };
+        let before = (1..=self.before.min(current)).rev()
+            .map(|k| (current - k, tint(Self::BEFORE_TINT, k)));
+        let after = (1..=self.after).rev()
+            .filter(|k| current + k < len)
+            .map(|k| (current + k, tint(Self::AFTER_TINT, k)));
+        before.chain(after).collect()
+    }
+}
+
+pub enum TimelineCommand {
+    Select(usize),
+    Add,
+    Duplicate,
+    Delete,
+    /// Swap the current frame with the one this far away
+    Move(isize),
+    TogglePlayback,
+    SetMode(PlaybackMode),
+    SetOnionSkin(bool),
+    SetOnionOpacity(f32),
+    /// Duration of the current frame in milliseconds
+    SetDuration(u32),
+    /// Set every frame's duration to match this rate
+    SetFps(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Duration,
+    Fps,
+}
+
+/// Strip of frame thumbnails and animation controls
+pub struct Timeline {
+    pub playback: Playback,
+    pub onion_skin: OnionSkin,
+    editing: Option<Field>,
+    duration: i32,
+    fps: i32,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            playback: Playback::default(),
+            onion_skin: OnionSkin::default(),
+            editing: None,
+            duration: AnimFrame::DEFAULT_DURATION as i32,
+            fps: (1000/AnimFrame::DEFAULT_DURATION) as i32,
+        }
+    }
+}
+
+impl Timeline {
+    pub const HEIGHT: f32 = 76.0;
+    const PANEL_WIDTH: f32 = 300.0;
+    const THUMB_SIZE: f32 = 56.0;
+    const THUMB_GAP: f32 = 6.0;
+
+    /// Typing into one of the number fields, so keys shouldn't trigger shortcuts
+    #[inline]
+    pub fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    /// Draw the timeline into `bounds`, returning the command chosen by the user, if any.
+    /// `canvases` holds every frame in order.
+    pub fn draw<D: RaylibDraw>(
+        &mut self,
+        d: &mut D,
+        bounds: Rectangle,
+        mouse_pos: Vector2,
+        is_clicked: bool,
+        canvases: &[&RenderTexture2D],
+        current: usize,
+        current_duration: u32,
+    ) -> Option<TimelineCommand> {
+        let mut command = None;
+
+        d.draw_rectangle_rec(bounds, Color::new(16, 16, 16, 255));
+
+        // controls
+        let x = bounds.x + 6.0;
+        let row = |i: f32| bounds.y + 6.0 + i*24.0;
+        let button = |i: f32, col: f32, width: f32| Rectangle::new(x + col, row(i), width, 20.0);
+
+        let buttons = [
+            ("+", TimelineCommand::Add),
+            ("Dup", TimelineCommand::Duplicate),
+            ("Del", TimelineCommand::Delete),
+            ("<", TimelineCommand::Move(-1)),
+            (">", TimelineCommand::Move(1)),
+        ];
+        for (i, (text, cmd)) in buttons.into_iter().enumerate() {
+            if d.gui_button(button(0.0, i as f32*44.0, 40.0), text) {
+                command = Some(cmd);
+            }
+        }
+
+        let play_text = if self.playback.is_playing() { "Stop" } else { "Play" };
+        if d.gui_button(button(1.0, 0.0, 52.0), play_text) {
+            command = Some(TimelineCommand::TogglePlayback);
+        }
+        let mut mode = self.playback.mode as i32;
+        d.gui_toggle_group(button(1.0, 56.0, 60.0), "Loop;Ping-pong", &mut mode);
+        let mode = if mode == 0 { PlaybackMode::Loop } else { PlaybackMode::PingPong };
+        if mode != self.playback.mode {
+            command = Some(TimelineCommand::SetMode(mode));
+        }
+
+        let mut is_onion = self.onion_skin.is_enabled;
+        d.gui_check_box(Rectangle::new(x, row(2.0) + 2.0, 16.0, 16.0), "Onion", &mut is_onion);
+        if is_onion != self.onion_skin.is_enabled {
+            command = Some(TimelineCommand::SetOnionSkin(is_onion));
+        }
+        let mut opacity = self.onion_skin.opacity;
+        d.gui_slider_bar(button(2.0, 70.0, 100.0), "", "", &mut opacity, 0.0, 1.0);
+        if opacity != self.onion_skin.opacity {
+            command = Some(TimelineCommand::SetOnionOpacity(opacity));
+        }
+
+        if self.editing != Some(Field::Duration) {
+            self.duration = current_duration as i32;
+        }
+        let old_duration = self.duration;
+        if d.gui_value_box(button(1.0, 230.0, 60.0), "ms ", &mut self.duration, 1, 10000, self.editing == Some(Field::Duration)) {
+            self.editing = if self.editing == Some(Field::Duration) { None } else { Some(Field::Duration) };
+        }
+        if self.duration != old_duration {
+            command = Some(TimelineCommand::SetDuration(self.duration.max(1) as u32));
+        }
+
+        let old_fps = self.fps;
+        if d.gui_value_box(button(2.0, 230.0, 60.0), "FPS ", &mut self.fps, 1, 100, self.editing == Some(Field::Fps)) {
+            self.editing = if self.editing == Some(Field::Fps) { None } else { Some(Field::Fps) };
+        }
+        if self.fps != old_fps {
+            command = Some(TimelineCommand::SetFps(self.fps.max(1) as u32));
+        }
+
+        // thumbnails
+        let step = Self::THUMB_SIZE + Self::THUMB_GAP;
+        let strip_x = bounds.x + Self::PANEL_WIDTH;
+        let visible = (((bounds.width - Self::PANEL_WIDTH)/step) as usize).max(1);
+        let first = (current + 1).saturating_sub(visible);
+        for (i, canvas) in canvases.iter().enumerate().skip(first).take(visible) {
+            let cell = Rectangle::new(
+                strip_x + (i - first) as f32*step,
+                bounds.y + (bounds.height - Self::THUMB_SIZE)*0.5,
+                Self::THUMB_SIZE,
+                Self::THUMB_SIZE,
+            );
+            let (w, h) = (canvas.texture.width as f32, canvas.texture.height as f32);
+            let scale = (Self::THUMB_SIZE/w).min(Self::THUMB_SIZE/h);
+            let thumb = Rectangle::new(
+                cell.x + (cell.width  - w*scale)*0.5,
+                cell.y + (cell.height - h*scale)*0.5,
+                w*scale,
+                h*scale,
+            );
+            d.draw_rectangle_rec(cell, Color::new(42, 42, 42, 255));
+            d.draw_texture_direct(*canvas, thumb);
+            let outline = if i == current { Color::WHITE } else { Color::GRAY };
+            d.draw_rectangle_lines_ex(cell, 1.0, outline);
+            d.draw_text(&(i + 1).to_string(), cell.x as i32 + 2, cell.y as i32 + 2, 10, outline);
+
+            if is_clicked && cell.check_collision_point_rec(mouse_pos) {
+                command = Some(TimelineCommand::Select(i));
+            }
+        }
+
+        command
+    }
+}
@@ -67,3 +67,26 @@ pub fn flood_fill(img: &mut Image, x: i32, y: i32, new_color: Color) {
         }
     }
 }
+
+/// Like [`flood_fill`], but spreading across the edges of the image along the wrapping axes
+pub fn flood_fill_wrapping(img: &mut Image, x: i32, y: i32, new_color: Color, wrap_x: bool, wrap_y: bool) {
+    let (width, height) = (img.width, img.height);
+    let x = if wrap_x { x.rem_euclid(width) } else { x };
+    let y = if wrap_y { y.rem_euclid(height) } else { y };
+    let Some(start_color) = get_color(img, x, y) else { return; };
+    if start_color == new_color { return; }
+
+    img.draw_pixel(x, y, new_color);
+    let mut s = VecDeque::from([(x, y)]);
+    while let Some((x, y)) = s.pop_front() {
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let nx = if wrap_x { (x + dx).rem_euclid(width) } else { x + dx };
+            let ny = if wrap_y { (y + dy).rem_euclid(height) } else { y + dy };
+            // filled pixels no longer match, so nothing is visited twice
+            if get_color(img, nx, ny) == Some(start_color) {
+                img.draw_pixel(nx, ny, new_color);
+                s.push_back((nx, ny));
+            }
+        }
+    }
+}
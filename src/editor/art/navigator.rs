@@ -0,0 +1,123 @@
+use raylib::prelude::*;
+use amygui::prelude::*;
+use super::view::{self, View};
+
+/// Thumbnail of the whole canvas in a corner of the viewport, showing which part of it is in view
+#[derive(Debug)]
+pub struct Navigator {
+    pub is_visible: bool,
+    is_dragging: bool,
+}
+
+impl Navigator {
+    /// Largest size of the thumbnail
+    const MAX_WIDTH: f32 = 160.0;
+    const MAX_HEIGHT: f32 = 120.0;
+    const PADDING: f32 = 4.0;
+    /// Space between the panel and the edges of the viewport
+    const MARGIN: f32 = 8.0;
+
+    pub const fn new() -> Self {
+        Self {
+            is_visible: true,
+            is_dragging: false,
+        }
+    }
+
+    #[inline]
+    pub const fn is_dragging(&self) -> bool {
+        self.is_dragging
+    }
+
+    /// Where the thumbnail of a `width` x `height` canvas goes in `viewport`, keeping its aspect ratio
+    fn thumbnail_rec(viewport: Rectangle, width: f32, height: f32) -> Rectangle {
+        let scale = (Self::MAX_WIDTH/width).min(Self::MAX_HEIGHT/height);
+        let (thumb_width, thumb_height) = ((width*scale).max(1.0), (height*scale).max(1.0));
+        Rectangle {
+            x: viewport.x + viewport.width  - Self::MARGIN - Self::PADDING - thumb_width,
+            y: viewport.y + viewport.height - Self::MARGIN - Self::PADDING - thumb_height,
+            width: thumb_width,
+            height: thumb_height,
+        }
+    }
+
+    /// The panel around the thumbnail
+    pub fn panel_rec(viewport: Rectangle, width: f32, height: f32) -> Rectangle {
+        let thumb = Self::thumbnail_rec(viewport, width, height);
+        Rectangle {
+            x: thumb.x - Self::PADDING,
+            y: thumb.y - Self::PADDING,
+            width:  thumb.width  + Self::PADDING*2.0,
+            height: thumb.height + Self::PADDING*2.0,
+        }
+    }
+
+    /// Drag to move the view over the part of the canvas under the mouse, and scroll to zoom it; returns whether the view changed
+    pub fn update(&mut self, rl: &RaylibHandle, view: &mut View, viewport: Rectangle, width: f32, height: f32) -> bool {
+        if !self.is_visible {
+            self.is_dragging = false;
+            return false;
+        }
+        let mouse_pos = rl.get_mouse_position();
+        let panel = Self::panel_rec(viewport, width, height);
+        let thumb = Self::thumbnail_rec(viewport, width, height);
+        let pivot = view::pivot(viewport);
+        let is_hovered = panel.check_collision_point_rec(mouse_pos);
+
+        if is_hovered && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.is_dragging = true;
+        } else if rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.is_dragging = false;
+        }
+
+        let mut is_changed = false;
+        if self.is_dragging {
+            let target = Vector2::new(
+                ((mouse_pos.x - thumb.x)/thumb.width ).clamp(0.0, 1.0)*width,
+                ((mouse_pos.y - thumb.y)/thumb.height).clamp(0.0, 1.0)*height,
+            );
+            // the pivot stays put when rotating, so this centers the target whatever the rotation
+            let pan = pivot/view.zoom - target;
+            if pan != view.pan {
+                view.pan = pan;
+                is_changed = true;
+            }
+        }
+
+        let scroll = rl.get_mouse_wheel_move();
+        if is_hovered && scroll != 0.0 {
+            view.zoom_about(view.scrolled_zoom(scroll), pivot, pivot);
+            is_changed = true;
+        }
+        is_changed
+    }
+
+    pub fn draw<D: RaylibDraw>(&self, d: &mut D, viewport: Rectangle, canvas: &RenderTexture2D, view: &View) {
+        if !self.is_visible { return; }
+        let (width, height) = (canvas.texture.width as f32, canvas.texture.height as f32);
+        let panel = Self::panel_rec(viewport, width, height);
+        let thumb = Self::thumbnail_rec(viewport, width, height);
+
+        d.draw_rectangle_rec(panel, Color::new(24, 24, 24, 230));
+        d.draw_rectangle_lines_ex(panel, 1.0, Color::new(64, 64, 64, 255));
+        d.draw_rectangle_rec(thumb, Color::new(42, 42, 42, 255));
+        // drawn straight from the canvas, so it is never out of date and never needs downloading
+        d.draw_texture_direct(canvas, thumb);
+
+        // the corners of the viewport on the canvas, which are a rotated rectangle when the view is rotated
+        let pivot = view::pivot(viewport);
+        let corners = [
+            Vector2::new(viewport.x, viewport.y),
+            Vector2::new(viewport.x + viewport.width, viewport.y),
+            Vector2::new(viewport.x + viewport.width, viewport.y + viewport.height),
+            Vector2::new(viewport.x, viewport.y + viewport.height),
+        ].map(|corner| {
+            let pos = view.to_canvas(corner, pivot);
+            Vector2::new(thumb.x + pos.x/width*thumb.width, thumb.y + pos.y/height*thumb.height)
+        });
+        let mut d = d.begin_scissor_mode(panel.x as i32, panel.y as i32, panel.width as i32, panel.height as i32);
+        for (i, &corner) in corners.iter().enumerate() {
+            d.draw_line_ex(corner, corners[(i + 1) % corners.len()], 1.0, Color::RED);
+        }
+    }
+}
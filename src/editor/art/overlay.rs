@@ -0,0 +1,272 @@
+use raylib::prelude::*;
+
+/// Lines drawn over the canvas to line things up with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridSettings {
+    /// Outline every pixel when zoomed in far enough
+    pub is_pixel_grid_enabled: bool,
+    /// Zoom factor from which the pixel grid shows
+    pub pixel_grid_min_zoom: f32,
+    pub is_grid_enabled: bool,
+    /// Size of a grid cell, in pixels
+    pub width: i32,
+    pub height: i32,
+    /// Where the first grid line is, in pixels
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub color: Color,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            is_pixel_grid_enabled: true,
+            pixel_grid_min_zoom: 8.0,
+            is_grid_enabled: false,
+            width: 16,
+            height: 16,
+            offset_x: 0,
+            offset_y: 0,
+            color: Color::new(0, 160, 255, 128),
+        }
+    }
+}
+
+/// A line across the whole canvas, on the edge before the given pixel row or column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Guide {
+    Horizontal(i32),
+    Vertical(i32),
+}
+
+#[derive(Debug, Default)]
+pub struct Overlay {
+    pub grid: GridSettings,
+    pub guides: Vec<Guide>,
+    pub is_showing_guides: bool,
+    /// Pull selections to nearby grid lines and guides
+    pub is_snapping: bool,
+    /// Index of the guide being dragged
+    dragging: Option<usize>,
+}
+
+impl Overlay {
+    /// Thickness of the rulers along the top and left of the canvas viewport
+    pub const RULER_SIZE: f32 = 16.0;
+    /// How close, in screen pixels, something has to be to a line to snap to it
+    const SNAP_DISTANCE: f32 = 6.0;
+    const GUIDE_COLOR: Color = Color::new(0, 255, 255, 200);
+
+    pub fn new() -> Self {
+        Self {
+            is_showing_guides: true,
+            is_snapping: true,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub const fn is_dragging(&self) -> bool {
+        self.dragging.is_some()
+    }
+
+    /// The nearest grid line or guide to `value`, a canvas coordinate across the lines,
+    /// if snapping is on and one is close enough at this zoom
+    pub fn snap(&self, value: f32, is_vertical: bool, zoom: f32) -> Option<i32> {
+        if !self.is_snapping {
+            return None;
+        }
+        let guides = self.guides.iter()
+            .filter(|_| self.is_showing_guides)
+            .filter_map(|guide| match (*guide, is_vertical) {
+                (Guide::Vertical(x), true) => Some(x),
+                (Guide::Horizontal(y), false) => Some(y),
+                _ => None,
+            });
+        let (size, offset) = if is_vertical { (self.grid.width, self.grid.offset_x) } else { (self.grid.height, self.grid.offset_y) };
+        let grid_line = (self.grid.is_grid_enabled && size > 0)
+            .then(|| offset + ((value - offset as f32)/size as f32).round() as i32*size);
+
+        guides.chain(grid_line)
+            .map(|line| (line, (line as f32 - value).abs()))
+            .filter(|&(_, distance)| distance*zoom <= Self::SNAP_DISTANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(line, _)| line)
+    }
+
+    /// Drag guides out of the rulers, or back into them to remove them; returns whether any moved.
+    /// Pressing a ruler next to the marker of an existing guide picks it up instead of making a new one.
+    pub fn update_guides(&mut self, rl: &RaylibHandle, top_ruler: Rectangle, left_ruler: Rectangle, canvas_mouse_pos: Vector2, to_screen: impl Fn(Vector2) -> Vector2) -> bool {
+        let mouse_pos = rl.get_mouse_position();
+        let edge = (canvas_mouse_pos.x.round() as i32, canvas_mouse_pos.y.round() as i32);
+
+        if self.dragging.is_none() && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            let is_on_top = top_ruler.check_collision_point_rec(mouse_pos);
+            let is_on_left = !is_on_top && left_ruler.check_collision_point_rec(mouse_pos);
+            if is_on_top || is_on_left {
+                // markers for vertical guides are on the top ruler and horizontal ones on the left
+                let grabbed = self.guides.iter().position(|guide| match *guide {
+                    Guide::Vertical(x)   => is_on_top  && (to_screen(Vector2::new(x as f32, 0.0)).x - mouse_pos.x).abs() <= 4.0,
+                    Guide::Horizontal(y) => is_on_left && (to_screen(Vector2::new(0.0, y as f32)).y - mouse_pos.y).abs() <= 4.0,
+                });
+                self.dragging = Some(grabbed.unwrap_or_else(|| {
+                    self.guides.push(if is_on_top { Guide::Horizontal(edge.1) } else { Guide::Vertical(edge.0) });
+                    self.guides.len() - 1
+                }));
+                self.is_showing_guides = true;
+                return true;
+            }
+            return false;
+        }
+
+        let Some(index) = self.dragging else { return false; };
+        let old_guide = self.guides[index];
+        if rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.dragging = None;
+            let is_dropped_on_ruler = match old_guide {
+                Guide::Horizontal(_) => top_ruler.check_collision_point_rec(mouse_pos),
+                Guide::Vertical(_) => left_ruler.check_collision_point_rec(mouse_pos),
+            };
+            if is_dropped_on_ruler {
+                self.guides.remove(index);
+            }
+            return true;
+        }
+        self.guides[index] = match old_guide {
+            Guide::Horizontal(_) => Guide::Horizontal(edge.1),
+            Guide::Vertical(_) => Guide::Vertical(edge.0),
+        };
+        self.guides[index] != old_guide
+    }
+
+    /// Pixel grid, custom grid and guides, clipped to the part of the canvas within `viewport`
+    pub fn draw<D: RaylibDraw>(&self, d: &mut D, viewport: Rectangle, canvas_rec: Rectangle, zoom: f32) {
+        let Some(visible) = viewport.get_collision_rec(&canvas_rec) else { return; };
+        // canvas columns and rows at least partly in view
+        let first_x = ((visible.x - canvas_rec.x)/zoom).floor() as i32;
+        let last_x  = ((visible.x + visible.width  - canvas_rec.x)/zoom).ceil() as i32;
+        let first_y = ((visible.y - canvas_rec.y)/zoom).floor() as i32;
+        let last_y  = ((visible.y + visible.height - canvas_rec.y)/zoom).ceil() as i32;
+        let vertical = |d: &mut D, x: i32, color: Color| {
+            let screen_x = canvas_rec.x + x as f32*zoom;
+            d.draw_line_v(Vector2::new(screen_x, visible.y), Vector2::new(screen_x, visible.y + visible.height), color);
+        };
+        let horizontal = |d: &mut D, y: i32, color: Color| {
+            let screen_y = canvas_rec.y + y as f32*zoom;
+            d.draw_line_v(Vector2::new(visible.x, screen_y), Vector2::new(visible.x + visible.width, screen_y), color);
+        };
+
+        if self.grid.is_pixel_grid_enabled && zoom >= self.grid.pixel_grid_min_zoom {
+            let color = Color::new(128, 128, 128, 64);
+            for x in first_x..=last_x {
+                vertical(d, x, color);
+            }
+            for y in first_y..=last_y {
+                horizontal(d, y, color);
+            }
+        }
+
+        // too dense to be any use when zoomed far out
+        let grid = &self.grid;
+        if grid.is_grid_enabled && grid.width as f32*zoom >= 4.0 && grid.height as f32*zoom >= 4.0 {
+            let first_line = |first: i32, offset: i32, size: i32| offset + (first - offset).div_euclid(size)*size;
+            for x in (first_line(first_x, grid.offset_x, grid.width)..=last_x).step_by(grid.width as usize) {
+                vertical(d, x, grid.color);
+            }
+            for y in (first_line(first_y, grid.offset_y, grid.height)..=last_y).step_by(grid.height as usize) {
+                horizontal(d, y, grid.color);
+            }
+        }
+
+        if self.is_showing_guides {
+            for guide in &self.guides {
+                match *guide {
+                    Guide::Vertical(x) => {
+                        let screen_x = canvas_rec.x + x as f32*zoom;
+                        d.draw_line_v(Vector2::new(screen_x, viewport.y), Vector2::new(screen_x, viewport.y + viewport.height), Self::GUIDE_COLOR);
+                    }
+                    Guide::Horizontal(y) => {
+                        let screen_y = canvas_rec.y + y as f32*zoom;
+                        d.draw_line_v(Vector2::new(viewport.x, screen_y), Vector2::new(viewport.x + viewport.width, screen_y), Self::GUIDE_COLOR);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The rulers, measuring canvas pixels from the canvas' top left, with a marker for every guide
+    pub fn draw_rulers<D: RaylibDraw>(&self, d: &mut D, top_ruler: Rectangle, left_ruler: Rectangle, canvas_rec: Rectangle, zoom: f32) {
+        const TICK_COLOR: Color = Color::new(160, 160, 160, 255);
+        d.draw_rectangle_rec(top_ruler, Color::new(24, 24, 24, 255));
+        d.draw_rectangle_rec(left_ruler, Color::new(24, 24, 24, 255));
+
+        // labelled ticks at least 50 screen pixels apart, on a 1-2-5 sequence
+        let major = std::iter::successors(Some(1), |step| Some(step*10))
+            .flat_map(|decade| [decade, decade*2, decade*5])
+            .find(|&step| step as f32*zoom >= 50.0)
+            .unwrap();
+        let minor = if major % 5 == 0 { major/5 } else { (major/2).max(1) };
+        let show_minor = minor < major && minor as f32*zoom >= 4.0;
+
+        let first = |start: f32, origin: f32| ((start - origin)/zoom/minor as f32).floor() as i32*minor;
+        let start_x = first(top_ruler.x, canvas_rec.x);
+        let end_x = ((top_ruler.x + top_ruler.width - canvas_rec.x)/zoom).ceil() as i32;
+        for x in (start_x..=end_x).step_by(minor as usize) {
+            let is_major = x % major == 0;
+            if !is_major && !show_minor {
+                continue;
+            }
+            let screen_x = (canvas_rec.x + x as f32*zoom).round();
+            let length = if is_major { top_ruler.height } else { top_ruler.height*0.3 };
+            let bottom = top_ruler.y + top_ruler.height;
+            d.draw_line_v(Vector2::new(screen_x + 0.5, bottom - length), Vector2::new(screen_x + 0.5, bottom), TICK_COLOR);
+            if is_major {
+                d.draw_text(&x.to_string(), screen_x as i32 + 3, top_ruler.y as i32 + 2, 10, TICK_COLOR);
+            }
+        }
+
+        let start_y = first(left_ruler.y, canvas_rec.y);
+        let end_y = ((left_ruler.y + left_ruler.height - canvas_rec.y)/zoom).ceil() as i32;
+        for y in (start_y..=end_y).step_by(minor as usize) {
+            let is_major = y % major == 0;
+            if !is_major && !show_minor {
+                continue;
+            }
+            let screen_y = (canvas_rec.y + y as f32*zoom).round();
+            let length = if is_major { left_ruler.width } else { left_ruler.width*0.3 };
+            let right = left_ruler.x + left_ruler.width;
+            d.draw_line_v(Vector2::new(right - length, screen_y + 0.5), Vector2::new(right, screen_y + 0.5), TICK_COLOR);
+            if is_major {
+                // no room to write across, so one character per line
+                for (i, c) in y.to_string().chars().enumerate() {
+                    d.draw_text(&c.to_string(), left_ruler.x as i32 + 2, screen_y as i32 + 3 + i as i32*9, 10, TICK_COLOR);
+                }
+            }
+        }
+
+        for guide in &self.guides {
+            match *guide {
+                Guide::Vertical(x) => {
+                    let screen_x = canvas_rec.x + x as f32*zoom;
+                    d.draw_triangle(
+                        Vector2::new(screen_x - 4.0, top_ruler.y),
+                        Vector2::new(screen_x, top_ruler.y + top_ruler.height),
+                        Vector2::new(screen_x + 4.0, top_ruler.y),
+                        Self::GUIDE_COLOR,
+                    );
+                }
+                Guide::Horizontal(y) => {
+                    let screen_y = canvas_rec.y + y as f32*zoom;
+                    d.draw_triangle(
+                        Vector2::new(left_ruler.x, screen_y - 4.0),
+                        Vector2::new(left_ruler.x, screen_y + 4.0),
+                        Vector2::new(left_ruler.x + left_ruler.width, screen_y),
+                        Self::GUIDE_COLOR,
+                    );
+                }
+            }
+        }
+        // the corner where the rulers meet
+        d.draw_rectangle_rec(Rectangle { height: top_ruler.height, ..left_ruler }, Color::new(16, 16, 16, 255));
+    }
+}
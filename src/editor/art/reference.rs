@@ -0,0 +1,321 @@
+use std::path::Path;
+use raylib::prelude::*;
+use crate::{brush::Brush, codec::format, error::{self, Error}, raster::{Raster, adjust::Adjustment}};
+use super::view::View;
+
+/// What dragging on a reference does
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Drag {
+    /// Moving the panel by its header; the mouse's offset from the panel's corner
+    Move(Vector2),
+    Resize,
+    Pan,
+    /// Picking the brush color from under the mouse
+    Pick,
+}
+
+/// What happened to a reference in [`Reference::update`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Response {
+    /// The mouse isn't on the reference, so the input is left for the canvas
+    Outside,
+    /// The reference took the input
+    Inside { is_changed: bool },
+    Closed,
+}
+
+/// An image to look at while painting, which is never part of the artwork
+///
+/// It either floats in its own panel with its own pan and zoom, or is pinned over the canvas and moves with it.
+pub struct Reference {
+    name: String,
+    raster: Raster,
+    texture: Texture2D,
+    gray_texture: Texture2D,
+    pub opacity: f32,
+    pub is_grayscale: bool,
+    /// Canvas position of the image's top left corner and canvas pixels per image pixel, while pinned
+    pin: Option<(Vector2, f32)>,
+    /// The whole panel, header included, in screen pixels
+    panel: Rectangle,
+    /// Pan and zoom of the image in the panel, relative to the panel's content
+    view: View,
+    drag: Option<Drag>,
+}
+
+impl Reference {
+    const HEADER_HEIGHT: f32 = 18.0;
+    const BUTTON_SIZE: f32 = 14.0;
+    const GRIP_SIZE: f32 = 10.0;
+    const MIN_SIZE: f32 = 80.0;
+    /// Largest size of a new panel's content
+    const INITIAL_SIZE: f32 = 240.0;
+
+    /// Load an image into a new panel with its top left corner at `pos`
+    pub fn load(rl: &mut RaylibHandle, thread: &RaylibThread, path: &Path, pos: Vector2) -> error::Result<Self> {
+        let raster = format::read(path)?;
+        let mut gray = raster.clone();
+        Adjustment::Desaturate.apply(&mut gray);
+
+        let (width, height) = (raster.width() as f32, raster.height() as f32);
+        let zoom = (Self::INITIAL_SIZE/width).min(Self::INITIAL_SIZE/height).min(1.0);
+        let panel = Rectangle::new(pos.x, pos.y, (width*zoom).max(Self::MIN_SIZE), (height*zoom).max(Self::MIN_SIZE) + Self::HEADER_HEIGHT);
+        Ok(Self {
+            name: path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned()),
+            texture: rl.load_texture_from_image(thread, &super::raster_to_image(&raster)).map_err(Error::gpu)?,
+            gray_texture: rl.load_texture_from_image(thread, &super::raster_to_image(&gray)).map_err(Error::gpu)?,
+            raster,
+            opacity: 1.0,
+            is_grayscale: false,
+            pin: None,
+            panel,
+            view: View { zoom, ..View::default() },
+            drag: None,
+        })
+    }
+
+    #[inline]
+    pub const fn is_pinned(&self) -> bool {
+        self.pin.is_some()
+    }
+
+    #[inline]
+    pub const fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Take the reference off the canvas and back into its panel
+    pub fn unpin(&mut self) {
+        self.pin = None;
+    }
+
+    /// Whether `pos` is on the panel, which is hidden while pinned
+    pub fn contains(&self, pos: Vector2) -> bool {
+        !self.is_pinned() && self.panel.check_collision_point_rec(pos)
+    }
+
+    fn header_rec(&self) -> Rectangle {
+        Rectangle { height: Self::HEADER_HEIGHT, ..self.panel }
+    }
+
+    fn content_rec(&self) -> Rectangle {
+        Rectangle {
+            y: self.panel.y + Self::HEADER_HEIGHT,
+            height: self.panel.height - Self::HEADER_HEIGHT,
+            ..self.panel
+        }
+    }
+
+    fn grip_rec(&self) -> Rectangle {
+        Rectangle::new(
+            self.panel.x + self.panel.width  - Self::GRIP_SIZE,
+            self.panel.y + self.panel.height - Self::GRIP_SIZE,
+            Self::GRIP_SIZE,
+            Self::GRIP_SIZE,
+        )
+    }
+
+    /// Grayscale, pin and close, right to left from the end of the header
+    fn button_recs(&self) -> [Rectangle; 3] {
+        let gap = (Self::HEADER_HEIGHT - Self::BUTTON_SIZE)*0.5;
+        let button = |i: f32| Rectangle::new(
+            self.panel.x + self.panel.width - (Self::BUTTON_SIZE + gap)*i,
+            self.panel.y + gap,
+            Self::BUTTON_SIZE,
+            Self::BUTTON_SIZE,
+        );
+        [button(3.0), button(2.0), button(1.0)]
+    }
+
+    /// The color shown at `pos` in image pixels, fully opaque so it can go straight into the brush
+    fn color_at(&self, pos: Vector2) -> Option<Color> {
+        let [r, g, b, _] = self.raster.get_checked(pos.x.floor() as i32, pos.y.floor() as i32)?;
+        let mut pixel = Raster::filled(1, 1, [r, g, b, 255]);
+        if self.is_grayscale {
+            Adjustment::Desaturate.apply(&mut pixel);
+        }
+        let [r, g, b, a] = pixel.get(0, 0);
+        Some(Color::new(r, g, b, a))
+    }
+
+    /// Set the brush color from under the mouse, given as a position on the image
+    fn pick(&self, brush: &mut Brush, pos: Vector2) -> bool {
+        match self.color_at(pos) {
+            Some(color) if color != brush.color => {
+                brush.color = color;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Handle dragging the panel and its controls, or Alt+clicking the image while pinned to pick a color.
+    /// `canvas_view` and `pivot` are the active view of the canvas, for pinning the image where it is on screen.
+    pub fn update(&mut self, rl: &RaylibHandle, brush: &mut Brush, canvas_view: &View, pivot: Vector2, canvas_mouse_pos: Vector2) -> Response {
+        let mouse_pos = rl.get_mouse_position();
+        let content = self.content_rec();
+        let content_origin = Vector2::new(content.x, content.y);
+        // where the mouse is on the image
+        let image_pos = match self.pin {
+            Some((pos, scale)) => (canvas_mouse_pos - pos)/scale,
+            None => self.view.to_canvas(mouse_pos - content_origin, Vector2::zero()),
+        };
+
+        if let Some(drag) = self.drag {
+            let button = if drag == Drag::Pan { MouseButton::MOUSE_BUTTON_MIDDLE } else { MouseButton::MOUSE_BUTTON_LEFT };
+            if rl.is_mouse_button_released(button) {
+                self.drag = None;
+                return Response::Inside { is_changed: false };
+            }
+            let old_panel = self.panel;
+            match drag {
+                Drag::Move(offset) => {
+                    self.panel.x = mouse_pos.x - offset.x;
+                    self.panel.y = mouse_pos.y - offset.y;
+                }
+                Drag::Resize => {
+                    self.panel.width  = (mouse_pos.x - self.panel.x).max(Self::MIN_SIZE);
+                    self.panel.height = (mouse_pos.y - self.panel.y).max(Self::MIN_SIZE + Self::HEADER_HEIGHT);
+                }
+                Drag::Pan => {
+                    let movement = rl.get_mouse_delta();
+                    if movement.length_sqr() > 0.0 {
+                        self.view.pan = self.view.pan + self.view.to_canvas_delta(movement);
+                        return Response::Inside { is_changed: true };
+                    }
+                }
+                Drag::Pick => {
+                    self.pick(brush, image_pos);
+                }
+            }
+            return Response::Inside { is_changed: self.panel != old_panel };
+        }
+
+        if self.is_pinned() {
+            let is_alt_down = rl.is_key_down(KeyboardKey::KEY_LEFT_ALT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_ALT);
+            if is_alt_down && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && self.color_at(image_pos).is_some() {
+                self.drag = Some(Drag::Pick);
+                self.pick(brush, image_pos);
+                return Response::Inside { is_changed: false };
+            }
+            return Response::Outside;
+        }
+
+        if !self.panel.check_collision_point_rec(mouse_pos) {
+            return Response::Outside;
+        }
+
+        let mut is_changed = false;
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            let [grayscale_button, pin_button, close_button] = self.button_recs();
+            if close_button.check_collision_point_rec(mouse_pos) {
+                return Response::Closed;
+            } else if grayscale_button.check_collision_point_rec(mouse_pos) {
+                self.is_grayscale = !self.is_grayscale;
+            } else if pin_button.check_collision_point_rec(mouse_pos) {
+                // pinned where it is on screen, as far as the canvas' rotation allows
+                let image_origin = content_origin + self.view.pan*self.view.zoom;
+                self.pin = Some((canvas_view.to_canvas(image_origin, pivot), self.view.zoom/canvas_view.zoom));
+            } else if self.header_rec().check_collision_point_rec(mouse_pos) {
+                self.drag = Some(Drag::Move(mouse_pos - Vector2::new(self.panel.x, self.panel.y)));
+            } else if self.grip_rec().check_collision_point_rec(mouse_pos) {
+                self.drag = Some(Drag::Resize);
+            } else {
+                self.drag = Some(Drag::Pick);
+                self.pick(brush, image_pos);
+            }
+            is_changed = true;
+        } else if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_MIDDLE) && content.check_collision_point_rec(mouse_pos) {
+            self.drag = Some(Drag::Pan);
+        }
+
+        // scroll the header for opacity and the image to zoom it
+        let scroll = rl.get_mouse_wheel_move();
+        if scroll != 0.0 {
+            if self.header_rec().check_collision_point_rec(mouse_pos) {
+                self.opacity = (self.opacity + scroll*0.05).clamp(0.05, 1.0);
+            } else {
+                let zoom = self.view.scrolled_zoom(scroll);
+                self.view.zoom_about(zoom, mouse_pos - content_origin, Vector2::zero());
+            }
+            is_changed = true;
+        }
+        Response::Inside { is_changed }
+    }
+
+    fn tint(&self) -> Color {
+        Color::new(255, 255, 255, (self.opacity*255.0).round() as u8)
+    }
+
+    fn current_texture(&self) -> &Texture2D {
+        if self.is_grayscale { &self.gray_texture } else { &self.texture }
+    }
+
+    /// Draw the image over the canvas if it's pinned, given the canvas' pan and zoom
+    pub fn draw_pinned<D: RaylibDraw>(&self, d: &mut D, pan: Vector2, zoom: f32) {
+        let Some((pos, scale)) = self.pin else { return; };
+        let texture = self.current_texture();
+        let source = Rectangle::new(0.0, 0.0, texture.width as f32, texture.height as f32);
+        let dest = Rectangle {
+            x: (pan.x + pos.x)*zoom,
+            y: (pan.y + pos.y)*zoom,
+            width:  source.width *scale*zoom,
+            height: source.height*scale*zoom,
+        };
+        d.draw_texture_pro(texture, source, dest, Vector2::zero(), 0.0, self.tint());
+    }
+
+    /// Draw the panel if the reference isn't pinned
+    pub fn draw_panel<D: RaylibDraw>(&self, d: &mut D) {
+        if self.is_pinned() { return; }
+        const TEXT_COLOR: Color = Color::new(200, 200, 200, 255);
+        let header = self.header_rec();
+        let content = self.content_rec();
+
+        d.draw_rectangle_rec(content, Color::new(16, 16, 16, 255));
+
+        // only the part of the image inside the panel, so it can't spill over the canvas
+        let texture = self.current_texture();
+        let zoom = self.view.zoom;
+        let image_rec = Rectangle {
+            x: content.x + self.view.pan.x*zoom,
+            y: content.y + self.view.pan.y*zoom,
+            width:  texture.width  as f32*zoom,
+            height: texture.height as f32*zoom,
+        };
+        if let Some(visible) = content.get_collision_rec(&image_rec) {
+            let source = Rectangle {
+                x: (visible.x - image_rec.x)/zoom,
+                y: (visible.y - image_rec.y)/zoom,
+                width:  visible.width /zoom,
+                height: visible.height/zoom,
+            };
+            d.draw_texture_pro(texture, source, visible, Vector2::zero(), 0.0, self.tint());
+        }
+
+        d.draw_rectangle_rec(header, Color::new(40, 40, 40, 255));
+        let [grayscale_button, pin_button, close_button] = self.button_recs();
+        let opacity = format!("{}%", (self.opacity*100.0).round());
+        let opacity_x = grayscale_button.x as i32 - measure_text(&opacity, 10) - 6;
+        // cut the name short rather than run under the controls
+        let mut name = self.name.clone();
+        while !name.is_empty() && header.x as i32 + 4 + measure_text(&name, 10) > opacity_x - 4 {
+            name.pop();
+        }
+        d.draw_text(&name, header.x as i32 + 4, header.y as i32 + 4, 10, TEXT_COLOR);
+        d.draw_text(&opacity, opacity_x, header.y as i32 + 4, 10, TEXT_COLOR);
+        for (rec, label, is_on) in [(grayscale_button, "G", self.is_grayscale), (pin_button, "P", false), (close_button, "x", false)] {
+            d.draw_rectangle_rec(rec, if is_on { Color::new(90, 90, 90, 255) } else { Color::new(60, 60, 60, 255) });
+            d.draw_text(label, rec.x as i32 + 4, rec.y as i32 + 2, 10, TEXT_COLOR);
+        }
+
+        let grip = self.grip_rec();
+        d.draw_triangle(
+            Vector2::new(grip.x + grip.width, grip.y),
+            Vector2::new(grip.x, grip.y + grip.height),
+            Vector2::new(grip.x + grip.width, grip.y + grip.height),
+            Color::new(90, 90, 90, 255),
+        );
+        d.draw_rectangle_lines_ex(self.panel, 1.0, Color::new(64, 64, 64, 255));
+    }
+}
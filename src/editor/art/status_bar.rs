@@ -0,0 +1,52 @@
+use raylib::prelude::*;
+use crate::raster::Rect;
+
+/// What the status bar shows
+pub struct Status<'a> {
+    /// Pixel under the mouse, if it's over the canvas
+    pub cursor: Option<(i32, i32)>,
+    /// Color of the pixel under the mouse
+    pub color: Option<Color>,
+    pub width: i32,
+    pub height: i32,
+    pub zoom: f32,
+    pub tool: &'a str,
+    pub selection: Option<Rect>,
+}
+
+pub const HEIGHT: f32 = 18.0;
+
+/// Draw the strip along the bottom of the canvas viewport
+pub fn draw<D: RaylibDraw>(d: &mut D, bounds: Rectangle, status: &Status) {
+    const TEXT_COLOR: Color = Color::new(200, 200, 200, 255);
+    d.draw_rectangle_rec(bounds, Color::new(24, 24, 24, 255));
+
+    let y = bounds.y as i32 + 4;
+    let mut x = bounds.x as i32 + 6;
+    let section = |d: &mut D, x: &mut i32, text: &str, width: i32| {
+        d.draw_text(text, *x, y, 10, TEXT_COLOR);
+        *x += width;
+    };
+
+    let cursor = status.cursor.map_or_else(|| "-".to_string(), |(cx, cy)| format!("{cx}, {cy}"));
+    section(d, &mut x, &cursor, 80);
+
+    // swatch of the color under the cursor, over a checker so transparency shows
+    let swatch = Rectangle::new(x as f32, bounds.y + 3.0, 12.0, 12.0);
+    d.draw_rectangle_rec(swatch, Color::new(204, 204, 204, 255));
+    d.draw_rectangle_rec(Rectangle { width: 6.0, height: 6.0, ..swatch }, Color::new(153, 153, 153, 255));
+    d.draw_rectangle_rec(Rectangle::new(swatch.x + 6.0, swatch.y + 6.0, 6.0, 6.0), Color::new(153, 153, 153, 255));
+    if let Some(color) = status.color {
+        d.draw_rectangle_rec(swatch, color);
+    }
+    x += 16;
+    let color = status.color.map_or_else(|| "-".to_string(), |c| format!("#{:02X}{:02X}{:02X}{:02X}", c.r, c.g, c.b, c.a));
+    section(d, &mut x, &color, 80);
+
+    section(d, &mut x, &format!("{} x {}", status.width, status.height), 90);
+    section(d, &mut x, &format!("{}%", (status.zoom*100.0).round()), 60);
+    section(d, &mut x, status.tool, 70);
+    if let Some(sel) = status.selection {
+        section(d, &mut x, &format!("Selection: {} x {}", sel.width, sel.height), 140);
+    }
+}
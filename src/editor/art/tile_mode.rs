@@ -0,0 +1,79 @@
+use raylib::prelude::*;
+
+/// Which edges of the canvas strokes and fills wrap across, for making seamless tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TileMode {
+    #[default]
+    Off,
+    X,
+    Y,
+    Both,
+}
+
+impl TileMode {
+    #[inline]
+    pub const fn wraps_x(self) -> bool {
+        matches!(self, Self::X | Self::Both)
+    }
+
+    #[inline]
+    pub const fn wraps_y(self) -> bool {
+        matches!(self, Self::Y | Self::Both)
+    }
+
+    /// Off, X, Y, both, then off again
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Off => Self::X,
+            Self::X => Self::Y,
+            Self::Y => Self::Both,
+            Self::Both => Self::Off,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::X => "Horizontal",
+            Self::Y => "Vertical",
+            Self::Both => "Both",
+        }
+    }
+
+    /// Copies of the canvas to draw around it, in multiples of its size; includes the canvas itself
+    pub fn repeats(self) -> impl Iterator<Item = (i32, i32)> {
+        let xs = if self.wraps_x() { -1..=1 } else { 0..=0 };
+        let ys = if self.wraps_y() { -1..=1 } else { 0..=0 };
+        ys.flat_map(move |y| xs.clone().map(move |x| (x, y)))
+    }
+
+    /// Move a point on a repeated copy of the canvas onto the canvas itself
+    pub fn wrap(self, pos: Vector2, size: Vector2) -> Vector2 {
+        Vector2 {
+            x: if self.wraps_x() { pos.x.rem_euclid(size.x) } else { pos.x },
+            y: if self.wraps_y() { pos.y.rem_euclid(size.y) } else { pos.y },
+        }
+    }
+
+    /// Translations of a stroke through `points` that overlap the canvas, so that
+    /// painting each one makes the stroke continue across the wrapping edges
+    pub fn offsets(self, points: &[Vector2], radius: f32, size: Vector2) -> Vec<Vector2> {
+        let margin = radius + 1.0;
+        let (min, max) = points.iter().fold(
+            (Vector2::new(f32::INFINITY, f32::INFINITY), Vector2::new(f32::NEG_INFINITY, f32::NEG_INFINITY)),
+            |(min, max), p| (Vector2::new(min.x.min(p.x), min.y.min(p.y)), Vector2::new(max.x.max(p.x), max.y.max(p.y))),
+        );
+        // every whole number of canvas sizes that brings [min, max] onto [0, size)
+        let shifts = |wraps: bool, min: f32, max: f32, size: f32| -> Vec<f32> {
+            if !wraps || points.is_empty() {
+                return vec![0.0];
+            }
+            let first = (-(max + margin)/size).floor() as i32 + 1;
+            let last = ((size - (min - margin))/size).ceil() as i32 - 1;
+            (first..=last).map(|k| k as f32*size).collect()
+        };
+        let xs = shifts(self.wraps_x(), min.x, max.x, size.x);
+        let ys = shifts(self.wraps_y(), min.y, max.y, size.y);
+        ys.iter().flat_map(|&y| xs.iter().map(move |&x| Vector2::new(x, y))).collect()
+    }
+}
@@ -0,0 +1,258 @@
+use raylib::prelude::*;
+use amygui::prelude::*;
+use crate::{error::{self, Error}, raster::{Raster, Rect, transform::{Affine, Sampler, transform}}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Handle {
+    Move,
+    Rotate,
+    /// Corner, clockwise from the top-left
+    Scale(usize),
+    /// Edge, clockwise from the top
+    Skew(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Params {
+    offset: Vector2,
+    angle: f32,
+    scale: Vector2,
+    skew: Vector2,
+}
+
+struct Drag {
+    handle: Handle,
+    start: Vector2,
+    params: Params,
+}
+
+/// Interactive rotate/scale/skew of a piece of the canvas, previewed on the GPU and resampled on commit
+pub struct FreeTransform {
+    source: Raster,
+    texture: Texture2D,
+    origin: Rect,
+    /// Canvas with the source area cleared
+    base: Raster,
+    params: Params,
+    sampler: usize,
+    drag: Option<Drag>,
+}
+
+impl FreeTransform {
+    /// Screen pixels around a handle that still grab it
+    const HANDLE_RADIUS: f32 = 5.0;
+    /// Screen pixels between the top edge and the rotation handle
+    const ROTATE_HANDLE_GAP: f32 = 20.0;
+
+    /// Lift `origin` out of `canvas`; returns `None` if there is nothing there to transform
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, mut canvas: Raster, origin: Rect) -> error::Result<Option<Self>> {
+        let Some(origin) = origin.intersect(&canvas.bounds()) else { return Ok(None); };
+        let source = canvas.crop(origin);
+        canvas.fill_rect(origin, [0; 4]);
+        let texture = rl.load_texture_from_image(thread, &super::raster_to_image(&source)).map_err(Error::gpu)?;
+        Ok(Some(Self {
+            source,
+            texture,
+            origin,
+            base: canvas,
+            params: Params {
+                offset: Vector2::zero(),
+                angle: 0.0,
+                scale: Vector2::one(),
+                skew: Vector2::zero(),
+            },
+            sampler: 0,
+            drag: None,
+        }))
+    }
+
+    /// The canvas as it should look while the transform is in progress
+    pub fn base(&self) -> &Raster {
+        &self.base
+    }
+
+    pub fn sampler(&self) -> Sampler {
+        Sampler::ALL[self.sampler]
+    }
+
+    pub fn cycle_sampler(&mut self) {
+        self.sampler = (self.sampler + 1) % Sampler::ALL.len();
+    }
+
+    #[inline]
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    fn center(&self) -> Vector2 {
+        Vector2::new(
+            self.origin.x as f32 + self.origin.width  as f32*0.5,
+            self.origin.y as f32 + self.origin.height as f32*0.5,
+        )
+    }
+
+    /// Maps from the center of the source into canvas space, without the scale and skew
+    fn placement(&self, params: &Params) -> Affine {
+        let center = self.center() + params.offset;
+        Affine::rotate(params.angle)
+            .then(Affine::translate(center.x, center.y))
+    }
+
+    /// Maps source pixels into canvas space
+    fn affine(&self, params: &Params) -> Affine {
+        Affine::translate(-self.origin.width as f32*0.5, -self.origin.height as f32*0.5)
+            .then(Affine::scale(params.scale.x, params.scale.y))
+            .then(Affine::skew(params.skew.x, params.skew.y))
+            .then(self.placement(params))
+    }
+
+    fn corners(&self) -> [Vector2; 4] {
+        self.affine(&self.params)
+            .corners(self.origin.width as f32, self.origin.height as f32)
+            .map(|(x, y)| Vector2::new(x, y))
+    }
+
+    /// Handle positions in canvas space, as (corners, edge midpoints, rotation handle)
+    fn handles(&self, zoom: f32) -> ([Vector2; 4], [Vector2; 4], Vector2) {
+        let corners = self.corners();
+        let edges = std::array::from_fn(|i| (corners[i] + corners[(i + 1) % 4])*0.5);
+        let up = (edges[0] - edges[2]).normalized();
+        (corners, edges, edges[0] + up*(Self::ROTATE_HANDLE_GAP/zoom))
+    }
+
+    fn handle_at(&self, pos: Vector2, zoom: f32) -> Option<Handle> {
+        let radius = Self::HANDLE_RADIUS/zoom;
+        let is_near = |p: Vector2| p.distance_to(pos) <= radius;
+        let (corners, edges, rotate) = self.handles(zoom);
+        if is_near(rotate) {
+            Some(Handle::Rotate)
+        } else if let Some(i) = corners.iter().position(|&p| is_near(p)) {
+            Some(Handle::Scale(i))
+        } else if let Some(i) = edges.iter().position(|&p| is_near(p)) {
+            Some(Handle::Skew(i))
+        } else {
+            let inverse = self.affine(&self.params).inverse()?;
+            let (u, v) = inverse.apply(pos.x, pos.y);
+            Rect::new(0, 0, self.origin.width, self.origin.height)
+                .contains(u.floor() as i32, v.floor() as i32)
+                .then_some(Handle::Move)
+        }
+    }
+
+    /// Handle mouse input at `pos` in canvas space, returning whether the preview changed
+    pub fn update(&mut self, rl: &RaylibHandle, pos: Vector2, zoom: f32) -> bool {
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.drag = self.handle_at(pos, zoom)
+                .map(|handle| Drag { handle, start: pos, params: self.params });
+        }
+
+        let Some(drag) = &self.drag else { return false; };
+        let start = drag.params;
+        let mut params = start;
+        let (half_w, half_h) = (self.origin.width as f32*0.5, self.origin.height as f32*0.5);
+
+        match drag.handle {
+            Handle::Move => {
+                params.offset = start.offset + (pos - drag.start);
+                if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT) {
+                    // constrain to an axis
+                    let delta = pos - drag.start;
+                    params.offset = start.offset + if delta.x.abs() > delta.y.abs() {
+                        Vector2::new(delta.x, 0.0)
+                    } else {
+                        Vector2::new(0.0, delta.y)
+                    };
+                }
+                params.offset = Vector2::new(params.offset.x.round(), params.offset.y.round());
+            }
+
+            Handle::Rotate => {
+                let center = self.center() + start.offset;
+                let angle_of = |p: Vector2| (p.y - center.y).atan2(p.x - center.x);
+                params.angle = start.angle + angle_of(pos) - angle_of(drag.start);
+                if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT) {
+                    const STEP: f32 = std::f32::consts::PI/12.0;
+                    params.angle = (params.angle/STEP).round()*STEP;
+                }
+            }
+
+            Handle::Scale(corner) => {
+                // position relative to the center, before skew and rotation
+                let Some(inverse) = Affine::skew(start.skew.x, start.skew.y).then(self.placement(&start)).inverse() else { return false; };
+                let (x, y) = inverse.apply(pos.x, pos.y);
+                let sign_x = if corner == 0 || corner == 3 { -1.0 } else { 1.0 };
+                let sign_y = if corner < 2 { -1.0 } else { 1.0 };
+                let clamp_scale = |s: f32| if s.abs() < 0.01 { 0.01f32.copysign(s) } else { s };
+                params.scale = Vector2::new(clamp_scale(x*sign_x/half_w), clamp_scale(y*sign_y/half_h));
+                if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT) {
+                    let uniform = params.scale.x.abs().max(params.scale.y.abs());
+                    params.scale = Vector2::new(uniform.copysign(params.scale.x), uniform.copysign(params.scale.y));
+                }
+            }
+
+            Handle::Skew(edge) => {
+                // position relative to the center, before rotation
+                let Some(inverse) = self.placement(&start).inverse() else { return false; };
+                let (x, y) = inverse.apply(pos.x, pos.y);
+                match edge {
+                    0 | 2 => {
+                        let edge_y = (if edge == 0 { -half_h } else { half_h })*start.scale.y;
+                        params.skew.x = x/edge_y;
+                    }
+                    _ => {
+                        let edge_x = (if edge == 3 { -half_w } else { half_w })*start.scale.x;
+                        params.skew.y = y/edge_x;
+                    }
+                }
+            }
+        }
+
+        if rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.drag = None;
+        }
+
+        let is_changed = params.offset != self.params.offset
+            || params.angle != self.params.angle
+            || params.scale != self.params.scale
+            || params.skew != self.params.skew;
+        self.params = params;
+        is_changed
+    }
+
+    /// Draw the preview and handles, where `to_screen` maps canvas space to the screen
+    pub fn draw<D: RaylibDraw>(&self, d: &mut D, to_screen: impl Fn(Vector2) -> Vector2, zoom: f32) {
+        let corners = self.corners().map(&to_screen);
+        d.draw_texture_quad(&self.texture, corners, Color::WHITE);
+
+        let (_, edges, rotate) = self.handles(zoom);
+        for i in 0..4 {
+            d.draw_line_v(corners[i], corners[(i + 1) % 4], Color::WHITE);
+        }
+        d.draw_line_v(to_screen(edges[0]), to_screen(rotate), Color::WHITE);
+        d.draw_circle_v(to_screen(rotate), Self::HANDLE_RADIUS, Color::WHITE);
+        for p in corners.into_iter().chain(edges.map(&to_screen)) {
+            let r = Self::HANDLE_RADIUS;
+            let rec = Rectangle::new(p.x - r, p.y - r, r*2.0, r*2.0);
+            d.draw_rectangle_rec(rec, Color::BLACK);
+            d.draw_rectangle_lines_ex(rec, 1.0, Color::WHITE);
+        }
+    }
+
+    /// Resample into the canvas, returning the finished canvas and the area now covered
+    pub fn commit(self) -> (Raster, Option<Rect>) {
+        let affine = self.affine(&self.params);
+        let sampler = self.sampler();
+        let Self { source, mut base, .. } = self;
+        let transformed = transform(&source, affine, base.width(), base.height(), sampler);
+        base.draw_over(&transformed, 0, 0);
+        let covered = affine.bounds(source.width() as f32, source.height() as f32).intersect(&base.bounds());
+        (base, covered)
+    }
+
+    /// The canvas as it was before the transform
+    pub fn cancel(self) -> Raster {
+        let Self { source, mut base, origin, .. } = self;
+        base.blit(&source, origin.x, origin.y);
+        base
+    }
+}
@@ -0,0 +1,159 @@
+use raylib::prelude::*;
+
+/// Levels that whole notches of the scroll wheel step between; whole numbers from 1 up keep pixels square
+const ZOOM_LEVELS: [f32; 22] = [
+    1.0/16.0, 1.0/12.0, 1.0/8.0, 1.0/6.0, 1.0/4.0, 1.0/3.0, 1.0/2.0, 2.0/3.0,
+    1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0, 10.0, 12.0, 16.0, 24.0, 32.0, 48.0, 64.0,
+];
+
+pub const MIN_ZOOM: f32 = ZOOM_LEVELS[0];
+pub const MAX_ZOOM: f32 = ZOOM_LEVELS[ZOOM_LEVELS.len() - 1];
+
+/// The middle of the viewport, which the view rotates around
+pub fn pivot(viewport: Rectangle) -> Vector2 {
+    Vector2::new(viewport.x + viewport.width*0.5, viewport.y + viewport.height*0.5)
+}
+
+/// How the canvas is placed on screen
+///
+/// Screen positions are found by offsetting canvas positions by `pan`, scaling by `zoom`,
+/// then rotating by `rotation` around a pivot, which is the middle of the viewport.
+/// None of it changes the canvas itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    /// Offset of the canvas in canvas pixels
+    pub pan: Vector2,
+    pub zoom: f32,
+    /// Clockwise, in degrees from -180 to 180
+    pub rotation: f32,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            pan: Vector2::zero(),
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl View {
+    #[inline]
+    pub fn is_rotated(&self) -> bool {
+        self.rotation != 0.0
+    }
+
+    /// Rotates everything drawn with it around `pivot`, for drawing the canvas and what's over it
+    pub fn camera(&self, pivot: Vector2) -> Camera2D {
+        Camera2D {
+            offset: pivot,
+            target: pivot,
+            rotation: self.rotation,
+            zoom: 1.0,
+        }
+    }
+
+    /// Keep the zoom and rotation within their limits, for views that weren't made by zooming and rotating
+    pub fn clamped(self) -> Self {
+        Self {
+            zoom: self.zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+            rotation: self.rotation.clamp(-180.0, 180.0),
+            ..self
+        }
+    }
+
+    /// Undo the rotation of a screen position, giving where it would be if the view wasn't rotated
+    pub fn unrotate(&self, screen_pos: Vector2, pivot: Vector2) -> Vector2 {
+        (screen_pos - pivot).rotated(-self.rotation.to_radians()) + pivot
+    }
+
+    /// The canvas position under a screen position
+    pub fn to_canvas(&self, screen_pos: Vector2, pivot: Vector2) -> Vector2 {
+        self.unrotate(screen_pos, pivot)/self.zoom - self.pan
+    }
+
+    /// Turn a distance the mouse moved on screen into one on the canvas
+    pub fn to_canvas_delta(&self, delta: Vector2) -> Vector2 {
+        delta.rotated(-self.rotation.to_radians())/self.zoom
+    }
+
+    /// Change the zoom while keeping the canvas position under `anchor` (a screen position) in place
+    pub fn zoom_about(&mut self, zoom: f32, anchor: Vector2, pivot: Vector2) {
+        let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        let anchor = self.unrotate(anchor, pivot);
+        self.pan = self.pan + anchor*(zoom.recip() - self.zoom.recip());
+        self.zoom = zoom;
+    }
+
+    /// The zoom level `steps` levels above (or below, when negative) the current zoom
+    ///
+    /// A zoom between two levels counts as a step away from both, so stepping always lands on a level.
+    pub fn stepped_zoom(&self, steps: i32) -> f32 {
+        // a zoom within rounding error of a level counts as being on it
+        const EPSILON: f32 = 1e-4;
+        let n = steps.unsigned_abs() as usize;
+        if steps > 0 {
+            ZOOM_LEVELS.iter().copied().filter(|&level| level > self.zoom*(1.0 + EPSILON)).nth(n - 1).unwrap_or(MAX_ZOOM)
+        } else if steps < 0 {
+            ZOOM_LEVELS.iter().rev().copied().filter(|&level| level < self.zoom*(1.0 - EPSILON)).nth(n - 1).unwrap_or(MIN_ZOOM)
+        } else {
+            self.zoom
+        }
+    }
+
+    /// The zoom after scrolling by `scroll`: whole notches of a wheel step between levels, while
+    /// trackpads (which also send pinches this way) scroll by fractions and zoom smoothly
+    pub fn scrolled_zoom(&self, scroll: f32) -> f32 {
+        if scroll.fract() == 0.0 {
+            self.stepped_zoom(scroll as i32)
+        } else {
+            self.zoom*2.0f32.powf(scroll*0.25)
+        }
+    }
+
+    /// Center a `width` x `height` canvas in `viewport` at the largest zoom that fits all of it
+    ///
+    /// Zooms of 1 or more are rounded down to whole numbers so pixels stay square.
+    pub fn fit(&mut self, width: f32, height: f32, viewport: Rectangle) {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        // size of the bounding box of the rotated canvas
+        let rotated_width  = (width*cos).abs() + (height*sin).abs();
+        let rotated_height = (width*sin).abs() + (height*cos).abs();
+        let zoom = (viewport.width/rotated_width).min(viewport.height/rotated_height).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.zoom = if zoom >= 1.0 { zoom.floor() } else { zoom };
+        self.center(width, height, viewport);
+    }
+
+    /// Put the middle of a `width` x `height` canvas in the middle of `viewport`, keeping the zoom
+    pub fn center(&mut self, width: f32, height: f32, viewport: Rectangle) {
+        let pivot = pivot(viewport);
+        // the pivot doesn't move when rotating, so centering the unrotated canvas on it centers the rotated one
+        self.pan = pivot/self.zoom - Vector2::new(width*0.5, height*0.5);
+    }
+
+    /// Pan rounded so the canvas' edges land on whole screen pixels
+    pub fn snapped_pan(&self) -> Vector2 {
+        Vector2::new(
+            (self.pan.x*self.zoom).round()/self.zoom,
+            (self.pan.y*self.zoom).round()/self.zoom,
+        )
+    }
+
+    /// The area of the unrotated screen that ends up covering `viewport` after rotating,
+    /// for limiting what gets drawn under the camera
+    pub fn visible_area(&self, viewport: Rectangle, pivot: Vector2) -> Rectangle {
+        if !self.is_rotated() {
+            return viewport;
+        }
+        let corners = [
+            Vector2::new(viewport.x, viewport.y),
+            Vector2::new(viewport.x + viewport.width, viewport.y),
+            Vector2::new(viewport.x + viewport.width, viewport.y + viewport.height),
+            Vector2::new(viewport.x, viewport.y + viewport.height),
+        ].map(|corner| self.unrotate(corner, pivot));
+        let (min_x, max_x) = corners.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), p| (min.min(p.x), max.max(p.x)));
+        let (min_y, max_y) = corners.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), p| (min.min(p.y), max.max(p.y)));
+        Rectangle::new(min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+}
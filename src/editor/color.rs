@@ -2,7 +2,7 @@ use std::collections::VecDeque;
 
 use raylib::prelude::*;
 use amygui::prelude::*;
-use crate::{brush::{Brush, InterpStyle}, editor::Editor, frame::Frame};
+use crate::{brush::{Brush, InterpStyle}, editor::Editor, error, frame::Frame, keymap::{Command, Keymap}};
 
 const _FRAC_1_255: f32 = 1.0/255.0;
 
@@ -49,6 +49,8 @@ pub struct ColorEditor {
     is_colorwheel_dirty: bool,
     palette: VecDeque<Vector3>,
     cached_brush_radius: f32,
+    /// The brush color as of the last redraw, to notice colors picked elsewhere
+    cached_brush_color: Color,
 }
 
 impl ColorEditor {
@@ -84,8 +86,26 @@ impl ColorEditor {
             is_colorwheel_dirty: true,
             palette: VecDeque::with_capacity(Self::PALETTE_CAP),
             cached_brush_radius: -1.0,
+            cached_brush_color: brush.color,
         }
     }
+
+    /// Saved colors, oldest first, as hue, saturation and value
+    pub fn palette(&self) -> impl Iterator<Item = Vector3> + '_ {
+        self.palette.iter().copied()
+    }
+
+    /// Replace the saved colors, keeping the newest if there are too many
+    pub fn set_palette(&mut self, colors: impl IntoIterator<Item = Vector3>) {
+        self.palette.clear();
+        for color in colors {
+            if self.palette.len() == self.palette.capacity() {
+                self.palette.pop_front();
+            }
+            self.palette.push_back(color);
+        }
+        self.is_colorwheel_dirty = true;
+    }
 }
 
 impl Editor for ColorEditor {
@@ -104,12 +124,17 @@ impl Editor for ColorEditor {
         false
     }
 
-    fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, brush: &mut Brush, viewport: Rectangle, frame: &mut Frame, is_awake: bool) {
+    fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, keymap: &Keymap, brush: &mut Brush, viewport: Rectangle, frame: &mut Frame, is_awake: bool) -> error::Result<()> {
         if brush.radius != self.cached_brush_radius {
             self.cached_brush_radius = brush.radius;
             self.is_colorwheel_dirty = true;
         }
 
+        if brush.color != self.cached_brush_color {
+            self.color_hsv = brush.color.color_to_hsv();
+            self.is_colorwheel_dirty = true;
+        }
+
         if is_awake {
             let mouse_pos = rl.get_mouse_position();
 
@@ -138,7 +163,7 @@ impl Editor for ColorEditor {
                 }
             }
 
-            if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            if keymap.is_triggered(Command::AddToPalette) {
                 if self.palette.len() == self.palette.capacity() {
                     self.palette.pop_front();
                 }
@@ -165,6 +190,7 @@ impl Editor for ColorEditor {
                     self.color_hsv,
                 );
                 brush.color = Color::color_from_hsv(self.color_hsv.x, self.color_hsv.y, self.color_hsv.z);
+                self.cached_brush_color = brush.color;
             }
             let mut color_slot = Rectangle::new(
                 Self::BRUSH_SLOT_X,
@@ -229,5 +255,6 @@ impl Editor for ColorEditor {
                 color_slot.x += Self::SEGMENT_WIDTH;
             }
         }
+        Ok(())
     }
 }
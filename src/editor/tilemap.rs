@@ -0,0 +1,442 @@
+use std::{fs::File, io::{self, BufWriter}, path::Path};
+use raylib::prelude::*;
+use amygui::prelude::*;
+use crate::{brush::Brush, codec::{MAX_SIZE, apng, tilemap as tilemap_codec}, editor::Editor, error::{self, Error}, frame::Frame, keymap::{Command, Keymap}, raster::Raster, tilemap::{Tile, Tilemap, Tileset}};
+
+/// What a click in the side panel asks for
+enum PanelCommand {
+    SelectTile(u32),
+    FlipX,
+    FlipY,
+    Rotate,
+    SelectLayer(usize),
+    SetLayerVisible(usize, bool),
+    AddLayer,
+    DeleteLayer,
+}
+
+/// Draw `tile` from `tileset_canvas` stretched over `dest`, flipped as it says
+fn draw_tile<D: RaylibDraw>(d: &mut D, tileset_canvas: &RenderTexture2D, tileset: &Tileset, tile: Tile, dest: Rectangle, tint: Color) {
+    let src = tileset.rect(tile.index);
+    // render textures are stored bottom row first
+    let source = Rectangle::new(
+        src.x as f32,
+        (tileset_canvas.texture.height - src.y) as f32,
+        src.width as f32,
+        -src.height as f32,
+    );
+    let corner = |point| {
+        let (x, y) = tile.transform(point);
+        Vector2::new(dest.x + (x + 1.0)*0.5*dest.width, dest.y + (y + 1.0)*0.5*dest.height)
+    };
+    d.draw_texture_quad_rec(tileset_canvas, source, [corner((-1.0, -1.0)), corner((1.0, -1.0)), corner((1.0, 1.0)), corner((-1.0, 1.0))], tint);
+}
+
+/// Builds maps out of tiles cut from the art canvas
+pub struct TilemapEditor {
+    /// Copy of the art canvas the tiles are cut from, refreshed whenever it's redrawn
+    tileset_canvas: Option<RenderTexture2D>,
+    map: Option<Tilemap>,
+    current_layer: usize,
+    /// The tile painted by the left mouse button
+    brush_tile: Tile,
+    is_dirty: bool,
+    zoom_pow: i32,
+    pan: Vector2,
+    is_drag_panning: bool,
+    /// Whether a stroke is erasing, while one is in progress
+    painting: Option<bool>,
+    /// Whether the map has changed since it was last exported in a format that keeps its tiles
+    is_modified: bool,
+}
+
+impl TilemapEditor {
+    pub const PANEL_WIDTH: f32 = 200.0;
+    const PADDING: f32 = 6.0;
+    const PALETTE_MAX_HEIGHT: f32 = 240.0;
+    const ROW_HEIGHT: f32 = 24.0;
+
+    pub const fn new() -> Self {
+        Self {
+            tileset_canvas: None,
+            map: None,
+            current_layer: 0,
+            brush_tile: Tile::new(0),
+            is_dirty: true,
+            zoom_pow: 1,
+            pan: Vector2::new(8.0, 8.0),
+            is_drag_panning: false,
+            painting: None,
+            is_modified: false,
+        }
+    }
+
+    #[inline]
+    pub const fn has_map(&self) -> bool {
+        self.map.is_some()
+    }
+
+    #[inline]
+    pub const fn is_modified(&self) -> bool {
+        self.is_modified
+    }
+
+    /// Start over with an empty map `columns` by `rows` tiles, cutting an `image_width` by `image_height` tileset into tiles
+    pub fn new_map(&mut self, tile_width: u32, tile_height: u32, columns: u32, rows: u32, image_width: i32, image_height: i32) {
+        let tileset = Tileset::new(image_width as usize, image_height as usize, tile_width as usize, tile_height as usize);
+        self.map = Some(Tilemap::new(tileset, columns as usize, rows as usize));
+        self.current_layer = 0;
+        self.brush_tile = Tile::new(0);
+        self.is_dirty = true;
+        // there's nothing in it to lose yet
+        self.is_modified = false;
+    }
+
+    /// Copy the art canvas to cut tiles from, so changes to a tile show up everywhere it's placed
+    pub fn sync_tileset(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, canvas: &RenderTexture2D) -> error::Result<()> {
+        let (width, height) = (canvas.texture.width, canvas.texture.height);
+        let tileset_canvas = match self.tileset_canvas.take() {
+            Some(copy) if copy.texture.width == width && copy.texture.height == height => copy,
+            _ => rl.load_render_texture(thread, width as u32, height as u32).map_err(Error::gpu)?,
+        };
+        let tileset_canvas = self.tileset_canvas.insert(tileset_canvas);
+        {
+            let mut d = rl.begin_texture_mode(thread, tileset_canvas);
+            d.clear_background(Color::BLANK);
+            // copy alpha as-is instead of blending it with the blank background
+            let mut d = d.begin_blend_mode(BlendMode::BLEND_ALPHA_PREMULTIPLY);
+            d.draw_texture_direct(canvas, Rectangle::new(0.0, 0.0, width as f32, height as f32));
+        }
+        if let Some(map) = &mut self.map {
+            map.tileset = Tileset::new(width as usize, height as usize, map.tileset.tile_width, map.tileset.tile_height);
+        }
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    /// Write the map as CSV (one file per layer when there are several), Tiled JSON next to a copy
+    /// of the tileset, or a flattened PNG, depending on the extension of `path`.
+    /// Only CSV and JSON count as saving the map, since a flattened image can't be read back as one.
+    pub fn export(&mut self, path: &Path, tileset_image: &Raster) -> io::Result<()> {
+        let Some(map) = &self.map else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "there is no map to export"));
+        };
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" if map.layers.len() == 1 => {
+                tilemap_codec::encode_csv(BufWriter::new(File::create(path)?), &map.layers[0], map.width())?;
+                self.is_modified = false;
+                Ok(())
+            }
+            "csv" => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                for (i, layer) in map.layers.iter().enumerate() {
+                    let layer_path = path.with_file_name(format!("{stem}_{}.csv", i + 1));
+                    tilemap_codec::encode_csv(BufWriter::new(File::create(layer_path)?), layer, map.width())?;
+                }
+                self.is_modified = false;
+                Ok(())
+            }
+            "json" => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let image = format!("{stem}_tileset.png");
+                apng::encode_still(BufWriter::new(File::create(path.with_file_name(&image))?), tileset_image)?;
+                tilemap_codec::encode_json(BufWriter::new(File::create(path)?), map, &image, tileset_image.width(), tileset_image.height())?;
+                self.is_modified = false;
+                Ok(())
+            }
+            _ => {
+                let side = |tiles: usize, tile_size: usize| tiles.checked_mul(tile_size).filter(|&side| side <= MAX_SIZE);
+                if side(map.width(), map.tileset.tile_width).is_none() || side(map.height(), map.tileset.tile_height).is_none() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("the map would be larger than {MAX_SIZE}x{MAX_SIZE} as an image")));
+                }
+                apng::encode_still(BufWriter::new(File::create(path)?), &map.flatten(tileset_image))
+            }
+        }
+    }
+
+    /// The panel along the left edge, with the tile palette, brush tile and layers
+    fn draw_panel<D: RaylibDraw>(&self, d: &mut D, bounds: Rectangle, is_clicked: bool, mouse_pos: Vector2) -> Option<PanelCommand> {
+        let mut command = None;
+        d.draw_rectangle_rec(bounds, Color::new(16, 16, 16, 255));
+        let (Some(map), Some(tileset_canvas)) = (&self.map, &self.tileset_canvas) else {
+            return None;
+        };
+        let x = bounds.x + Self::PADDING;
+        let width = bounds.width - Self::PADDING*2.0;
+
+        // palette
+        let (tex_width, tex_height) = (tileset_canvas.texture.width as f32, tileset_canvas.texture.height as f32);
+        let scale = (width/tex_width).min(Self::PALETTE_MAX_HEIGHT/tex_height);
+        let palette_rec = Rectangle::new(x, bounds.y + Self::PADDING, tex_width*scale, tex_height*scale);
+        d.draw_rectangle_rec(palette_rec, Color::new(42, 42, 42, 255));
+        d.draw_texture_direct(tileset_canvas, palette_rec);
+        let tileset = &map.tileset;
+        let (cell_width, cell_height) = (tileset.tile_width as f32*scale, tileset.tile_height as f32*scale);
+        for column in 1..tileset.columns {
+            let line_x = palette_rec.x + column as f32*cell_width;
+            d.draw_line_v(Vector2::new(line_x, palette_rec.y), Vector2::new(line_x, palette_rec.y + tileset.rows as f32*cell_height), Color::new(255, 255, 255, 48));
+        }
+        for row in 1..tileset.rows {
+            let line_y = palette_rec.y + row as f32*cell_height;
+            d.draw_line_v(Vector2::new(palette_rec.x, line_y), Vector2::new(palette_rec.x + tileset.columns as f32*cell_width, line_y), Color::new(255, 255, 255, 48));
+        }
+        if (self.brush_tile.index as usize) < tileset.len() {
+            let src = tileset.rect(self.brush_tile.index);
+            let selected_rec = Rectangle::new(palette_rec.x + src.x as f32*scale, palette_rec.y + src.y as f32*scale, cell_width, cell_height);
+            d.draw_rectangle_lines_ex(selected_rec, 2.0, Color::WHITE);
+        }
+        if is_clicked && palette_rec.check_collision_point_rec(mouse_pos) {
+            let pos = (mouse_pos - Vector2::new(palette_rec.x, palette_rec.y))/scale;
+            if let Some(index) = tileset.index_at(pos.x as i32, pos.y as i32) {
+                command = Some(PanelCommand::SelectTile(index));
+            }
+        }
+
+        // brush tile
+        let mut y = palette_rec.y + palette_rec.height + Self::PADDING;
+        let preview_size = Self::ROW_HEIGHT*2.0 + Self::PADDING;
+        let preview_rec = Rectangle::new(x, y, preview_size, preview_size);
+        d.draw_rectangle_rec(preview_rec, Color::new(42, 42, 42, 255));
+        if (self.brush_tile.index as usize) < tileset.len() {
+            draw_tile(d, tileset_canvas, tileset, self.brush_tile, preview_rec, Color::WHITE);
+        }
+        let button_x = x + preview_size + Self::PADDING;
+        let button_width = (x + width - button_x - Self::PADDING)*0.5;
+        if d.gui_button(Rectangle::new(button_x, y, button_width, Self::ROW_HEIGHT - 4.0), "Flip X") {
+            command = Some(PanelCommand::FlipX);
+        }
+        if d.gui_button(Rectangle::new(button_x + button_width + Self::PADDING, y, button_width, Self::ROW_HEIGHT - 4.0), "Flip Y") {
+            command = Some(PanelCommand::FlipY);
+        }
+        if d.gui_button(Rectangle::new(button_x, y + Self::ROW_HEIGHT + Self::PADDING, button_width*2.0 + Self::PADDING, Self::ROW_HEIGHT - 4.0), "Rotate") {
+            command = Some(PanelCommand::Rotate);
+        }
+        y += preview_size + Self::PADDING*2.0;
+
+        // layers, top first
+        d.draw_text("Layers", x as i32, y as i32 + 5, 10, Color::WHITE);
+        if d.gui_button(Rectangle::new(x + width - 84.0, y, 40.0, Self::ROW_HEIGHT - 4.0), "+") {
+            command = Some(PanelCommand::AddLayer);
+        }
+        if d.gui_button(Rectangle::new(x + width - 40.0, y, 40.0, Self::ROW_HEIGHT - 4.0), "Del") {
+            command = Some(PanelCommand::DeleteLayer);
+        }
+        y += Self::ROW_HEIGHT;
+        for (i, layer) in map.layers.iter().enumerate().rev() {
+            if y + Self::ROW_HEIGHT > bounds.y + bounds.height {
+                break;
+            }
+            let mut is_visible = layer.is_visible;
+            d.gui_check_box(Rectangle::new(x, y + 2.0, 16.0, 16.0), "", &mut is_visible);
+            if is_visible != layer.is_visible {
+                command = Some(PanelCommand::SetLayerVisible(i, is_visible));
+            }
+            let name_rec = Rectangle::new(x + 22.0, y, width - 22.0, Self::ROW_HEIGHT - 4.0);
+            if d.gui_button(name_rec, &layer.name) {
+                command = Some(PanelCommand::SelectLayer(i));
+            }
+            if i == self.current_layer {
+                d.draw_rectangle_lines_ex(name_rec, 2.0, Color::WHITE);
+            }
+            y += Self::ROW_HEIGHT;
+        }
+
+        command
+    }
+
+    fn run_panel_command(&mut self, command: PanelCommand) {
+        let Some(map) = &mut self.map else { return; };
+        match command {
+            PanelCommand::SelectTile(index) => self.brush_tile = Tile { index, ..self.brush_tile },
+            PanelCommand::FlipX => self.brush_tile = self.brush_tile.flipped_x(),
+            PanelCommand::FlipY => self.brush_tile = self.brush_tile.flipped_y(),
+            PanelCommand::Rotate => self.brush_tile = self.brush_tile.rotated_cw(),
+            PanelCommand::SelectLayer(index) => self.current_layer = index,
+            PanelCommand::SetLayerVisible(index, is_visible) => {
+                map.layers[index].is_visible = is_visible;
+                self.is_modified = true;
+            }
+            PanelCommand::AddLayer => {
+                self.current_layer = map.add_layer();
+                self.is_modified = true;
+            }
+            PanelCommand::DeleteLayer => {
+                // keep at least one layer to paint on
+                if map.layers.len() > 1 {
+                    map.layers.remove(self.current_layer);
+                    self.current_layer = self.current_layer.min(map.layers.len() - 1);
+                    self.is_modified = true;
+                }
+            }
+        }
+        self.is_dirty = true;
+    }
+}
+
+impl Editor for TilemapEditor {
+    fn mark_dirty(&mut self) {
+        self.is_dirty = true;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    fn is_focused(&self) -> bool {
+        self.painting.is_some() || self.is_drag_panning
+    }
+
+    fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, keymap: &Keymap, brush: &mut Brush, viewport: Rectangle, frame: &mut Frame, is_awake: bool) -> error::Result<()> {
+        let mouse_pos = rl.get_mouse_position();
+        let panel_rec = Rectangle { width: Self::PANEL_WIDTH, ..viewport };
+        let map_viewport = Rectangle {
+            x: viewport.x + Self::PANEL_WIDTH,
+            width: viewport.width - Self::PANEL_WIDTH,
+            ..viewport
+        };
+        let is_over_panel = panel_rec.check_collision_point_rec(mouse_pos);
+        if is_awake && is_over_panel {
+            self.is_dirty = true;
+        }
+        let is_map_awake = is_awake && (self.is_focused() || !is_over_panel);
+
+        let origin = Vector2::new(map_viewport.x, map_viewport.y);
+
+        // Zoom + pan
+        if is_map_awake {
+            let zoom_scroll = keymap.scroll(Command::Zoom);
+            if zoom_scroll != 0.0 {
+                // zoom, keeping the point under the mouse in place
+                let map_mouse_pos = (mouse_pos - origin)/2.0f32.powi(self.zoom_pow) - self.pan;
+                self.zoom_pow = (self.zoom_pow + zoom_scroll.round() as i32).clamp(-2, 4);
+                self.pan = (mouse_pos - origin)/2.0f32.powi(self.zoom_pow) - map_mouse_pos;
+                self.is_dirty = true;
+            }
+            let pan_scroll = Vector2::new(keymap.scroll(Command::PanHorizontal), keymap.scroll(Command::PanVertical));
+            if pan_scroll != Vector2::zero() {
+                self.pan = self.pan + pan_scroll*20.0;
+                self.is_dirty = true;
+            }
+
+            if keymap.is_triggered(Command::DragPan) {
+                self.is_drag_panning = true;
+            } else if !keymap.is_down(rl, Command::DragPan) {
+                self.is_drag_panning = false;
+            }
+            if self.is_drag_panning {
+                let movement = rl.get_mouse_delta();
+                if movement.length_sqr() > 0.0 {
+                    self.pan = self.pan + movement/2.0f32.powi(self.zoom_pow);
+                    self.is_dirty = true;
+                }
+            }
+
+            if keymap.is_triggered(Command::FlipTileX) {
+                self.run_panel_command(PanelCommand::FlipX);
+            } else if keymap.is_triggered(Command::FlipTileY) {
+                self.run_panel_command(PanelCommand::FlipY);
+            } else if keymap.is_triggered(Command::RotateTile) {
+                self.run_panel_command(PanelCommand::Rotate);
+            }
+        }
+
+        let zoom = 2.0f32.powi(self.zoom_pow);
+        let map_mouse_pos = (mouse_pos - origin)/zoom - self.pan;
+
+        // Paint
+        if is_map_awake && let Some(map) = &mut self.map {
+            let cell = (
+                (map_mouse_pos.x/map.tileset.tile_width  as f32).floor() as i32,
+                (map_mouse_pos.y/map.tileset.tile_height as f32).floor() as i32,
+            );
+            let is_alt_down = rl.is_key_down(KeyboardKey::KEY_LEFT_ALT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_ALT);
+
+            if rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT) || rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_RIGHT) {
+                self.painting = None;
+            }
+            let is_right_pressed = rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT);
+            if is_alt_down && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                // pick up a placed tile along with its flips
+                if let Some(tile) = map.layers[self.current_layer].get(cell.0, cell.1) {
+                    self.brush_tile = tile;
+                    self.is_dirty = true;
+                }
+            } else if self.painting.is_none() && (rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) || is_right_pressed) {
+                self.painting = Some(is_right_pressed);
+            }
+
+            if let Some(is_erasing) = self.painting && !map.tileset.is_empty() {
+                // the brush radius counts tiles rather than pixels
+                let tile = (!is_erasing).then_some(self.brush_tile);
+                let reach = brush.radius.floor() as i32;
+                let layer = &mut map.layers[self.current_layer];
+                for dy in -reach..=reach {
+                    for dx in -reach..=reach {
+                        if (dx*dx + dy*dy) as f32 <= brush.radius*brush.radius && layer.set(cell.0 + dx, cell.1 + dy, tile) {
+                            self.is_dirty = true;
+                            self.is_modified = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Render
+        if self.is_dirty {
+            self.is_dirty = false;
+            let is_clicked = is_awake && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT);
+            let command = {
+                let mut d = frame.begin_drawing(rl, thread);
+                {
+                    let mut d = d.begin_scissor_mode(map_viewport.x as i32, map_viewport.y as i32, map_viewport.width as i32, map_viewport.height as i32);
+                    d.clear_background(Color::BLACK);
+                    if let (Some(map), Some(tileset_canvas)) = (&self.map, &self.tileset_canvas) {
+                        let (tile_width, tile_height) = (map.tileset.tile_width as f32*zoom, map.tileset.tile_height as f32*zoom);
+                        let map_rec = Rectangle::new(
+                            origin.x + self.pan.x.round()*zoom,
+                            origin.y + self.pan.y.round()*zoom,
+                            map.width() as f32*tile_width,
+                            map.height() as f32*tile_height,
+                        );
+                        d.draw_rectangle_rec(map_rec, Color::new(42, 42, 42, 255));
+                        for layer in map.layers.iter().filter(|layer| layer.is_visible) {
+                            for (i, tile) in layer.cells().iter().enumerate() {
+                                if let Some(tile) = *tile && (tile.index as usize) < map.tileset.len() {
+                                    let dest = Rectangle::new(
+                                        map_rec.x + (i % map.width()) as f32*tile_width,
+                                        map_rec.y + (i / map.width()) as f32*tile_height,
+                                        tile_width,
+                                        tile_height,
+                                    );
+                                    draw_tile(&mut d, tileset_canvas, &map.tileset, tile, dest, Color::WHITE);
+                                }
+                            }
+                        }
+                        for column in 0..=map.width() {
+                            let x = map_rec.x + column as f32*tile_width;
+                            d.draw_line_v(Vector2::new(x, map_rec.y), Vector2::new(x, map_rec.y + map_rec.height), Color::new(255, 255, 255, 32));
+                        }
+                        for row in 0..=map.height() {
+                            let y = map_rec.y + row as f32*tile_height;
+                            d.draw_line_v(Vector2::new(map_rec.x, y), Vector2::new(map_rec.x + map_rec.width, y), Color::new(255, 255, 255, 32));
+                        }
+                    } else {
+                        d.draw_text("No map (Ctrl+Alt+M to create one)", map_viewport.x as i32 + 5, map_viewport.y as i32 + 5, 10, Color::WHITE);
+                    }
+                }
+                let mut d = d.begin_scissor_mode(panel_rec.x as i32, panel_rec.y as i32, panel_rec.width as i32, panel_rec.height as i32);
+                if !is_awake {
+                    d.gui_lock();
+                }
+                let command = self.draw_panel(&mut d, panel_rec, is_clicked, mouse_pos);
+                d.gui_unlock();
+                command
+            };
+            if let Some(command) = command {
+                self.run_panel_command(command);
+            }
+        }
+        Ok(())
+    }
+}
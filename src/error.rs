@@ -0,0 +1,57 @@
+use std::{fmt, io};
+
+/// Anything that can go wrong reading, writing or making room for artwork
+#[derive(Debug)]
+pub enum Error {
+    /// Opening, reading or writing a file
+    Io(io::Error),
+    /// A file that isn't an image, or is one that can't be read
+    Decode(String),
+    /// Artwork that couldn't be written in the chosen format
+    Encode(String),
+    /// The GPU couldn't make or read back a texture, usually for lack of memory
+    Gpu(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// For raylib's errors, which are only messages
+    pub fn gpu(err: impl fmt::Display) -> Self {
+        Self::Gpu(err.to_string())
+    }
+
+    pub fn decode(err: impl fmt::Display) -> Self {
+        Self::Decode(err.to_string())
+    }
+
+    pub fn encode(err: impl fmt::Display) -> Self {
+        Self::Encode(err.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => err.fmt(f),
+            Self::Decode(msg) => write!(f, "couldn't read the image: {msg}"),
+            Self::Encode(msg) => write!(f, "couldn't write the image: {msg}"),
+            Self::Gpu(msg) => write!(f, "GPU error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Decode(_) | Self::Encode(_) | Self::Gpu(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
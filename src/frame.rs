@@ -1,6 +1,7 @@
 use std::marker::PhantomData;
 use raylib::prelude::*;
 use amygui::prelude::*;
+use crate::error::{self, Error};
 
 pub struct Frame {
     buffer: RenderTexture2D,
@@ -37,20 +38,23 @@ impl<'a, 'b, T> Drop for RaylibFrameMode<'a, 'b, T> {
 impl<'a, 'b, T> RaylibDraw for RaylibFrameMode<'a, 'b, T> {}
 
 impl Frame {
-    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> Self {
-        Self {
-            buffer: rl.load_render_texture(&thread, rl.get_screen_width().try_into().unwrap(), rl.get_screen_height().try_into().unwrap()).unwrap(),
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> error::Result<Self> {
+        Ok(Self {
+            buffer: rl.load_render_texture(&thread, rl.get_screen_width().max(1) as u32, rl.get_screen_height().max(1) as u32).map_err(Error::gpu)?,
             is_dirty: true,
-        }
+        })
     }
 
-    pub fn resize(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) {
+    /// Match the window's size, keeping the old buffer if there's no room for a new one
+    pub fn resize(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> error::Result<()> {
         let width = rl.get_screen_width();
         let height = rl.get_screen_height();
-        if width != self.buffer.texture.width || height != self.buffer.texture.height {
-            self.buffer = rl.load_render_texture(&thread, width.try_into().unwrap(), height.try_into().unwrap()).unwrap();
+        // minimizing on some platforms reports a window with no area
+        if (width != self.buffer.texture.width || height != self.buffer.texture.height) && width > 0 && height > 0 {
+            self.buffer = rl.load_render_texture(&thread, width as u32, height as u32).map_err(Error::gpu)?;
             self.is_dirty = true;
         }
+        Ok(())
     }
 
     pub fn is_dirty(&self) -> bool {
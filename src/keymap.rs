@@ -0,0 +1,540 @@
+//! Named commands and the inputs bound to them
+//!
+//! Bindings start out as each command's defaults, and any command listed in the `[bindings]` table
+//! of `keymap.toml` in the config directory has its defaults replaced:
+//!
+//! ```toml
+//! [bindings]
+//! save = "Ctrl+S"
+//! split_view = ["Ctrl+\\", "Ctrl+K Ctrl+V"]
+//! toggle_navigator = []
+//! ```
+//!
+//! A combo is any of `Ctrl`, `Shift` and `Alt`, optionally one more key that has to be held,
+//! then the key, mouse button or `Wheel` that triggers it, joined by `+`. A binding of several
+//! combos separated by spaces is a chord, typed one combo after another.
+
+use std::{collections::BTreeMap, fmt, fs, io, path::{Path, PathBuf}, str::FromStr};
+use raylib::prelude::*;
+use serde::Deserialize;
+use crate::settings;
+
+/// Where a command is handled, which decides whose bindings can get in each other's way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    /// Handled whichever editor the mouse is over
+    Global,
+    /// Moving around, in both the art and tilemap editors
+    View,
+    Canvas,
+    /// Only while transforming, which happens on the canvas
+    Transform,
+    Tilemap,
+    Palette,
+}
+
+impl Context {
+    /// Whether commands in both contexts can be triggered by the same input
+    fn overlaps(self, other: Self) -> bool {
+        use Context::*;
+        self == other || matches!((self, other),
+            (Global, _) | (_, Global) |
+            (View, Canvas | Transform | Tilemap) | (Canvas | Transform | Tilemap, View) |
+            (Canvas, Transform) | (Transform, Canvas)
+        )
+    }
+}
+
+macro_rules! commands {
+    ($($(#[doc = $doc:literal])* $variant:ident = $name:literal in $context:ident: [$($binding:literal),*],)*) => {
+        /// Something that can be asked for by name and bound to inputs
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Command {
+            $($(#[doc = $doc])* $variant,)*
+        }
+
+        impl Command {
+            pub const ALL: &[Self] = &[$(Self::$variant,)*];
+
+            /// What the command is called in the config file
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name,)*
+                }
+            }
+
+            pub const fn context(self) -> Context {
+                match self {
+                    $(Self::$variant => Context::$context,)*
+                }
+            }
+
+            const fn default_bindings(self) -> &'static [&'static str] {
+                match self {
+                    $(Self::$variant => &[$($binding),*],)*
+                }
+            }
+        }
+    };
+}
+
+commands! {
+    CanvasSize = "canvas_size" in Global: ["Ctrl+Alt+C"],
+    ImageSize = "image_size" in Global: ["Ctrl+Alt+I"],
+    CropToSelection = "crop_to_selection" in Global: ["Ctrl+Alt+X"],
+    Trim = "trim" in Global: ["Ctrl+Alt+T"],
+    FlipHorizontal = "flip_horizontal" in Global: ["Ctrl+Alt+H"],
+    FlipVertical = "flip_vertical" in Global: ["Ctrl+Alt+V"],
+    RotateClockwise = "rotate_clockwise" in Global: ["Ctrl+Alt+R"],
+    RotateHalf = "rotate_half" in Global: ["Ctrl+Alt+U"],
+    RotateCounterClockwise = "rotate_counter_clockwise" in Global: ["Ctrl+Alt+L"],
+    BoxBlur = "box_blur" in Global: ["Ctrl+Alt+B"],
+    GaussianBlur = "gaussian_blur" in Global: ["Ctrl+Alt+G"],
+    UnsharpMask = "unsharp_mask" in Global: ["Ctrl+Alt+K"],
+    EdgeDetect = "edge_detect" in Global: ["Ctrl+Alt+E"],
+    Outline = "outline" in Global: ["Ctrl+Alt+O"],
+    DropShadow = "drop_shadow" in Global: ["Ctrl+Alt+D"],
+    RemoveOrphans = "remove_orphans" in Global: ["Ctrl+Alt+N"],
+    NewTilemap = "new_tilemap" in Global: ["Ctrl+Alt+M"],
+    GridSettings = "grid_settings" in Global: ["Ctrl+Alt+'"],
+    ClearGuides = "clear_guides" in Global: ["Ctrl+Alt+;"],
+    ResetRotation = "reset_rotation" in Global: ["Ctrl+Alt+0"],
+    HueSaturation = "hue_saturation" in Global: ["Ctrl+U"],
+    Desaturate = "desaturate" in Global: ["Ctrl+Shift+U"],
+    Invert = "invert" in Global: ["Ctrl+I"],
+    Levels = "levels" in Global: ["Ctrl+L"],
+    Curves = "curves" in Global: ["Ctrl+M"],
+    ColorBalance = "color_balance" in Global: ["Ctrl+B"],
+    BrightnessContrast = "brightness_contrast" in Global: ["Ctrl+Shift+B"],
+    Posterize = "posterize" in Global: ["Ctrl+P"],
+    /// Switch to the tilemap, or start one if there isn't one yet
+    ToggleTilemap = "toggle_tilemap" in Global: ["Ctrl+Shift+M"],
+    Deselect = "deselect" in Global: ["Ctrl+D"],
+    ToggleGrid = "toggle_grid" in Global: ["Ctrl+'"],
+    ToggleGuides = "toggle_guides" in Global: ["Ctrl+;"],
+    ToggleSnapping = "toggle_snapping" in Global: ["Ctrl+Shift+;"],
+    OpenReference = "open_reference" in Global: ["Ctrl+R"],
+    UnpinReferences = "unpin_references" in Global: ["Ctrl+Shift+R"],
+    SplitView = "split_view" in Global: ["Ctrl+\\"],
+    CloseView = "close_view" in Global: ["Ctrl+Shift+\\"],
+    ZoomToFit = "zoom_to_fit" in Global: ["Ctrl+0"],
+    ZoomToActualSize = "zoom_to_actual_size" in Global: ["Ctrl+1"],
+    Transform = "transform" in Global: ["Ctrl+T"],
+    Save = "save" in Global: ["Ctrl+S"],
+    SaveAs = "save_as" in Global: ["Ctrl+Shift+S"],
+    /// Export the animation, or the tilemap while it's shown
+    Export = "export" in Global: ["Ctrl+E"],
+    ExportSheet = "export_sheet" in Global: ["Ctrl+Shift+E"],
+    NewDocument = "new_document" in Global: ["Ctrl+N"],
+    /// Open a file in a new tab
+    Open = "open" in Global: ["Ctrl+O"],
+    CloseDocument = "close_document" in Global: ["Ctrl+W"],
+    NextDocument = "next_document" in Global: ["Ctrl+Tab"],
+    PreviousDocument = "previous_document" in Global: ["Ctrl+Shift+Tab"],
+    /// Replace every frame with an animation or sprite sheet
+    ImportFrames = "import_frames" in Global: ["Ctrl+Shift+O"],
+    ShowKeymap = "show_keymap" in Global: ["F1"],
+
+    Zoom = "zoom" in View: ["Ctrl+Wheel"],
+    PanHorizontal = "pan_horizontal" in View: ["Shift+Wheel"],
+    PanVertical = "pan_vertical" in View: ["Wheel"],
+    DragPan = "drag_pan" in View: ["MouseMiddle"],
+
+    RotateView = "rotate_view" in Canvas: ["R+MouseLeft"],
+    PreviousFrame = "previous_frame" in Canvas: [","],
+    NextFrame = "next_frame" in Canvas: ["."],
+    TogglePlayback = "toggle_playback" in Canvas: ["Space"],
+    ToggleOnionSkin = "toggle_onion_skin" in Canvas: ["O"],
+    CycleTileMode = "cycle_tile_mode" in Canvas: ["W"],
+    ToggleNavigator = "toggle_navigator" in Canvas: ["N"],
+    FillTool = "fill_tool" in Canvas: ["G"],
+    PenTool = "pen_tool" in Canvas: ["B"],
+    SelectTool = "select_tool" in Canvas: ["M"],
+    BrushSize = "brush_size" in Canvas: ["Alt+Wheel"],
+    BrushSize1 = "brush_size_1" in Canvas: ["1"],
+    BrushSize2 = "brush_size_2" in Canvas: ["2"],
+    BrushSize3 = "brush_size_3" in Canvas: ["3"],
+    BrushSize4 = "brush_size_4" in Canvas: ["4"],
+    BrushSize5 = "brush_size_5" in Canvas: ["5"],
+
+    CycleSampler = "cycle_sampler" in Transform: ["Tab"],
+    ApplyTransform = "apply_transform" in Transform: ["Enter", "KpEnter"],
+    CancelTransform = "cancel_transform" in Transform: ["Escape"],
+
+    FlipTileX = "flip_tile_x" in Tilemap: ["X"],
+    FlipTileY = "flip_tile_y" in Tilemap: ["Y"],
+    RotateTile = "rotate_tile" in Tilemap: ["R"],
+
+    AddToPalette = "add_to_palette" in Palette: ["Enter"],
+}
+
+impl Command {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|command| command.name() == name)
+    }
+}
+
+/// Names keys can be written as; the first name of each key is the one it's shown as
+const KEYS: &[(&str, KeyboardKey)] = &[
+    ("A", KeyboardKey::KEY_A), ("B", KeyboardKey::KEY_B), ("C", KeyboardKey::KEY_C), ("D", KeyboardKey::KEY_D),
+    ("E", KeyboardKey::KEY_E), ("F", KeyboardKey::KEY_F), ("G", KeyboardKey::KEY_G), ("H", KeyboardKey::KEY_H),
+    ("I", KeyboardKey::KEY_I), ("J", KeyboardKey::KEY_J), ("K", KeyboardKey::KEY_K), ("L", KeyboardKey::KEY_L),
+    ("M", KeyboardKey::KEY_M), ("N", KeyboardKey::KEY_N), ("O", KeyboardKey::KEY_O), ("P", KeyboardKey::KEY_P),
+    ("Q", KeyboardKey::KEY_Q), ("R", KeyboardKey::KEY_R), ("S", KeyboardKey::KEY_S), ("T", KeyboardKey::KEY_T),
+    ("U", KeyboardKey::KEY_U), ("V", KeyboardKey::KEY_V), ("W", KeyboardKey::KEY_W), ("X", KeyboardKey::KEY_X),
+    ("Y", KeyboardKey::KEY_Y), ("Z", KeyboardKey::KEY_Z),
+    ("0", KeyboardKey::KEY_ZERO), ("1", KeyboardKey::KEY_ONE), ("2", KeyboardKey::KEY_TWO), ("3", KeyboardKey::KEY_THREE),
+    ("4", KeyboardKey::KEY_FOUR), ("5", KeyboardKey::KEY_FIVE), ("6", KeyboardKey::KEY_SIX), ("7", KeyboardKey::KEY_SEVEN),
+    ("8", KeyboardKey::KEY_EIGHT), ("9", KeyboardKey::KEY_NINE),
+    ("F1", KeyboardKey::KEY_F1), ("F2", KeyboardKey::KEY_F2), ("F3", KeyboardKey::KEY_F3), ("F4", KeyboardKey::KEY_F4),
+    ("F5", KeyboardKey::KEY_F5), ("F6", KeyboardKey::KEY_F6), ("F7", KeyboardKey::KEY_F7), ("F8", KeyboardKey::KEY_F8),
+    ("F9", KeyboardKey::KEY_F9), ("F10", KeyboardKey::KEY_F10), ("F11", KeyboardKey::KEY_F11), ("F12", KeyboardKey::KEY_F12),
+    ("'", KeyboardKey::KEY_APOSTROPHE), ("Apostrophe", KeyboardKey::KEY_APOSTROPHE),
+    (",", KeyboardKey::KEY_COMMA), ("Comma", KeyboardKey::KEY_COMMA),
+    ("-", KeyboardKey::KEY_MINUS), ("Minus", KeyboardKey::KEY_MINUS),
+    (".", KeyboardKey::KEY_PERIOD), ("Period", KeyboardKey::KEY_PERIOD),
+    ("/", KeyboardKey::KEY_SLASH), ("Slash", KeyboardKey::KEY_SLASH),
+    (";", KeyboardKey::KEY_SEMICOLON), ("Semicolon", KeyboardKey::KEY_SEMICOLON),
+    ("=", KeyboardKey::KEY_EQUAL), ("Equal", KeyboardKey::KEY_EQUAL),
+    ("[", KeyboardKey::KEY_LEFT_BRACKET), ("LeftBracket", KeyboardKey::KEY_LEFT_BRACKET),
+    ("\\", KeyboardKey::KEY_BACKSLASH), ("Backslash", KeyboardKey::KEY_BACKSLASH),
+    ("]", KeyboardKey::KEY_RIGHT_BRACKET), ("RightBracket", KeyboardKey::KEY_RIGHT_BRACKET),
+    ("`", KeyboardKey::KEY_GRAVE), ("Grave", KeyboardKey::KEY_GRAVE),
+    ("Space", KeyboardKey::KEY_SPACE), ("Escape", KeyboardKey::KEY_ESCAPE), ("Esc", KeyboardKey::KEY_ESCAPE),
+    ("Enter", KeyboardKey::KEY_ENTER), ("Return", KeyboardKey::KEY_ENTER), ("Tab", KeyboardKey::KEY_TAB),
+    ("Backspace", KeyboardKey::KEY_BACKSPACE), ("Insert", KeyboardKey::KEY_INSERT), ("Delete", KeyboardKey::KEY_DELETE),
+    ("Right", KeyboardKey::KEY_RIGHT), ("Left", KeyboardKey::KEY_LEFT), ("Down", KeyboardKey::KEY_DOWN), ("Up", KeyboardKey::KEY_UP),
+    ("PageUp", KeyboardKey::KEY_PAGE_UP), ("PageDown", KeyboardKey::KEY_PAGE_DOWN),
+    ("Home", KeyboardKey::KEY_HOME), ("End", KeyboardKey::KEY_END),
+    ("Kp0", KeyboardKey::KEY_KP_0), ("Kp1", KeyboardKey::KEY_KP_1), ("Kp2", KeyboardKey::KEY_KP_2), ("Kp3", KeyboardKey::KEY_KP_3),
+    ("Kp4", KeyboardKey::KEY_KP_4), ("Kp5", KeyboardKey::KEY_KP_5), ("Kp6", KeyboardKey::KEY_KP_6), ("Kp7", KeyboardKey::KEY_KP_7),
+    ("Kp8", KeyboardKey::KEY_KP_8), ("Kp9", KeyboardKey::KEY_KP_9),
+    ("KpDecimal", KeyboardKey::KEY_KP_DECIMAL), ("KpDivide", KeyboardKey::KEY_KP_DIVIDE), ("KpMultiply", KeyboardKey::KEY_KP_MULTIPLY),
+    ("KpSubtract", KeyboardKey::KEY_KP_SUBTRACT), ("KpAdd", KeyboardKey::KEY_KP_ADD), ("KpEnter", KeyboardKey::KEY_KP_ENTER),
+    ("KpEqual", KeyboardKey::KEY_KP_EQUAL),
+];
+
+const MOUSE_BUTTONS: &[(&str, MouseButton)] = &[
+    ("MouseLeft", MouseButton::MOUSE_BUTTON_LEFT),
+    ("MouseRight", MouseButton::MOUSE_BUTTON_RIGHT),
+    ("MouseMiddle", MouseButton::MOUSE_BUTTON_MIDDLE),
+    ("MouseBack", MouseButton::MOUSE_BUTTON_BACK),
+    ("MouseForward", MouseButton::MOUSE_BUTTON_FORWARD),
+];
+
+/// The last part of a combo, which triggers it when pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trigger {
+    Key(KeyboardKey),
+    Mouse(MouseButton),
+    /// Scrolling either way
+    Wheel,
+}
+
+impl Trigger {
+    fn is_pressed(self, rl: &RaylibHandle) -> bool {
+        match self {
+            Self::Key(key) => rl.is_key_pressed(key),
+            Self::Mouse(button) => rl.is_mouse_button_pressed(button),
+            Self::Wheel => rl.get_mouse_wheel_move() != 0.0,
+        }
+    }
+
+    fn is_down(self, rl: &RaylibHandle) -> bool {
+        match self {
+            Self::Key(key) => rl.is_key_down(key),
+            Self::Mouse(button) => rl.is_mouse_button_down(button),
+            Self::Wheel => false,
+        }
+    }
+}
+
+impl FromStr for Trigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("Wheel") {
+            Ok(Self::Wheel)
+        } else if let Some(&(_, button)) = MOUSE_BUTTONS.iter().find(|(name, _)| name.eq_ignore_ascii_case(s)) {
+            Ok(Self::Mouse(button))
+        } else if let Some(&(_, key)) = KEYS.iter().find(|(name, _)| name.eq_ignore_ascii_case(s)) {
+            Ok(Self::Key(key))
+        } else {
+            Err(format!("unknown key \"{s}\""))
+        }
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(key) => f.write_str(KEYS.iter().find(|(_, k)| k == key).map_or("?", |(name, _)| name)),
+            Self::Mouse(button) => f.write_str(MOUSE_BUTTONS.iter().find(|(_, b)| b == button).map_or("?", |(name, _)| name)),
+            Self::Wheel => f.write_str("Wheel"),
+        }
+    }
+}
+
+/// Which of ctrl, shift and alt are down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Modifiers {
+    is_ctrl: bool,
+    is_shift: bool,
+    is_alt: bool,
+}
+
+impl Modifiers {
+    fn current(rl: &RaylibHandle) -> Self {
+        Self {
+            is_ctrl: rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL),
+            is_shift: rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT),
+            is_alt: rl.is_key_down(KeyboardKey::KEY_LEFT_ALT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_ALT),
+        }
+    }
+}
+
+/// One step of a binding, like `Ctrl+Shift+S` or `R+MouseLeft`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Combo {
+    /// Have to match exactly, so `Ctrl+S` doesn't also trigger on `Ctrl+Shift+S`
+    modifiers: Modifiers,
+    /// A key besides the modifiers that has to be down
+    held: Option<KeyboardKey>,
+    trigger: Trigger,
+}
+
+impl Combo {
+    fn is_pressed(&self, rl: &RaylibHandle, modifiers: Modifiers) -> bool {
+        self.modifiers == modifiers
+            && self.held.is_none_or(|key| rl.is_key_down(key))
+            && self.trigger.is_pressed(rl)
+    }
+}
+
+impl FromStr for Combo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Modifiers::default();
+        let mut inputs = Vec::new();
+        for part in s.split('+') {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.is_ctrl = true,
+                "shift" => modifiers.is_shift = true,
+                "alt" => modifiers.is_alt = true,
+                _ => inputs.push(part.parse::<Trigger>()?),
+            }
+        }
+        let (held, trigger) = match inputs[..] {
+            [trigger] => (None, trigger),
+            [Trigger::Key(held), trigger] => (Some(held), trigger),
+            [] => return Err(format!("\"{s}\" is only modifiers")),
+            _ => return Err(format!("\"{s}\" can only have one key held besides the modifiers")),
+        };
+        Ok(Self { modifiers, held, trigger })
+    }
+}
+
+impl fmt::Display for Combo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Modifiers { is_ctrl, is_shift, is_alt } = self.modifiers;
+        if is_ctrl { f.write_str("Ctrl+")?; }
+        if is_shift { f.write_str("Shift+")?; }
+        if is_alt { f.write_str("Alt+")?; }
+        if let Some(key) = self.held {
+            write!(f, "{}+", Trigger::Key(key))?;
+        }
+        self.trigger.fmt(f)
+    }
+}
+
+/// Combos typed one after another, which is a chord when there's more than one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding(Vec<Combo>);
+
+impl Binding {
+    /// Whether typing this binding gets partway through or all the way through `other`
+    fn is_prefix_of(&self, other: &Self) -> bool {
+        other.0.starts_with(&self.0)
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let combos = s.split_whitespace().map(str::parse).collect::<Result<Vec<Combo>, _>>()?;
+        if combos.is_empty() {
+            return Err("empty binding".to_string());
+        }
+        Ok(Self(combos))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, combo) in self.0.iter().enumerate() {
+            if i > 0 { f.write_str(" ")?; }
+            combo.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BindingList {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize, Default)]
+struct KeymapFile {
+    #[serde(default)]
+    bindings: BTreeMap<String, BindingList>,
+}
+
+/// Every command's bindings, and what they triggered this frame
+pub struct Keymap {
+    bindings: Vec<(Command, Binding)>,
+    /// The combos of a chord typed so far
+    pending: Vec<Combo>,
+    triggered: Vec<Command>,
+    wheel: f32,
+    /// Commands whose bindings get in each other's way
+    conflicts: Vec<(Command, Command)>,
+    /// Anything wrong with the config file, including conflicts
+    problems: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl Keymap {
+    const FILE_NAME: &str = "keymap.toml";
+
+    /// Load the bindings from the config directory, falling back on the defaults for anything it
+    /// doesn't mention or that can't be read
+    pub fn load() -> Self {
+        let path = settings::config_dir().map(|dir| dir.join(Self::FILE_NAME));
+        let mut problems = Vec::new();
+        let file = match path.as_deref().map(fs::read_to_string) {
+            Some(Ok(text)) => toml::from_str(&text).unwrap_or_else(|e| {
+                problems.push(format!("{}: {e}", Self::FILE_NAME));
+                KeymapFile::default()
+            }),
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => {
+                problems.push(format!("{}: {e}", Self::FILE_NAME));
+                KeymapFile::default()
+            }
+            _ => KeymapFile::default(),
+        };
+
+        for name in file.bindings.keys() {
+            if Command::from_name(name).is_none() {
+                problems.push(format!("unknown command \"{name}\""));
+            }
+        }
+
+        let mut bindings = Vec::new();
+        for &command in Command::ALL {
+            let texts: Vec<&str> = match file.bindings.get(command.name()) {
+                Some(BindingList::One(text)) => vec![text.as_str()],
+                Some(BindingList::Many(texts)) => texts.iter().map(String::as_str).collect(),
+                None => command.default_bindings().to_vec(),
+            };
+            for text in texts {
+                match text.parse::<Binding>() {
+                    Ok(binding) => bindings.push((command, binding)),
+                    Err(e) => problems.push(format!("{}: {e}", command.name())),
+                }
+            }
+        }
+
+        let mut conflicts = Vec::new();
+        for (i, (a, a_binding)) in bindings.iter().enumerate() {
+            for (b, b_binding) in &bindings[i + 1..] {
+                if a != b && a.context().overlaps(b.context()) {
+                    if a_binding == b_binding {
+                        problems.push(format!("{} and {} are both bound to {a_binding}", a.name(), b.name()));
+                    } else if a_binding.is_prefix_of(b_binding) || b_binding.is_prefix_of(a_binding) {
+                        problems.push(format!("{a_binding} ({}) starts {b_binding} ({})", a.name(), b.name()));
+                    } else {
+                        continue;
+                    }
+                    conflicts.push((*a, *b));
+                }
+            }
+        }
+
+        Self {
+            bindings,
+            pending: Vec::new(),
+            triggered: Vec::new(),
+            wheel: 0.0,
+            conflicts,
+            problems,
+            path,
+        }
+    }
+
+    /// Find what this frame's input triggered; call once per frame before asking
+    pub fn update(&mut self, rl: &RaylibHandle) {
+        self.triggered.clear();
+        self.wheel = rl.get_mouse_wheel_move();
+        let modifiers = Modifiers::current(rl);
+        let n = self.pending.len();
+        let mut pending = None;
+        for (command, Binding(combos)) in &self.bindings {
+            if combos.len() > n && combos[..n] == self.pending[..] && combos[n].is_pressed(rl, modifiers) {
+                if combos.len() == n + 1 {
+                    self.triggered.push(*command);
+                } else {
+                    pending = Some(combos[..=n].to_vec());
+                }
+            }
+        }
+
+        if !self.triggered.is_empty() {
+            self.pending.clear();
+        } else if let Some(pending) = pending {
+            self.pending = pending;
+        } else if n > 0 && self.bindings.iter().any(|(_, Binding(combos))| combos.iter().any(|combo| combo.trigger.is_pressed(rl))) {
+            // pressing anything that doesn't continue the chord gives up on it
+            self.pending.clear();
+        }
+    }
+
+    #[inline]
+    pub fn is_triggered(&self, command: Command) -> bool {
+        self.triggered.contains(&command)
+    }
+
+    /// How far the wheel scrolled if it triggered `command` this frame, otherwise 0
+    pub fn scroll(&self, command: Command) -> f32 {
+        if self.is_triggered(command) { self.wheel } else { 0.0 }
+    }
+
+    /// Whether the key or button that finishes one of `command`'s bindings is still down,
+    /// for ending drags that it started
+    pub fn is_down(&self, rl: &RaylibHandle, command: Command) -> bool {
+        self.bindings(command).any(|Binding(combos)| combos.last().is_some_and(|combo| combo.trigger.is_down(rl)))
+    }
+
+    pub fn bindings(&self, command: Command) -> impl Iterator<Item = &Binding> {
+        self.bindings.iter().filter(move |(c, _)| *c == command).map(|(_, binding)| binding)
+    }
+
+    pub fn is_conflicting(&self, command: Command) -> bool {
+        self.conflicts.iter().any(|&(a, b)| a == command || b == command)
+    }
+
+    #[inline]
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+
+    /// Where the bindings are loaded from, if there is a config directory
+    #[inline]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
@@ -1,101 +1,629 @@
 #![windows_subsystem = "windows"]
 
+use std::{panic::{self, AssertUnwindSafe}, path::{Path, PathBuf}};
 use raylib::prelude::*;
 
 mod brush;
 mod frame;
 mod editor;
+mod raster;
+mod codec;
+mod dialog;
+mod tilemap;
+mod keymap;
+mod settings;
+mod recovery;
+mod document;
+mod tabs;
+mod error;
+mod toast;
 
 use brush::Brush;
 use frame::Frame;
-use editor::{EditorID, art::ArtEditor, color::ColorEditor};
-use rfd::FileDialog;
+use editor::{EditorID, columns, art::{ArtEditor, View}, color::ColorEditor, tilemap::TilemapEditor};
+use dialog::{Action, Dialog, DialogResult, adjust::AdjustDialog, canvas_size::CanvasSizeDialog, effect::EffectDialog, export_animation::ExportAnimationDialog, export_image::ExportImageDialog, grid::GridDialog, image_size::ImageSizeDialog, keymap::KeymapDialog, new_document::NewDocumentDialog, recovery::RecoveryDialog, sprite_sheet::{ExportSheetDialog, SliceSheetDialog}, tilemap::NewTilemapDialog, unsaved::{Intent, UnsavedChangesDialog}};
+use codec::{FrameSource, format::{self, EncodeOptions, FORMATS}};
+use keymap::{Command, Keymap};
+use document::Document;
+use recovery::Recoverable;
+use tabs::{TabBar, TabCommand};
+use toast::Toasts;
+use settings::{BrushSettings, Session, Settings, ViewSettings};
+use error::Error;
+use raster::{adjust::Adjustment, effect::Effect};
+use rfd::{FileDialog, MessageDialog, MessageLevel};
 use crate::editor::Editor;
 
+/// Where the editors below the color editor and the tabs start
+const EDITOR_TOP: i32 = ColorEditor::HEIGHT + TabBar::HEIGHT;
+
 fn main() {
+    let mut settings = Settings::load();
+
     let (mut rl, thread) = init()
+        .size(settings.window.width, settings.window.height)
         .title("Amity Raster Art")
         .resizable()
         .build();
 
     rl.set_exit_key(None);
-    rl.maximize_window();
+    if settings.window.is_maximized {
+        rl.maximize_window();
+    }
 
     unsafe {
         ffi::EnableEventWaiting();
     }
 
-    let mut brush = Brush::new(0.5, Color::WHITE);
+    let mut brush = Brush::from(&settings.brush);
+    let mut toasts = Toasts::new();
+    for problem in settings.problems() {
+        toasts.push(rl.get_time(), problem.clone());
+    }
 
-    let mut art_editor = ArtEditor::new(rl.load_render_texture(&thread, 720, 480).unwrap());
+    let session = &settings.session;
+    // the file may ask for a canvas the GPU can't hold, which is no reason not to start
+    let side = |side: i32| side.clamp(1, codec::MAX_SIZE as i32) as u32;
+    let fallback = Session::default();
+    let mut document = match new_document(&mut rl, &thread, side(session.canvas_width), side(session.canvas_height), Color::BLANK)
+        .or_else(|err| {
+            toasts.push(rl.get_time(), format!("Couldn't make the last session's canvas, so it's the default size: {err}"));
+            new_document(&mut rl, &thread, side(fallback.canvas_width), side(fallback.canvas_height), Color::BLANK)
+        })
+    {
+        Ok(document) => document,
+        Err(err) => return fail_to_start(err),
+    };
+    // pick up where the last session left off, saying so if the file can't be opened anymore
+    if let Some(path) = session.path.clone() {
+        let time = rl.get_time();
+        if let Some(warnings) = toasts.report(time, &format!("Couldn't reopen {}", path.display()), document.art_editor.open(&mut rl, &thread, &path)) {
+            for warning in warnings {
+                toasts.push(time, warning);
+            }
+            document.path = Some(path);
+        }
+    }
+    if let Some(view) = session.view {
+        document.art_editor.set_view(View::from(view));
+    }
+    let mut documents = vec![document];
+    let mut active = 0;
     let mut color_editor = ColorEditor::new(&mut rl, &thread, &brush);
+    color_editor.set_palette(settings.palette.iter().map(|&[h, s, v]| Vector3::new(h, s, v)));
+    let mut tab_bar = TabBar::new();
     let mut current_editor = EditorID::Art;
-    let mut frame = Frame::new(&mut rl, &thread);
-    let mut current_path = None;
+    let mut frame = match Frame::new(&mut rl, &thread) {
+        Ok(frame) => frame,
+        Err(err) => return fail_to_start(err),
+    };
+    let mut keymap = Keymap::load();
+    let recoverable = Recoverable::list();
+    // work left behind by a crash comes first, then anything wrong with the keymap
+    let mut dialog = if !recoverable.is_empty() {
+        Some(Dialog::Recovery(RecoveryDialog::new(recoverable)))
+    } else if !keymap.problems().is_empty() {
+        Some(Dialog::Keymap(KeymapDialog::new(&keymap)))
+    } else {
+        None
+    };
+    let mut is_waiting_for_events = true;
+    let mut title = String::new();
+
+    // a panic still gets to keep a copy of every open canvas before going down
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        loop {
+            rl.poll_input_events();
+            let time = rl.get_time();
+            let previous = active;
+
+            // anything that would lose unsaved changes waits here until they've been asked about
+            let mut intent = rl.window_should_close().then_some(Intent::Quit);
+            if intent.is_some() {
+                // what was open when quitting was asked for is what's reopened next time
+                let document = &documents[active];
+                settings.session = Session {
+                    path: document.path.clone(),
+                    canvas_width: document.art_editor.width(),
+                    canvas_height: document.art_editor.height(),
+                    view: Some(ViewSettings::from(document.art_editor.view())),
+                };
+            }
 
-    art_editor.set_pan(Vector2::new(0.0, ColorEditor::HEIGHT as f32));
+            keymap.update(&rl);
 
-    while !rl.window_should_close() {
-        rl.poll_input_events();
+            let mouse_pos = rl.get_mouse_position();
 
-        if rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL) {
-            if rl.is_key_pressed(KeyboardKey::KEY_S) {
-                if current_path.is_none() || (rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT)) {
-                    current_path = FileDialog::new()
+            if rl.is_window_resized() {
+                toasts.report(time, "Couldn't resize the window", frame.resize(&mut rl, &thread));
+                documents[active].art_editor.mark_dirty();
+                documents[active].tilemap_editor.mark_dirty();
+                color_editor.mark_dirty();
+                tab_bar.mark_dirty();
+            }
+
+            let color_viewport = rrect(0, 0, rl.get_screen_width(), ColorEditor::HEIGHT);
+            let tab_viewport = rrect(0, ColorEditor::HEIGHT, rl.get_screen_width(), TabBar::HEIGHT);
+            let editor_area = rrect(0, EDITOR_TOP, rl.get_screen_width(), rl.get_screen_height() - EDITOR_TOP);
+            // the tilemap takes the right half, next to the tileset being painted; the art editor splits
+            // what's left between its views
+            let (art_viewport, tilemap_viewport) = if documents[active].is_tilemap_mode {
+                let halves = columns(editor_area, 2);
+                (halves[0], halves[1])
+            } else {
+                (editor_area, Rectangle { width: 0.0, ..editor_area })
+            };
+
+            let is_color_editor_focused = color_editor.is_focused();
+            let is_art_editor_focused = documents[active].art_editor.is_focused();
+            let is_tilemap_editor_focused = documents[active].is_tilemap_mode && documents[active].tilemap_editor.is_focused();
+
+            debug_assert!([is_color_editor_focused, is_art_editor_focused, is_tilemap_editor_focused].into_iter().map(|x| x as usize).sum::<usize>() <= 1, "only one editor should be focused at a time");
+
+            if !is_color_editor_focused && !is_art_editor_focused && !is_tilemap_editor_focused {
+                current_editor = match mouse_pos.y as i32 {
+                    ..ColorEditor::HEIGHT => EditorID::Color,
+                    ..EDITOR_TOP => EditorID::Tabs,
+                    EDITOR_TOP.. if documents[active].is_tilemap_mode && mouse_pos.x >= tilemap_viewport.x => EditorID::Tilemap,
+                    EDITOR_TOP.. => EditorID::Art,
+                };
+            }
+
+            // switching and closing tabs happen before anything is done to the active document
+            let titles = documents.iter().map(Document::title).collect();
+            match tab_bar.update(&mut rl, &thread, tab_viewport, &mut frame, titles, active, dialog.is_none() && current_editor == EditorID::Tabs) {
+                Some(TabCommand::Select(i)) => active = i,
+                Some(TabCommand::Close(i)) => {
+                    active = i;
+                    intent = Some(Intent::Close);
+                }
+                Some(TabCommand::New) => {
+                    let document = &documents[active].art_editor;
+                    dialog = Some(Dialog::NewDocument(NewDocumentDialog::new(document.width(), document.height(), brush.color, settings.canvas_presets.clone())));
+                }
+                None => {}
+            }
+            if dialog.is_none() && !documents[active].art_editor.is_transforming() {
+                if keymap.is_triggered(Command::NextDocument) {
+                    active = (active + 1) % documents.len();
+                } else if keymap.is_triggered(Command::PreviousDocument) {
+                    active = (active + documents.len() - 1) % documents.len();
+                }
+            }
+
+            // opened files and new documents join the tabs once nothing else is using them
+            let mut opened = None;
+            let name = documents[active].name();
+            let Document { art_editor, tilemap_editor, is_tilemap_mode, path: current_path, encode_options, autosave } = &mut documents[active];
+
+            if dialog.is_none() && !art_editor.is_transforming() {
+                if keymap.is_triggered(Command::CanvasSize) {
+                    dialog = Some(Dialog::CanvasSize(CanvasSizeDialog::new(art_editor.width(), art_editor.height())));
+                } else if keymap.is_triggered(Command::ImageSize) {
+                    dialog = Some(Dialog::ImageSize(ImageSizeDialog::new(art_editor.width(), art_editor.height())));
+                } else if keymap.is_triggered(Command::CropToSelection) {
+                    toasts.report(time, "Couldn't crop", art_editor.crop_to_selection(&mut rl, &thread));
+                } else if keymap.is_triggered(Command::Trim) {
+                    toasts.report(time, "Couldn't trim", art_editor.trim(&mut rl, &thread));
+                } else if keymap.is_triggered(Command::FlipHorizontal) {
+                    toasts.report(time, "Couldn't flip", art_editor.flip(&mut rl, &thread, true));
+                } else if keymap.is_triggered(Command::FlipVertical) {
+                    toasts.report(time, "Couldn't flip", art_editor.flip(&mut rl, &thread, false));
+                } else if keymap.is_triggered(Command::RotateClockwise) {
+                    toasts.report(time, "Couldn't rotate", art_editor.rotate(&mut rl, &thread, 1));
+                } else if keymap.is_triggered(Command::RotateHalf) {
+                    toasts.report(time, "Couldn't rotate", art_editor.rotate(&mut rl, &thread, 2));
+                } else if keymap.is_triggered(Command::RotateCounterClockwise) {
+                    toasts.report(time, "Couldn't rotate", art_editor.rotate(&mut rl, &thread, 3));
+                } else if keymap.is_triggered(Command::BoxBlur) {
+                    dialog = Some(Dialog::Effect(EffectDialog::box_blur()));
+                } else if keymap.is_triggered(Command::GaussianBlur) {
+                    dialog = Some(Dialog::Effect(EffectDialog::gaussian_blur()));
+                } else if keymap.is_triggered(Command::UnsharpMask) {
+                    dialog = Some(Dialog::Effect(EffectDialog::unsharp_mask()));
+                } else if keymap.is_triggered(Command::EdgeDetect) {
+                    toasts.report(time, "Couldn't apply the filter", art_editor.apply_effect(&mut rl, &thread, |raster| Effect::EdgeDetect.apply(raster)));
+                } else if keymap.is_triggered(Command::Outline) {
+                    dialog = Some(Dialog::Effect(EffectDialog::outline(brush.color)));
+                } else if keymap.is_triggered(Command::DropShadow) {
+                    dialog = Some(Dialog::Effect(EffectDialog::drop_shadow(Color::new(0, 0, 0, 128))));
+                } else if keymap.is_triggered(Command::RemoveOrphans) {
+                    dialog = Some(Dialog::Effect(EffectDialog::remove_orphans()));
+                } else if keymap.is_triggered(Command::NewTilemap) {
+                    dialog = Some(Dialog::NewTilemap(NewTilemapDialog::new()));
+                } else if keymap.is_triggered(Command::GridSettings) {
+                    dialog = Some(Dialog::Grid(GridDialog::new(*art_editor.grid())));
+                } else if keymap.is_triggered(Command::ClearGuides) {
+                    art_editor.clear_guides();
+                } else if keymap.is_triggered(Command::ResetRotation) {
+                    art_editor.reset_rotation();
+                } else if keymap.is_triggered(Command::HueSaturation) {
+                    dialog = Some(Dialog::Adjust(AdjustDialog::hue_saturation()));
+                } else if keymap.is_triggered(Command::Desaturate) {
+                    toasts.report(time, "Couldn't adjust the colors", art_editor.apply_effect(&mut rl, &thread, |raster| Adjustment::Desaturate.apply(raster)));
+                } else if keymap.is_triggered(Command::Invert) {
+                    toasts.report(time, "Couldn't adjust the colors", art_editor.apply_effect(&mut rl, &thread, |raster| Adjustment::Invert.apply(raster)));
+                } else if keymap.is_triggered(Command::Levels) {
+                    dialog = Some(Dialog::Adjust(AdjustDialog::levels()));
+                } else if keymap.is_triggered(Command::Curves) {
+                    dialog = Some(Dialog::Adjust(AdjustDialog::curves()));
+                } else if keymap.is_triggered(Command::ColorBalance) {
+                    dialog = Some(Dialog::Adjust(AdjustDialog::color_balance()));
+                } else if keymap.is_triggered(Command::BrightnessContrast) {
+                    dialog = Some(Dialog::Adjust(AdjustDialog::brightness_contrast()));
+                } else if keymap.is_triggered(Command::Posterize) {
+                    dialog = Some(Dialog::Adjust(AdjustDialog::posterize()));
+                } else if keymap.is_triggered(Command::ToggleTilemap) {
+                    if tilemap_editor.has_map() {
+                        *is_tilemap_mode = !*is_tilemap_mode;
+                        art_editor.mark_dirty();
+                        tilemap_editor.mark_dirty();
+                    } else {
+                        dialog = Some(Dialog::NewTilemap(NewTilemapDialog::new()));
+                    }
+                } else if keymap.is_triggered(Command::Deselect) {
+                    art_editor.clear_selection();
+                } else if keymap.is_triggered(Command::ToggleGrid) {
+                    art_editor.toggle_grid();
+                } else if keymap.is_triggered(Command::ToggleGuides) {
+                    art_editor.toggle_guides();
+                } else if keymap.is_triggered(Command::ToggleSnapping) {
+                    art_editor.toggle_snapping();
+                } else if keymap.is_triggered(Command::OpenReference) {
+                    let load_path = image_filters(FileDialog::new(), false).pick_file();
+
+                    if let Some(path) = &load_path {
+                        toasts.report(time, "Couldn't open the reference", art_editor.add_reference(&mut rl, &thread, path));
+                    }
+                } else if keymap.is_triggered(Command::UnpinReferences) {
+                    art_editor.unpin_references();
+                } else if keymap.is_triggered(Command::SplitView) {
+                    art_editor.split_view();
+                } else if keymap.is_triggered(Command::CloseView) {
+                    art_editor.close_view();
+                } else if keymap.is_triggered(Command::ZoomToFit) {
+                    art_editor.zoom_to_fit();
+                } else if keymap.is_triggered(Command::ZoomToActualSize) {
+                    art_editor.zoom_to_actual_size();
+                } else if keymap.is_triggered(Command::Transform) {
+                    toasts.report(time, "Couldn't start transforming", art_editor.begin_transform(&mut rl, &thread));
+                } else if keymap.is_triggered(Command::Save) || keymap.is_triggered(Command::SaveAs) {
+                    let is_save_as = keymap.is_triggered(Command::SaveAs);
+                    // somewhere that can only be opened from needs somewhere new to save to
+                    if let Some(path) = current_path.clone().filter(|path| !is_save_as && format::can_write(path)) {
+                        toasts.report(time, "Couldn't save", save(art_editor, path, *encode_options, current_path, encode_options));
+                    } else if let Some(path) = pick_save_path() {
+                        // a new place to save might be in a format with options to choose
+                        match format::by_extension(&path) {
+                            Some(format) if !format.options().is_empty() => {
+                                dialog = Some(Dialog::ExportImage(ExportImageDialog::new(path, format, *encode_options)));
+                            }
+                            _ => {
+                                toasts.report(time, "Couldn't save", save(art_editor, path, *encode_options, current_path, encode_options));
+                            }
+                        }
+                    }
+                } else if *is_tilemap_mode && keymap.is_triggered(Command::Export) {
+                    if let Some(path) = pick_tilemap_path() {
+                        toasts.report(time, "Couldn't export the tilemap", export_tilemap(art_editor, tilemap_editor, &path));
+                    }
+                } else if keymap.is_triggered(Command::ExportSheet) {
+                    let export_path = FileDialog::new()
                         .set_can_create_directories(true)
-                        .add_filter("image", &["png"])
-                        .set_file_name("image.png")
+                        .add_filter("sprite sheet", &["png"])
+                        .set_file_name("sheet.png")
                         .save_file();
+
+                    if let Some(path) = export_path {
+                        dialog = Some(Dialog::ExportSheet(ExportSheetDialog::new(path, art_editor.frame_count())));
+                    }
+                } else if keymap.is_triggered(Command::Export) {
+                    let export_path = FileDialog::new()
+                        .set_can_create_directories(true)
+                        .add_filter("GIF", &["gif"])
+                        .add_filter("animated PNG", &["png"])
+                        .set_file_name("animation.gif")
+                        .save_file();
+
+                    if let Some(path) = export_path {
+                        dialog = Some(Dialog::ExportAnimation(ExportAnimationDialog::new(path)));
+                    }
+                } else if keymap.is_triggered(Command::ImportFrames) {
+                    intent = Some(Intent::ImportFrames);
+                } else if keymap.is_triggered(Command::NewDocument) {
+                    dialog = Some(Dialog::NewDocument(NewDocumentDialog::new(art_editor.width(), art_editor.height(), brush.color, settings.canvas_presets.clone())));
+                } else if keymap.is_triggered(Command::Open) {
+                    let load_path = image_filters(FileDialog::new(), false).pick_file();
+
+                    if let Some(path) = load_path {
+                        let document = new_document(&mut rl, &thread, 1, 1, Color::BLANK).and_then(|mut document| {
+                            let warnings = document.art_editor.open(&mut rl, &thread, &path)?;
+                            document.path = Some(path.clone());
+                            Ok((document, warnings))
+                        });
+                        if let Some((document, warnings)) = toasts.report(time, &format!("Couldn't open {}", path.display()), document) {
+                            for warning in warnings {
+                                toasts.push(time, warning);
+                            }
+                            opened = Some(document);
+                        }
+                    }
+                } else if keymap.is_triggered(Command::CloseDocument) {
+                    intent = Some(Intent::Close);
+                } else if keymap.is_triggered(Command::ShowKeymap) {
+                    dialog = Some(Dialog::Keymap(KeymapDialog::new(&keymap)));
                 }
+            }
 
-                if let Some(path) = &current_path {
-                    art_editor.save(&mut rl, &thread, path).unwrap();
+            let mut proceeding = None;
+            if let Some(intent) = intent {
+                // replacing the frames leaves the tilemap be
+                let is_tilemap_lost = intent != Intent::ImportFrames && tilemap_editor.is_modified();
+                if !art_editor.is_modified() && !is_tilemap_lost {
+                    proceeding = Some(intent);
+                } else if !matches!(dialog, Some(Dialog::UnsavedChanges(_))) {
+                    // closing the window can interrupt another dialog
+                    toasts.report(time, "Couldn't undo the preview", art_editor.cancel_preview(&mut rl, &thread));
+                    dialog = Some(Dialog::UnsavedChanges(UnsavedChangesDialog::new(intent, name)));
+                    art_editor.mark_dirty();
+                    color_editor.mark_dirty();
+                    tilemap_editor.mark_dirty();
+                    tab_bar.mark_dirty();
                 }
-            } else if rl.is_key_pressed(KeyboardKey::KEY_O) {
-                let load_path = FileDialog::new()
-                    .add_filter("image", &["png"])
-                    .pick_file();
+            }
 
-                if let Some(path) = &load_path {
-                    art_editor.import(&mut rl, &thread, path).unwrap();
+            let updated = color_editor.update(&mut rl, &thread, &keymap, &mut brush, color_viewport, &mut frame, dialog.is_none() && current_editor == EditorID::Color);
+            toasts.report(time, "Couldn't update the colors", updated);
+
+            let updated = art_editor.update(&mut rl, &thread, &keymap, &mut brush, art_viewport, &mut frame, dialog.is_none() && current_editor == EditorID::Art);
+            toasts.report(time, "Couldn't edit the canvas", updated);
+
+            if *is_tilemap_mode {
+                // any change to the tileset shows up wherever its tiles are placed
+                if art_editor.was_redrawn() {
+                    toasts.report(time, "Couldn't copy the tileset", tilemap_editor.sync_tileset(&mut rl, &thread, art_editor.canvas()));
                 }
+                let updated = tilemap_editor.update(&mut rl, &thread, &keymap, &mut brush, tilemap_viewport, &mut frame, dialog.is_none() && current_editor == EditorID::Tilemap);
+                toasts.report(time, "Couldn't edit the tilemap", updated);
             }
-        }
 
-        let mouse_pos = rl.get_mouse_position();
+            if let Some(current_dialog) = &mut dialog {
+                let screen = rrect(0, 0, rl.get_screen_width(), rl.get_screen_height());
+                let result = current_dialog.update(&mut frame.begin_drawing(&mut rl, &thread), screen);
+                if let Some(preview) = current_dialog.take_preview() {
+                    toasts.report(time, "Couldn't preview", preview.show(&mut rl, &thread, art_editor));
+                }
+                if !matches!(result, DialogResult::Pending) {
+                    dialog = None;
+                    if let DialogResult::Confirm(action) = result {
+                        if matches!(action, Action::NewTilemap { .. }) {
+                            *is_tilemap_mode = true;
+                        }
+                        // the document only takes the recovered file's path once it holds the recovered artwork
+                        let recovered_path = match &action { Action::Recover(recoverable) => Some(recoverable.path.clone()), _ => None };
+                        if let Action::NewDocument { width, height, background, presets } = &action {
+                            settings.canvas_presets = presets.clone();
+                            opened = toasts.report(time, "Couldn't create the document", new_document(&mut rl, &thread, *width, *height, *background));
+                        }
+                        if let Action::SaveImage { path, options } = &action {
+                            toasts.report(time, "Couldn't save", save(art_editor, path.clone(), *options, current_path, encode_options));
+                        }
+                        if let &Action::Proceed { intent, is_saving } = &action
+                            && (!is_saving || !art_editor.is_modified() || current_path.clone().filter(|path| format::can_write(path)).or_else(pick_save_path).is_some_and(|path| {
+                                toasts.report(time, "Couldn't save", save(art_editor, path, *encode_options, current_path, encode_options)).is_some()
+                            }))
+                            // the map isn't in the image, so saving it is a separate export
+                            && (!is_saving || intent == Intent::ImportFrames || !tilemap_editor.is_modified() || pick_tilemap_path().is_some_and(|path| {
+                                toasts.report(time, "Couldn't export the tilemap", export_tilemap(art_editor, tilemap_editor, &path)).is_some()
+                            }))
+                        {
+                            proceeding = Some(intent);
+                        }
+                        let what = format!("Couldn't {}", action.description());
+                        let applied = toasts.report(time, &what, action.apply(&mut rl, &thread, art_editor, tilemap_editor));
+                        if let (Some(()), Some(path)) = (applied, recovered_path) {
+                            *current_path = path;
+                            autosave.mark_changed();
+                        }
+                    } else {
+                        toasts.report(time, "Couldn't undo the preview", art_editor.cancel_preview(&mut rl, &thread));
+                    }
+                    art_editor.mark_dirty();
+                    color_editor.mark_dirty();
+                    tilemap_editor.mark_dirty();
+                    tab_bar.mark_dirty();
+                }
+            }
 
-        if rl.is_window_resized() {
-            frame.resize(&mut rl, &thread);
-            art_editor.mark_dirty();
-            color_editor.mark_dirty();
-        }
+            if proceeding == Some(Intent::ImportFrames) {
+                let load_path = image_filters(FileDialog::new(), false).pick_file();
 
-        let is_color_editor_focused = color_editor.is_focused();
-        let is_art_editor_focused = art_editor.is_focused();
+                if let Some(path) = &load_path {
+                    match toasts.report(time, "Couldn't import frames", codec::read_frames(path)) {
+                        Some(FrameSource::Animation(animation)) => {
+                            toasts.report(time, "Couldn't import frames", art_editor.load_animation(&mut rl, &thread, animation));
+                        }
+                        Some(FrameSource::Sheet(sheet)) => {
+                            dialog = Some(Dialog::SliceSheet(SliceSheetDialog::new(sheet, art_editor.width(), art_editor.height())));
+                        }
+                        None => {}
+                    }
+                }
+            }
 
-        debug_assert!([is_color_editor_focused, is_art_editor_focused].into_iter().map(|x| x as usize).sum::<usize>() <= 1, "only one editor should be focused at a time");
+            // redraws include panning and zooming, which only cost a copy that wasn't needed;
+            // once saved, there's nothing a copy would add
+            if art_editor.was_redrawn() && art_editor.is_modified() {
+                autosave.mark_changed();
+            }
+            // tabs in the background keep being copied, so a crash loses none of them
+            for document in &mut documents {
+                let what = format!("Couldn't autosave {}", document.name());
+                match document.autosave.is_due(rl.get_time()) {
+                    Ok(true) => match document.art_editor.animation() {
+                        Ok(animation) => document.autosave.save(rl.get_time(), animation, document.path.clone()),
+                        Err(err) => {
+                            document.autosave.postpone(rl.get_time());
+                            toasts.push(time, format!("{what}: {err}"));
+                        }
+                    },
+                    Ok(false) => {}
+                    Err(err) => toasts.push(time, format!("{what}: {err}")),
+                }
+            }
 
-        if !is_color_editor_focused && !art_editor.is_focused() {
-            current_editor = match mouse_pos.y as i32 {
-                ..ColorEditor::HEIGHT => EditorID::Color,
-                ColorEditor::HEIGHT.. => EditorID::Art,
-            };
-        }
+            match proceeding {
+                Some(Intent::Quit) => {
+                    // the one that was asked about goes, along with every one that needn't be asked about
+                    documents.remove(active).close();
+                    let (modified, saved): (Vec<_>, Vec<_>) = documents.drain(..).partition(Document::is_modified);
+                    saved.into_iter().for_each(Document::close);
+                    documents = modified;
+                    let Some(next) = documents.first() else { break; };
+                    active = 0;
+                    dialog = Some(Dialog::UnsavedChanges(UnsavedChangesDialog::new(Intent::Quit, next.name())));
+                }
+                Some(Intent::Close) => {
+                    // there's always something to paint on, so the last one stays if there's no room for another
+                    let replacement = if documents.len() == 1 {
+                        let closing = &documents[active].art_editor;
+                        toasts.report(time, "Couldn't close the document", new_document(&mut rl, &thread, closing.width() as u32, closing.height() as u32, Color::BLANK)).map(Some)
+                    } else {
+                        Some(None)
+                    };
+                    if let Some(replacement) = replacement {
+                        documents.remove(active).close();
+                        documents.extend(replacement);
+                        active = active.min(documents.len() - 1);
+                    }
+                }
+                Some(Intent::ImportFrames) | None => {}
+            }
+            if let Some(document) = opened {
+                documents.push(document);
+                active = documents.len() - 1;
+            }
+            if active != previous || proceeding.is_some_and(|intent| intent != Intent::ImportFrames) {
+                documents[active].art_editor.mark_dirty();
+                documents[active].tilemap_editor.mark_dirty();
+                color_editor.mark_dirty();
+                tab_bar.mark_dirty();
+            }
+
+            let new_title = format!("{} - Amity Raster Art", documents[active].title());
+            if new_title != title {
+                rl.set_window_title(&thread, &new_title);
+                title = new_title;
+            }
 
-        let color_viewport = rrect(0, 0, rl.get_screen_width(), ColorEditor::HEIGHT);
-        color_editor.update(&mut rl, &thread, &mut brush, color_viewport, &mut frame, current_editor == EditorID::Color);
+            // animation playback and toasts going away need to keep ticking without input
+            if (documents[active].art_editor.is_playing() || toasts.is_showing()) == is_waiting_for_events {
+                is_waiting_for_events = !is_waiting_for_events;
+                unsafe {
+                    if is_waiting_for_events {
+                        ffi::EnableEventWaiting();
+                    } else {
+                        ffi::DisableEventWaiting();
+                    }
+                }
+            }
 
-        let art_viewport = rrect(0, ColorEditor::HEIGHT, rl.get_screen_width(), rl.get_screen_height() - ColorEditor::HEIGHT);
-        art_editor.update(&mut rl, &thread, &mut brush, art_viewport, &mut frame, current_editor == EditorID::Art);
+            toasts.update(time);
+            if frame.is_dirty() || toasts.is_dirty() {
+                #[cfg(debug_assertions)] {
+                    let mut d = frame.begin_drawing(&mut rl, &thread);
+                    d.draw_rectangle(0, 0, 60, 10, Color::BLACK);
+                    d.draw_text(&d.get_time().to_string(), 0, 0, 10, Color::MAGENTA);
+                }
+                {
+                    let screen = rrect(0, 0, rl.get_screen_width(), rl.get_screen_height());
+                    let mut d = rl.begin_drawing(&thread);
+                    frame.present(&mut d);
+                    toasts.draw(&mut d, screen);
+                }
+                rl.swap_screen_buffer();
+            }
 
-        if frame.is_dirty() {
-            #[cfg(debug_assertions)] {
-                let mut d = frame.begin_drawing(&mut rl, &thread);
-                d.draw_rectangle(0, 0, 60, 10, Color::BLACK);
-                d.draw_text(&d.get_time().to_string(), 0, 0, 10, Color::MAGENTA);
+            if !is_waiting_for_events {
+                unsafe {
+                    ffi::WaitTime(1.0/60.0);
+                }
             }
-            frame.present(&mut rl.begin_drawing(&thread));
-            rl.swap_screen_buffer();
         }
+    }));
+    if let Err(panic) = result {
+        // the last background copy is still there if a canvas can't be read back either;
+        // unchanged documents have nothing to lose
+        for document in documents.iter_mut().filter(|document| document.art_editor.is_modified()) {
+            if let Ok(Ok(animation)) = panic::catch_unwind(AssertUnwindSafe(|| document.art_editor.animation())) {
+                let _ = document.autosave.save_now(&animation, document.path.clone());
+            }
+        }
+        panic::resume_unwind(panic);
+    }
+
+    settings.window.is_maximized = rl.is_window_maximized();
+    if !settings.window.is_maximized {
+        (settings.window.width, settings.window.height) = (rl.get_screen_width(), rl.get_screen_height());
     }
+    settings.brush = BrushSettings::from(&brush);
+    settings.palette = color_editor.palette().map(|color| [color.x, color.y, color.z]).collect();
+    // there's nothing to be done about it failing while quitting
+    let _ = settings.save();
+}
+
+/// There's no window to show toasts in without a frame or a canvas, so it's said in a message box
+fn fail_to_start(err: Error) {
+    MessageDialog::new()
+        .set_level(MessageLevel::Error)
+        .set_title("Amity Raster Art")
+        .set_description(format!("Couldn't start: {err}"))
+        .show();
+}
+
+/// A blank document of the given size, filled with `background`
+fn new_document(rl: &mut RaylibHandle, thread: &RaylibThread, width: u32, height: u32, background: Color) -> error::Result<Document> {
+    let mut canvas = rl.load_render_texture(thread, width, height).map_err(Error::gpu)?;
+    rl.begin_texture_mode(thread, &mut canvas).clear_background(background);
+    let mut art_editor = ArtEditor::new(canvas);
+    art_editor.set_view(View { pan: Vector2::new(0.0, EDITOR_TOP as f32), ..View::default() });
+    Ok(Document::new(art_editor, None))
+}
+
+/// Every still image format together, then each on its own, leaving out those that can only be opened if `is_saving`
+fn image_filters(dialog: FileDialog, is_saving: bool) -> FileDialog {
+    let formats: Vec<_> = FORMATS.iter().filter(|format| !is_saving || format.is_writable()).collect();
+    let extensions: Vec<&str> = formats.iter().flat_map(|format| format.extensions()).copied().collect();
+    formats.iter().fold(dialog.add_filter("image", &extensions), |dialog, format| dialog.add_filter(format.name(), format.extensions()))
+}
+
+/// Ask where to save; the extension picks the format
+fn pick_save_path() -> Option<PathBuf> {
+    image_filters(FileDialog::new().set_can_create_directories(true), true)
+        .set_file_name("image.png")
+        .save_file()
+}
+
+fn pick_tilemap_path() -> Option<PathBuf> {
+    FileDialog::new()
+        .set_can_create_directories(true)
+        .add_filter("Tiled JSON", &["json"])
+        .add_filter("CSV", &["csv"])
+        .add_filter("flattened image", &["png"])
+        .set_file_name("map.json")
+        .save_file()
+}
+
+/// Write the map with the art canvas as its tileset
+fn export_tilemap(art_editor: &ArtEditor, tilemap_editor: &mut TilemapEditor, path: &Path) -> error::Result<()> {
+    tilemap_editor.export(path, &art_editor.download()?)?;
+    Ok(())
+}
+
+/// Save to `path` with `options`, which are where and how the document saves from then on
+fn save(art_editor: &mut ArtEditor, path: PathBuf, options: EncodeOptions, current_path: &mut Option<PathBuf>, current_options: &mut EncodeOptions) -> error::Result<()> {
+    art_editor.save(&path, options)?;
+    *current_path = Some(path);
+    *current_options = options;
+    Ok(())
 }
@@ -0,0 +1,286 @@
+pub mod adjust;
+pub mod blend;
+pub mod effect;
+pub mod quantize;
+pub mod resample;
+pub mod transform;
+
+/// Integer rectangle in pixel coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Smallest rectangle containing both pixels (inclusive)
+    pub fn from_corners((x0, y0): (i32, i32), (x1, y1): (i32, i32)) -> Self {
+        Self {
+            x: x0.min(x1),
+            y: y0.min(y1),
+            width:  x0.abs_diff(x1) as i32 + 1,
+            height: y0.abs_diff(y1) as i32 + 1,
+        }
+    }
+
+    #[inline]
+    pub const fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    #[inline]
+    pub const fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+
+    #[inline]
+    pub const fn contains(&self, x: i32, y: i32) -> bool {
+        self.x <= x && x < self.right() && self.y <= y && y < self.bottom()
+    }
+
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let rect = Rect {
+            x,
+            y,
+            width:  self.right() .min(other.right())  - x,
+            height: self.bottom().min(other.bottom()) - y,
+        };
+        (!rect.is_empty()).then_some(rect)
+    }
+
+    /// Smallest rectangle containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect {
+            x,
+            y,
+            width:  self.right() .max(other.right())  - x,
+            height: self.bottom().max(other.bottom()) - y,
+        }
+    }
+
+    #[inline]
+    pub const fn translated(&self, dx: i32, dy: i32) -> Rect {
+        Rect::new(self.x + dx, self.y + dy, self.width, self.height)
+    }
+}
+
+/// CPU-side RGBA8 image, stored row-major starting from the top-left
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Raster {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Raster {
+    /// A fully transparent image
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::filled(width, height, [0; 4])
+    }
+
+    pub fn filled(width: usize, height: usize, color: [u8; 4]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![color; width*height],
+        }
+    }
+
+    /// Panics if `pixels` is not exactly `width*height` long
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<[u8; 4]>) -> Self {
+        assert_eq!(pixels.len(), width*height, "pixel count should match dimensions");
+        Self { width, height, pixels }
+    }
+
+    /// Panics if `bytes` is not exactly `width*height*4` long
+    pub fn from_bytes(width: usize, height: usize, bytes: &[u8]) -> Self {
+        assert_eq!(bytes.len(), width*height*4, "byte count should match dimensions");
+        Self {
+            width,
+            height,
+            pixels: bytes.chunks_exact(4).map(|px| [px[0], px[1], px[2], px[3]]).collect(),
+        }
+    }
+
+    #[inline]
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    pub const fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width as i32, self.height as i32)
+    }
+
+    #[inline]
+    pub fn pixels(&self) -> &[[u8; 4]] {
+        &self.pixels
+    }
+
+    #[inline]
+    pub fn pixels_mut(&mut self) -> &mut [[u8; 4]] {
+        &mut self.pixels
+    }
+
+    #[inline]
+    pub fn into_pixels(self) -> Vec<[u8; 4]> {
+        self.pixels
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.pixels.as_flattened()
+    }
+
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels[y*self.width + x]
+    }
+
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, color: [u8; 4]) {
+        self.pixels[y*self.width + x] = color;
+    }
+
+    /// Returns `None` if outside the image
+    #[inline]
+    pub fn get_checked(&self, x: i32, y: i32) -> Option<[u8; 4]> {
+        self.bounds().contains(x, y)
+            .then(|| self.get(x as usize, y as usize))
+    }
+
+    /// Clamps coordinates to the nearest edge pixel
+    #[inline]
+    pub fn get_clamped(&self, x: i32, y: i32) -> [u8; 4] {
+        self.get(
+            x.clamp(0, self.width  as i32 - 1) as usize,
+            y.clamp(0, self.height as i32 - 1) as usize,
+        )
+    }
+
+    pub fn row(&self, y: usize) -> &[[u8; 4]] {
+        &self.pixels[y*self.width..(y + 1)*self.width]
+    }
+
+    pub fn flip_vertical(&mut self) {
+        let (w, h) = (self.width, self.height);
+        for y in 0..h/2 {
+            let (top, bottom) = self.pixels.split_at_mut((h - y - 1)*w);
+            top[y*w..(y + 1)*w].swap_with_slice(&mut bottom[..w]);
+        }
+    }
+
+    pub fn flip_horizontal(&mut self) {
+        for row in self.pixels.chunks_exact_mut(self.width) {
+            row.reverse();
+        }
+    }
+
+    pub fn rotate_180(&mut self) {
+        self.pixels.reverse();
+    }
+
+    /// Rotated a quarter turn clockwise, swapping width and height
+    pub fn rotated_cw(&self) -> Raster {
+        let (width, height) = (self.height, self.width);
+        let mut pixels = Vec::with_capacity(width*height);
+        for y in 0..height {
+            pixels.extend((0..width).map(|x| self.get(y, self.height - 1 - x)));
+        }
+        Raster::from_pixels(width, height, pixels)
+    }
+
+    /// Rotated a quarter turn counterclockwise, swapping width and height
+    pub fn rotated_ccw(&self) -> Raster {
+        let (width, height) = (self.height, self.width);
+        let mut pixels = Vec::with_capacity(width*height);
+        for y in 0..height {
+            pixels.extend((0..width).map(|x| self.get(self.width - 1 - y, x)));
+        }
+        Raster::from_pixels(width, height, pixels)
+    }
+
+    /// Fill `rect` (clipped to this image) with a single color
+    pub fn fill_rect(&mut self, rect: Rect, color: [u8; 4]) {
+        let Some(area) = self.bounds().intersect(&rect) else { return; };
+        for y in area.y..area.bottom() {
+            let start = y as usize*self.width + area.x as usize;
+            self.pixels[start..start + area.width as usize].fill(color);
+        }
+    }
+
+    /// Alpha-blend `src` placed at (`x`, `y`) on top of this image
+    pub fn draw_over(&mut self, src: &Raster, x: i32, y: i32) {
+        let Some(area) = self.bounds().intersect(&src.bounds().translated(x, y)) else { return; };
+        for row in area.y..area.bottom() {
+            for col in area.x..area.right() {
+                let dst = &mut self.pixels[row as usize*self.width + col as usize];
+                *dst = blend_over(*dst, src.get((col - x) as usize, (row - y) as usize));
+            }
+        }
+    }
+
+    /// Copy of the pixels within `rect`; areas outside the image are transparent
+    pub fn crop(&self, rect: Rect) -> Raster {
+        let mut out = Raster::new(rect.width.max(0) as usize, rect.height.max(0) as usize);
+        out.blit(self, -rect.x, -rect.y);
+        out
+    }
+
+    /// Overwrite pixels with `src` placed at (`x`, `y`), clipped to this image
+    pub fn blit(&mut self, src: &Raster, x: i32, y: i32) {
+        let Some(area) = self.bounds().intersect(&src.bounds().translated(x, y)) else { return; };
+        let width = area.width as usize;
+        for row in area.y..area.bottom() {
+            let dst_start = row as usize*self.width + area.x as usize;
+            let src_start = (row - y) as usize*src.width + (area.x - x) as usize;
+            self.pixels[dst_start..dst_start + width]
+                .copy_from_slice(&src.pixels[src_start..src_start + width]);
+        }
+    }
+
+    /// Tightest rectangle containing every pixel that isn't fully transparent
+    pub fn content_bounds(&self) -> Option<Rect> {
+        let is_row_empty = |y: &usize| self.row(*y).iter().all(|px| px[3] == 0);
+        let top    = (0..self.height).find(|y| !is_row_empty(y))?;
+        let bottom = (0..self.height).rfind(|y| !is_row_empty(y))?;
+        let is_col_empty = |x: &usize| (top..=bottom).all(|y| self.get(*x, y)[3] == 0);
+        let left  = (0..self.width).find(|x| !is_col_empty(x))?;
+        let right = (0..self.width).rfind(|x| !is_col_empty(x))?;
+        Some(Rect::from_corners((left as i32, top as i32), (right as i32, bottom as i32)))
+    }
+}
+
+/// Porter-Duff "over" of straight-alpha colors
+pub fn blend_over(dst: [u8; 4], src: [u8; 4]) -> [u8; 4] {
+    match (src[3], dst[3]) {
+        (255, _) | (_, 0) => return src,
+        (0, _) => return dst,
+        _ => {}
+    }
+    let sa = src[3] as f32/255.0;
+    let da = dst[3] as f32/255.0*(1.0 - sa);
+    let a = sa + da;
+    let channel = |i: usize| ((src[i] as f32*sa + dst[i] as f32*da)/a).round() as u8;
+    [channel(0), channel(1), channel(2), (a*255.0).round() as u8]
+}
@@ -0,0 +1,349 @@
+use super::Raster;
+
+/// A per-pixel color adjustment; alpha is always left untouched
+#[derive(Debug, Clone, PartialEq)]
+pub enum Adjustment {
+    /// Hue in degrees (-180..=180); saturation and lightness in -1..=1
+    HueSaturation {
+        hue: f32,
+        saturation: f32,
+        lightness: f32,
+    },
+    /// Both in -1..=1
+    BrightnessContrast {
+        brightness: f32,
+        contrast: f32,
+    },
+    Levels {
+        in_black: u8,
+        in_white: u8,
+        /// Midtone exponent; above 1 brightens
+        gamma: f32,
+        out_black: u8,
+        out_white: u8,
+    },
+    /// Control points as (input, output), interpolated with a monotone cubic; applied to all color channels
+    Curves {
+        points: Vec<(u8, u8)>,
+    },
+    Invert,
+    Desaturate,
+    Posterize {
+        /// Values per channel, at least 2
+        levels: u8,
+    },
+    /// Shift towards red, green and blue (negative is towards cyan, magenta and yellow), each in -1..=1
+    ColorBalance {
+        shadows: [f32; 3],
+        midtones: [f32; 3],
+        highlights: [f32; 3],
+    },
+}
+
+impl Adjustment {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::HueSaturation { .. } => "Hue/Saturation",
+            Self::BrightnessContrast { .. } => "Brightness/Contrast",
+            Self::Levels { .. } => "Levels",
+            Self::Curves { .. } => "Curves",
+            Self::Invert => "Invert",
+            Self::Desaturate => "Desaturate",
+            Self::Posterize { .. } => "Posterize",
+            Self::ColorBalance { .. } => "Color Balance",
+        }
+    }
+
+    pub fn apply(&self, raster: &mut Raster) {
+        match self {
+            Self::HueSaturation { hue, saturation, lightness } => {
+                for px in raster.pixels_mut() {
+                    let [h, s, l] = rgb_to_hsl(px[0], px[1], px[2]);
+                    let s = if *saturation > 0.0 { s + (1.0 - s)*saturation } else { s*(1.0 + saturation) };
+                    let l = if *lightness  > 0.0 { l + (1.0 - l)*lightness  } else { l*(1.0 + lightness)  };
+                    let [r, g, b] = hsl_to_rgb((h + hue).rem_euclid(360.0), s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+                    *px = [r, g, b, px[3]];
+                }
+            }
+
+            Self::Desaturate => {
+                for px in raster.pixels_mut() {
+                    let y = luma(px[0], px[1], px[2]);
+                    *px = [y, y, y, px[3]];
+                }
+            }
+
+            _ => {
+                let lut = self.lut();
+                for px in raster.pixels_mut() {
+                    *px = [lut[0][px[0] as usize], lut[1][px[1] as usize], lut[2][px[2] as usize], px[3]];
+                }
+            }
+        }
+    }
+
+    /// Per-channel lookup table for adjustments that treat each channel independently
+    fn lut(&self) -> [[u8; 256]; 3] {
+        let shared = |f: &dyn Fn(f32) -> f32| {
+            let table: [u8; 256] = std::array::from_fn(|i| to_u8(f(i as f32/255.0)));
+            [table; 3]
+        };
+        match self {
+            Self::BrightnessContrast { brightness, contrast } => {
+                let slope = ((contrast.clamp(-1.0, 1.0) + 1.0)*std::f32::consts::FRAC_PI_4).tan();
+                shared(&|v| (v + brightness - 0.5)*slope + 0.5)
+            }
+
+            Self::Levels { in_black, in_white, gamma, out_black, out_white } => {
+                let (in_black, in_white) = (*in_black as f32/255.0, *in_white as f32/255.0);
+                let (out_black, out_white) = (*out_black as f32/255.0, *out_white as f32/255.0);
+                let range = (in_white - in_black).max(1.0/255.0);
+                let exponent = gamma.max(0.01).recip();
+                shared(&|v| out_black + ((v - in_black)/range).clamp(0.0, 1.0).powf(exponent)*(out_white - out_black))
+            }
+
+            Self::Curves { points } => [curve_lut(points); 3],
+
+            Self::Invert => shared(&|v| 1.0 - v),
+
+            Self::Posterize { levels } => {
+                let steps = (levels.max(&2) - 1) as f32;
+                shared(&|v| (v*steps).round()/steps)
+            }
+
+            Self::ColorBalance { shadows, midtones, highlights } => {
+                // tonal range weights from GIMP
+                const A: f32 = 0.25;
+                const B: f32 = 0.333;
+                const SCALE: f32 = 0.7;
+                std::array::from_fn(|channel| std::array::from_fn(|i| {
+                    let v = i as f32/255.0;
+                    let shadow    = ((v - B)/-A + 0.5).clamp(0.0, 1.0)*SCALE;
+                    let midtone   = ((v - B)/A + 0.5).clamp(0.0, 1.0)*((v + B - 1.0)/-A + 0.5).clamp(0.0, 1.0)*SCALE;
+                    let highlight = ((v + B - 1.0)/A + 0.5).clamp(0.0, 1.0)*SCALE;
+                    to_u8(v + shadows[channel]*shadow + midtones[channel]*midtone + highlights[channel]*highlight)
+                }))
+            }
+
+            Self::HueSaturation { .. } | Self::Desaturate => {
+                let identity: [u8; 256] = std::array::from_fn(|i| i as u8);
+                [identity; 3]
+            }
+        }
+    }
+}
+
+#[inline]
+fn to_u8(v: f32) -> u8 {
+    (v*255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Rec. 601 luma
+pub fn luma(r: u8, g: u8, b: u8) -> u8 {
+    (0.299*r as f32 + 0.587*g as f32 + 0.114*b as f32).round() as u8
+}
+
+/// Hue in degrees, saturation and lightness in 0..=1
+pub fn rgb_to_hsl(r: u8, g: u8, b: u8) -> [f32; 3] {
+    let (r, g, b) = (r as f32/255.0, g as f32/255.0, b as f32/255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min)*0.5;
+    let delta = max - min;
+    if delta == 0.0 {
+        return [0.0, 0.0, l];
+    }
+    let s = delta/(1.0 - (2.0*l - 1.0).abs());
+    let h = if max == r {
+        ((g - b)/delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r)/delta + 2.0
+    } else {
+        (r - g)/delta + 4.0
+    };
+    [h*60.0, s, l]
+}
+
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> [u8; 3] {
+    let c = (1.0 - (2.0*l - 1.0).abs())*s;
+    let h = h/60.0;
+    let x = c*(1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = l - c*0.5;
+    [to_u8(r + m), to_u8(g + m), to_u8(b + m)]
+}
+
+/// Evaluate a curve at every input value using monotone cubic (Fritsch-Carlson) interpolation,
+/// holding the ends flat beyond the first and last points
+pub fn curve_lut(points: &[(u8, u8)]) -> [u8; 256] {
+    let mut points: Vec<(f32, f32)> = points.iter().map(|&(x, y)| (x as f32, y as f32)).collect();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    points.dedup_by(|a, b| a.0 == b.0);
+    match points.len() {
+        0 => return std::array::from_fn(|i| i as u8),
+        1 => return [points[0].1.round() as u8; 256],
+        _ => {}
+    }
+
+    let n = points.len();
+    let secants: Vec<f32> = points.windows(2)
+        .map(|w| (w[1].1 - w[0].1)/(w[1].0 - w[0].0))
+        .collect();
+    let mut tangents: Vec<f32> = (0..n).map(|i| match i {
+        0 => secants[0],
+        _ if i == n - 1 => secants[n - 2],
+        _ if secants[i - 1]*secants[i] <= 0.0 => 0.0,
+        _ => (secants[i - 1] + secants[i])*0.5,
+    }).collect();
+    for i in 0..n - 1 {
+        if secants[i] == 0.0 {
+            tangents[i] = 0.0;
+            tangents[i + 1] = 0.0;
+        } else {
+            let alpha = tangents[i]/secants[i];
+            let beta = tangents[i + 1]/secants[i];
+            let norm = alpha.hypot(beta);
+            if norm > 3.0 {
+                let t = 3.0/norm;
+                tangents[i] = t*alpha*secants[i];
+                tangents[i + 1] = t*beta*secants[i];
+            }
+        }
+    }
+
+    std::array::from_fn(|i| {
+        let x = i as f32;
+        let y = if x <= points[0].0 {
+            points[0].1
+        } else if x >= points[n - 1].0 {
+            points[n - 1].1
+        } else {
+            let k = points.windows(2).position(|w| x < w[1].0).unwrap();
+            let ((x0, y0), (x1, y1)) = (points[k], points[k + 1]);
+            let h = x1 - x0;
+            let t = (x - x0)/h;
+            let (t2, t3) = (t*t, t*t*t);
+            (2.0*t3 - 3.0*t2 + 1.0)*y0
+                + (t3 - 2.0*t2 + t)*h*tangents[k]
+                + (-2.0*t3 + 3.0*t2)*y1
+                + (t3 - t2)*h*tangents[k + 1]
+        };
+        y.round().clamp(0.0, 255.0) as u8
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The pixel `adjustment` turns `px` into
+    fn adjusted(adjustment: Adjustment, px: [u8; 4]) -> [u8; 4] {
+        let mut raster = Raster::filled(1, 1, px);
+        adjustment.apply(&mut raster);
+        raster.get(0, 0)
+    }
+
+    fn gray(v: u8) -> [u8; 4] {
+        [v, v, v, 255]
+    }
+
+    fn levels(in_black: u8, in_white: u8, gamma: f32, out_black: u8, out_white: u8) -> Adjustment {
+        Adjustment::Levels { in_black, in_white, gamma, out_black, out_white }
+    }
+
+    #[test]
+    fn invert() {
+        assert_eq!(adjusted(Adjustment::Invert, [10, 200, 255, 77]), [245, 55, 0, 77]);
+    }
+
+    #[test]
+    fn desaturate() {
+        assert_eq!(adjusted(Adjustment::Desaturate, [255, 0, 0, 128]), [76, 76, 76, 128]);
+        assert_eq!(adjusted(Adjustment::Desaturate, [0, 0, 255, 255]), gray(29));
+    }
+
+    #[test]
+    fn levels_stretch_and_clip() {
+        let stretch = || levels(50, 200, 1.0, 0, 255);
+        assert_eq!(adjusted(stretch(), gray(20)), gray(0));
+        assert_eq!(adjusted(stretch(), gray(50)), gray(0));
+        assert_eq!(adjusted(stretch(), gray(110)), gray(102));
+        assert_eq!(adjusted(stretch(), gray(250)), gray(255));
+
+        assert_eq!(adjusted(levels(0, 255, 1.0, 20, 220), [0, 255, 0, 9]), [20, 220, 20, 9]);
+        // a gamma of 2 takes the square root
+        assert_eq!(adjusted(levels(0, 255, 2.0, 0, 255), gray(64)), gray(128));
+    }
+
+    #[test]
+    fn curves_interpolate_between_points() {
+        let inverted = Adjustment::Curves { points: vec![(0, 255), (255, 0)] };
+        assert_eq!(adjusted(inverted, [0, 100, 255, 40]), [255, 155, 0, 40]);
+
+        // straight between the points and flat beyond them
+        let points = vec![(64, 32), (192, 224)];
+        let lut = curve_lut(&points);
+        assert_eq!((lut[0], lut[64], lut[128], lut[192], lut[255]), (32, 32, 128, 224, 224));
+        assert_eq!(curve_lut(&[]), std::array::from_fn(|i| i as u8));
+
+        // never overshoots between points that rise
+        let lut = curve_lut(&[(0, 0), (100, 200), (110, 250), (255, 255)]);
+        assert!(lut.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn posterize() {
+        let posterize = |levels| Adjustment::Posterize { levels };
+        assert_eq!(adjusted(posterize(2), [100, 200, 127, 255]), [0, 255, 0, 255]);
+        assert_eq!(adjusted(posterize(3), [40, 100, 230, 255]), [0, 128, 255, 255]);
+        // fewer than two levels is treated as two
+        assert_eq!(adjusted(posterize(0), gray(200)), gray(255));
+    }
+
+    #[test]
+    fn hue_saturation() {
+        let hue_saturation = |hue, saturation, lightness| Adjustment::HueSaturation { hue, saturation, lightness };
+        assert_eq!(adjusted(hue_saturation(120.0, 0.0, 0.0), [255, 0, 0, 200]), [0, 255, 0, 200]);
+        assert_eq!(adjusted(hue_saturation(-120.0, 0.0, 0.0), [255, 0, 0, 255]), [0, 0, 255, 255]);
+        assert_eq!(adjusted(hue_saturation(0.0, -1.0, 0.0), [255, 0, 0, 255]), gray(128));
+        assert_eq!(adjusted(hue_saturation(0.0, 0.0, 1.0), [255, 0, 0, 255]), gray(255));
+        assert_eq!(adjusted(hue_saturation(0.0, 0.0, -1.0), [255, 0, 0, 255]), gray(0));
+    }
+
+    #[test]
+    fn neutral_settings_change_nothing() {
+        let px = [12, 140, 251, 60];
+        assert_eq!(adjusted(Adjustment::HueSaturation { hue: 0.0, saturation: 0.0, lightness: 0.0 }, px), px);
+        assert_eq!(adjusted(Adjustment::BrightnessContrast { brightness: 0.0, contrast: 0.0 }, px), px);
+        assert_eq!(adjusted(levels(0, 255, 1.0, 0, 255), px), px);
+        assert_eq!(adjusted(Adjustment::ColorBalance { shadows: [0.0; 3], midtones: [0.0; 3], highlights: [0.0; 3] }, px), px);
+    }
+
+    #[test]
+    fn alpha_is_preserved() {
+        let adjustments = [
+            Adjustment::HueSaturation { hue: 90.0, saturation: 0.5, lightness: -0.3 },
+            Adjustment::BrightnessContrast { brightness: 0.4, contrast: 0.8 },
+            levels(30, 180, 0.5, 10, 240),
+            Adjustment::Curves { points: vec![(0, 255), (128, 0), (255, 255)] },
+            Adjustment::Invert,
+            Adjustment::Desaturate,
+            Adjustment::Posterize { levels: 4 },
+            Adjustment::ColorBalance { shadows: [1.0, -1.0, 0.0], midtones: [0.5; 3], highlights: [-1.0; 3] },
+        ];
+        let pixels: Vec<[u8; 4]> = (0..16).map(|i| [i*16, 255 - i*16, i*8, i*17]).collect();
+        for adjustment in adjustments {
+            let mut raster = Raster::from_pixels(4, 4, pixels.clone());
+            adjustment.apply(&mut raster);
+            let alphas = raster.pixels().iter().map(|px| px[3]);
+            assert!(alphas.eq(pixels.iter().map(|px| px[3])), "{}", adjustment.name());
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use super::Raster;
+
+/// How a layer's colors combine with those below it, as defined by the W3C compositing spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    /// Sum of the colors, clamped
+    Add,
+}
+
+impl BlendMode {
+    /// Blend one channel of the source over the backdrop, both in 0..=1
+    fn channel(self, backdrop: f32, source: f32) -> f32 {
+        let (b, s) = (backdrop, source);
+        match self {
+            Self::Normal => s,
+            Self::Multiply => b*s,
+            Self::Screen => b + s - b*s,
+            Self::Overlay => Self::HardLight.channel(s, b),
+            Self::Darken => b.min(s),
+            Self::Lighten => b.max(s),
+            Self::ColorDodge => match (b, s) {
+                (0.0, _) => 0.0,
+                (_, 1.0) => 1.0,
+                _ => (b/(1.0 - s)).min(1.0),
+            },
+            Self::ColorBurn => match (b, s) {
+                (1.0, _) => 1.0,
+                (_, 0.0) => 0.0,
+                _ => 1.0 - ((1.0 - b)/s).min(1.0),
+            },
+            Self::HardLight => if s <= 0.5 {
+                Self::Multiply.channel(b, 2.0*s)
+            } else {
+                Self::Screen.channel(b, 2.0*s - 1.0)
+            },
+            Self::SoftLight => if s <= 0.5 {
+                b - (1.0 - 2.0*s)*b*(1.0 - b)
+            } else {
+                let d = if b <= 0.25 { ((16.0*b - 12.0)*b + 4.0)*b } else { b.sqrt() };
+                b + (2.0*s - 1.0)*(d - b)
+            },
+            Self::Difference => (b - s).abs(),
+            Self::Exclusion => b + s - 2.0*b*s,
+            Self::Add => (b + s).min(1.0),
+        }
+    }
+
+    /// Straight-alpha `src` blended over `dst`, its alpha scaled by `opacity`
+    pub fn apply(self, dst: [u8; 4], src: [u8; 4], opacity: f32) -> [u8; 4] {
+        let sa = src[3] as f32/255.0*opacity.clamp(0.0, 1.0);
+        if sa <= 0.0 {
+            return dst;
+        }
+        let ba = dst[3] as f32/255.0;
+        let a = sa + ba*(1.0 - sa);
+        let channel = |i: usize| {
+            let (b, s) = (dst[i] as f32/255.0, src[i] as f32/255.0);
+            // where there's nothing underneath, the source shows as it is
+            let mixed = (1.0 - ba)*s + ba*self.channel(b, s);
+            ((sa*mixed + ba*b*(1.0 - sa))/a*255.0).round() as u8
+        };
+        [channel(0), channel(1), channel(2), (a*255.0).round() as u8]
+    }
+}
+
+/// Blend `src` placed at (`x`, `y`) on top of `dst`, like [`Raster::draw_over`] but with any mode and opacity
+pub fn composite(dst: &mut Raster, src: &Raster, x: i32, y: i32, mode: BlendMode, opacity: f32) {
+    let Some(area) = dst.bounds().intersect(&src.bounds().translated(x, y)) else { return; };
+    for row in area.y..area.bottom() {
+        for col in area.x..area.right() {
+            let blended = mode.apply(dst.get(col as usize, row as usize), src.get((col - x) as usize, (row - y) as usize), opacity);
+            dst.set(col as usize, row as usize, blended);
+        }
+    }
+}
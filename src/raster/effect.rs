@@ -0,0 +1,216 @@
+use super::{Raster, adjust::luma, resample::{premultiply, unpremultiply}};
+
+/// Which neighbours count as touching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Connectivity {
+    /// Orthogonal only
+    Four,
+    /// Orthogonal and diagonal
+    #[default]
+    Eight,
+}
+
+impl Connectivity {
+    pub const fn offsets(self) -> &'static [(i32, i32)] {
+        const ORTHOGONAL: [(i32, i32); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+        const ALL: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+        match self {
+            Self::Four => &ORTHOGONAL,
+            Self::Eight => &ALL,
+        }
+    }
+}
+
+/// A filter that looks at each pixel's surroundings
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    BoxBlur {
+        radius: u32,
+    },
+    GaussianBlur {
+        sigma: f32,
+    },
+    /// Sharpen by adding back the difference from a blurred copy
+    UnsharpMask {
+        sigma: f32,
+        amount: f32,
+        /// Differences smaller than this are left alone
+        threshold: u8,
+    },
+    /// Sobel gradient magnitude as grayscale
+    EdgeDetect,
+    /// One pixel border around opaque areas
+    Outline {
+        color: [u8; 4],
+        /// Paint over the edge of the shape rather than around it
+        is_inner: bool,
+        connectivity: Connectivity,
+    },
+    /// Copy of the silhouette beneath the image
+    DropShadow {
+        offset: (i32, i32),
+        color: [u8; 4],
+        sigma: f32,
+    },
+    /// Replace pixels that match none of their neighbours with the most common neighbouring color
+    RemoveOrphans {
+        connectivity: Connectivity,
+    },
+}
+
+impl Effect {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::BoxBlur { .. } => "Box Blur",
+            Self::GaussianBlur { .. } => "Gaussian Blur",
+            Self::UnsharpMask { .. } => "Unsharp Mask",
+            Self::EdgeDetect => "Edge Detect",
+            Self::Outline { .. } => "Outline",
+            Self::DropShadow { .. } => "Drop Shadow",
+            Self::RemoveOrphans { .. } => "Remove Orphan Pixels",
+        }
+    }
+
+    pub fn apply(&self, raster: &mut Raster) {
+        match self {
+            Self::BoxBlur { radius } => {
+                let kernel = vec![1.0; *radius as usize*2 + 1];
+                *raster = convolve_separable(raster, &kernel);
+            }
+
+            Self::GaussianBlur { sigma } => {
+                *raster = convolve_separable(raster, &gaussian_kernel(*sigma));
+            }
+
+            Self::UnsharpMask { sigma, amount, threshold } => {
+                let blurred = convolve_separable(raster, &gaussian_kernel(*sigma));
+                for (px, blur) in raster.pixels_mut().iter_mut().zip(blurred.pixels()) {
+                    for i in 0..3 {
+                        let diff = px[i] as f32 - blur[i] as f32;
+                        if diff.abs() >= *threshold as f32 {
+                            px[i] = (px[i] as f32 + diff*amount).round().clamp(0.0, 255.0) as u8;
+                        }
+                    }
+                }
+            }
+
+            Self::EdgeDetect => {
+                let src = raster.clone();
+                let lum = |x: i32, y: i32| {
+                    let [r, g, b, _] = src.get_clamped(x, y);
+                    luma(r, g, b) as f32
+                };
+                for y in 0..src.height() as i32 {
+                    for x in 0..src.width() as i32 {
+                        let gx = lum(x + 1, y - 1) + 2.0*lum(x + 1, y) + lum(x + 1, y + 1)
+                               - lum(x - 1, y - 1) - 2.0*lum(x - 1, y) - lum(x - 1, y + 1);
+                        let gy = lum(x - 1, y + 1) + 2.0*lum(x, y + 1) + lum(x + 1, y + 1)
+                               - lum(x - 1, y - 1) - 2.0*lum(x, y - 1) - lum(x + 1, y - 1);
+                        let v = gx.hypot(gy).min(255.0) as u8;
+                        let a = src.get(x as usize, y as usize)[3];
+                        raster.set(x as usize, y as usize, [v, v, v, a]);
+                    }
+                }
+            }
+
+            Self::Outline { color, is_inner, connectivity } => {
+                let src = raster.clone();
+                let is_opaque = |x: i32, y: i32| src.get_checked(x, y).is_some_and(|px| px[3] != 0);
+                for y in 0..src.height() as i32 {
+                    for x in 0..src.width() as i32 {
+                        // inner outlines replace opaque pixels touching transparency, outer ones fill transparent pixels touching opacity
+                        if is_opaque(x, y) != *is_inner { continue; }
+                        let is_edge = connectivity.offsets().iter()
+                            .any(|&(dx, dy)| is_opaque(x + dx, y + dy) != *is_inner);
+                        if is_edge {
+                            raster.set(x as usize, y as usize, *color);
+                        }
+                    }
+                }
+            }
+
+            Self::DropShadow { offset: (dx, dy), color, sigma } => {
+                let mut shadow = Raster::new(raster.width(), raster.height());
+                for y in 0..raster.height() as i32 {
+                    for x in 0..raster.width() as i32 {
+                        if let Some(px) = raster.get_checked(x - dx, y - dy) {
+                            let a = (color[3] as u16*px[3] as u16/255) as u8;
+                            shadow.set(x as usize, y as usize, [color[0], color[1], color[2], a]);
+                        }
+                    }
+                }
+                if *sigma > 0.0 {
+                    shadow = convolve_separable(&shadow, &gaussian_kernel(*sigma));
+                }
+                shadow.draw_over(raster, 0, 0);
+                *raster = shadow;
+            }
+
+            Self::RemoveOrphans { connectivity } => {
+                let src = raster.clone();
+                for y in 0..src.height() as i32 {
+                    for x in 0..src.width() as i32 {
+                        let px = src.get(x as usize, y as usize);
+                        let neighbours: Vec<[u8; 4]> = connectivity.offsets().iter()
+                            .filter_map(|&(dx, dy)| src.get_checked(x + dx, y + dy))
+                            .collect();
+                        if neighbours.is_empty() || neighbours.contains(&px) { continue; }
+                        let most_common = neighbours.iter()
+                            .max_by_key(|&c| neighbours.iter().filter(|&n| n == c).count())
+                            .copied()
+                            .unwrap();
+                        raster.set(x as usize, y as usize, most_common);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Gaussian weights out to three standard deviations (not normalized)
+pub fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    if sigma <= 0.0 {
+        return vec![1.0];
+    }
+    let radius = (sigma*3.0).ceil() as i32;
+    let denom = 2.0*sigma*sigma;
+    (-radius..=radius)
+        .map(|i| (-(i*i) as f32/denom).exp())
+        .collect()
+}
+
+/// Apply an odd-length kernel horizontally then vertically in premultiplied alpha, repeating edge pixels.
+/// The kernel does not need to be normalized.
+pub fn convolve_separable(src: &Raster, kernel: &[f32]) -> Raster {
+    let (width, height) = (src.width(), src.height());
+    if width == 0 || height == 0 {
+        return src.clone();
+    }
+    let total: f32 = kernel.iter().sum();
+    let radius = kernel.len() as isize/2;
+    let premul: Vec<[f32; 4]> = src.pixels().iter().copied().map(premultiply).collect();
+
+    let pass = |input: &[[f32; 4]], step_x: isize, step_y: isize| -> Vec<[f32; 4]> {
+        let mut output = vec![[0.0; 4]; input.len()];
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let mut acc = [0.0; 4];
+                for (k, w) in kernel.iter().enumerate() {
+                    let offset = k as isize - radius;
+                    let sx = (x + offset*step_x).clamp(0, width  as isize - 1) as usize;
+                    let sy = (y + offset*step_y).clamp(0, height as isize - 1) as usize;
+                    for (a, c) in acc.iter_mut().zip(input[sy*width + sx]) {
+                        *a += c*w;
+                    }
+                }
+                output[y as usize*width + x as usize] = acc.map(|c| c/total);
+            }
+        }
+        output
+    };
+
+    let horizontal = pass(&premul, 1, 0);
+    let vertical = pass(&horizontal, 0, 1);
+    Raster::from_pixels(width, height, vertical.into_iter().map(unpremultiply).collect())
+}
+
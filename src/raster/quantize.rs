@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use super::Raster;
+
+/// Pixels with less alpha than this count as transparent when reducing to a palette
+pub const ALPHA_THRESHOLD: u8 = 128;
+
+/// Pick up to `max_colors` colors representing `colors` by repeatedly splitting the box
+/// with the widest channel range at its median. If there are few enough distinct colors
+/// they are returned exactly, so pixel art survives untouched.
+pub fn median_cut(colors: impl IntoIterator<Item = [u8; 3]>, max_colors: usize) -> Vec<[u8; 3]> {
+    let mut histogram = HashMap::new();
+    for color in colors {
+        *histogram.entry(color).or_insert(0u64) += 1;
+    }
+    let mut unique: Vec<([u8; 3], u64)> = histogram.into_iter().collect();
+    unique.sort_unstable();
+    if unique.len() <= max_colors {
+        return unique.into_iter().map(|(color, _)| color).collect();
+    }
+
+    let range = |bucket: &[([u8; 3], u64)]| -> (usize, u8) {
+        (0..3).map(|channel| {
+            let (min, max) = bucket.iter().fold((u8::MAX, u8::MIN), |(min, max), (c, _)| (min.min(c[channel]), max.max(c[channel])));
+            (channel, max - min)
+        }).max_by_key(|&(_, extent)| extent).unwrap()
+    };
+
+    let mut buckets = vec![unique];
+    while buckets.len() < max_colors {
+        let Some((i, channel)) = buckets.iter().enumerate()
+            .filter(|(_, bucket)| bucket.len() > 1)
+            .map(|(i, bucket)| (i, range(bucket)))
+            .max_by_key(|&(_, (_, extent))| extent)
+            .map(|(i, (channel, _))| (i, channel))
+        else { break; };
+
+        let mut bucket = buckets.swap_remove(i);
+        bucket.sort_unstable_by_key(|(c, _)| c[channel]);
+        let half = bucket.iter().map(|(_, n)| n).sum::<u64>()/2;
+        let mut total = 0;
+        let split = bucket.iter()
+            .position(|(_, n)| { total += n; total > half })
+            .unwrap()
+            .clamp(1, bucket.len() - 1);
+        let upper = bucket.split_off(split);
+        buckets.push(bucket);
+        buckets.push(upper);
+    }
+
+    buckets.iter().map(|bucket| {
+        let count: u64 = bucket.iter().map(|(_, n)| n).sum();
+        std::array::from_fn(|channel| {
+            let sum: u64 = bucket.iter().map(|(c, n)| c[channel] as u64*n).sum();
+            ((sum + count/2)/count) as u8
+        })
+    }).collect()
+}
+
+fn nearest(palette: &[[u8; 3]], color: [i32; 3]) -> usize {
+    palette.iter()
+        .map(|p| (0..3).map(|i| (p[i] as i32 - color[i]).pow(2)).sum::<i32>())
+        .enumerate()
+        .min_by_key(|&(_, distance)| distance)
+        .map_or(0, |(i, _)| i)
+}
+
+/// Index of the nearest palette entry for each pixel, top row first, optionally with
+/// Floyd-Steinberg dithering. Transparent pixels get `palette.len()`, so callers with any
+/// should reserve the entry after the palette for transparency.
+pub fn remap(raster: &Raster, palette: &[[u8; 3]], is_dithered: bool) -> Vec<u8> {
+    debug_assert!(palette.len() <= 256, "indices are bytes");
+    let (width, height) = (raster.width(), raster.height());
+    let mut indices = vec![palette.len() as u8; width*height];
+    let mut cache = HashMap::new();
+    // error carried to this row and the next
+    let mut errors = [vec![[0i32; 3]; width + 2], vec![[0i32; 3]; width + 2]];
+
+    for y in 0..height {
+        let [current, next] = &mut errors;
+        for x in 0..width {
+            let px = raster.get(x, y);
+            if px[3] < ALPHA_THRESHOLD { continue; }
+            let wanted: [i32; 3] = std::array::from_fn(|i| (px[i] as i32 + current[x + 1][i]/16).clamp(0, 255));
+            let index = *cache.entry(wanted).or_insert_with(|| nearest(palette, wanted));
+            indices[y*width + x] = index as u8;
+            if is_dithered {
+                let chosen = palette[index];
+                for i in 0..3 {
+                    let error = wanted[i] - chosen[i] as i32;
+                    current[x + 2][i] += error*7;
+                    next[x][i] += error*3;
+                    next[x + 1][i] += error*5;
+                    next[x + 2][i] += error;
+                }
+            }
+        }
+        errors.swap(0, 1);
+        errors[1].fill([0; 3]);
+    }
+    indices
+}
@@ -0,0 +1,334 @@
+use super::Raster;
+
+/// Reconstruction filter for smooth resampling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Filter {
+    #[default]
+    Nearest,
+    Bilinear,
+    Bicubic,
+    Lanczos,
+}
+
+impl Filter {
+    /// Radius of the kernel when not minifying
+    pub(super) const fn support(self) -> f32 {
+        match self {
+            Filter::Nearest  => 0.5,
+            Filter::Bilinear => 1.0,
+            Filter::Bicubic  => 2.0,
+            Filter::Lanczos  => 3.0,
+        }
+    }
+
+    pub(super) fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Filter::Nearest => if x <= 0.5 { 1.0 } else { 0.0 },
+            Filter::Bilinear => (1.0 - x).max(0.0),
+            // Catmull-Rom (a = -0.5)
+            Filter::Bicubic => match x {
+                ..1.0 => (1.5*x - 2.5)*x*x + 1.0,
+                ..2.0 => ((-0.5*x + 2.5)*x - 4.0)*x + 2.0,
+                _ => 0.0,
+            },
+            Filter::Lanczos => match x {
+                ..1e-6 => 1.0,
+                ..3.0 => {
+                    let px = std::f32::consts::PI*x;
+                    3.0*px.sin()*(px/3.0).sin()/(px*px)
+                }
+                _ => 0.0,
+            },
+        }
+    }
+}
+
+/// Any way of changing the size of an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scaler {
+    Filter(Filter),
+    /// Repeated [`scale2x`], finished with nearest-neighbour
+    Scale2x,
+    /// Repeated [`hq2x`], finished with nearest-neighbour
+    Hq2x,
+}
+
+impl Default for Scaler {
+    fn default() -> Self {
+        Self::Filter(Filter::Nearest)
+    }
+}
+
+/// Resize to exactly `width` by `height` using `scaler`
+pub fn scale(src: &Raster, width: usize, height: usize, scaler: Scaler) -> Raster {
+    let upscaler = match scaler {
+        Scaler::Filter(filter) => return resize(src, width, height, filter),
+        Scaler::Scale2x => scale2x,
+        Scaler::Hq2x => hq2x,
+    };
+    let mut img = src.clone();
+    while img.width()*2 <= width && img.height()*2 <= height {
+        img = upscaler(&img);
+    }
+    resize(&img, width, height, Filter::Nearest)
+}
+
+/// Resize to exactly `width` by `height`, widening the kernel when shrinking so detail is averaged rather than skipped
+pub fn resize(src: &Raster, width: usize, height: usize, filter: Filter) -> Raster {
+    if src.width() == width && src.height() == height {
+        return src.clone();
+    }
+    if width == 0 || height == 0 || src.width() == 0 || src.height() == 0 {
+        return Raster::new(width, height);
+    }
+    if filter == Filter::Nearest {
+        return resize_nearest(src, width, height);
+    }
+
+    let premul: Vec<[f32; 4]> = src.pixels().iter().copied().map(premultiply).collect();
+
+    // horizontal pass: src.height rows of `width`
+    let weights = axis_weights(src.width(), width, filter);
+    let mut horiz = vec![[0.0; 4]; width*src.height()];
+    for y in 0..src.height() {
+        let row = &premul[y*src.width()..(y + 1)*src.width()];
+        for (x, taps) in weights.iter().enumerate() {
+            horiz[y*width + x] = convolve(taps.iter().map(|&(i, w)| (row[i], w)));
+        }
+    }
+
+    // vertical pass
+    let weights = axis_weights(src.height(), height, filter);
+    let mut pixels = Vec::with_capacity(width*height);
+    for taps in &weights {
+        for x in 0..width {
+            pixels.push(unpremultiply(convolve(taps.iter().map(|&(i, w)| (horiz[i*width + x], w)))));
+        }
+    }
+    Raster::from_pixels(width, height, pixels)
+}
+
+fn resize_nearest(src: &Raster, width: usize, height: usize) -> Raster {
+    let map = |i: usize, src_len: usize, dst_len: usize| ((2*i + 1)*src_len/(2*dst_len)).min(src_len - 1);
+    let mut pixels = Vec::with_capacity(width*height);
+    for y in 0..height {
+        let sy = map(y, src.height(), height);
+        pixels.extend((0..width).map(|x| src.get(map(x, src.width(), width), sy)));
+    }
+    Raster::from_pixels(width, height, pixels)
+}
+
+/// For each destination index along one axis, the (clamped) source indices it samples and their normalized weights
+fn axis_weights(src_len: usize, dst_len: usize, filter: Filter) -> Vec<Vec<(usize, f32)>> {
+    let ratio = src_len as f32/dst_len as f32;
+    let stretch = ratio.max(1.0);
+    let support = filter.support()*stretch;
+    (0..dst_len).map(|i| {
+        let center = (i as f32 + 0.5)*ratio;
+        let first = (center - support).floor() as isize;
+        let last  = (center + support).ceil()  as isize;
+        let mut taps: Vec<(usize, f32)> = (first..=last)
+            .map(|j| {
+                let w = filter.weight((j as f32 + 0.5 - center)/stretch);
+                (j.clamp(0, src_len as isize - 1) as usize, w)
+            })
+            .filter(|&(_, w)| w != 0.0)
+            .collect();
+        let total: f32 = taps.iter().map(|&(_, w)| w).sum();
+        if total != 0.0 {
+            for (_, w) in &mut taps {
+                *w /= total;
+            }
+        }
+        taps
+    }).collect()
+}
+
+fn convolve(taps: impl IntoIterator<Item = ([f32; 4], f32)>) -> [f32; 4] {
+    taps.into_iter().fold([0.0; 4], |mut acc, (px, w)| {
+        for (a, c) in acc.iter_mut().zip(px) {
+            *a += c*w;
+        }
+        acc
+    })
+}
+
+pub(super) fn premultiply([r, g, b, a]: [u8; 4]) -> [f32; 4] {
+    let alpha = a as f32/255.0;
+    [r as f32*alpha, g as f32*alpha, b as f32*alpha, a as f32]
+}
+
+pub(super) fn unpremultiply([r, g, b, a]: [f32; 4]) -> [u8; 4] {
+    let a = a.clamp(0.0, 255.0);
+    if a < 0.5 {
+        return [0; 4];
+    }
+    let alpha_inv = 255.0/a;
+    let channel = |c: f32| (c*alpha_inv).round().clamp(0.0, 255.0) as u8;
+    [channel(r), channel(g), channel(b), a.round() as u8]
+}
+
+/// Neighbours of (`x`, `y`) as `[up, left, right, down]`, repeating edge pixels
+fn cross(src: &Raster, x: usize, y: usize) -> [[u8; 4]; 4] {
+    let (x, y) = (x as i32, y as i32);
+    [
+        src.get_clamped(x, y - 1),
+        src.get_clamped(x - 1, y),
+        src.get_clamped(x + 1, y),
+        src.get_clamped(x, y + 1),
+    ]
+}
+
+/// Double the size with the EPX rules, given a pixel comparison and a way of producing each rounded corner
+fn expand2x(src: &Raster, similar: impl Fn([u8; 4], [u8; 4]) -> bool, corner: impl Fn([u8; 4], [u8; 4], [u8; 4]) -> [u8; 4]) -> Raster {
+    let (width, height) = (src.width()*2, src.height()*2);
+    let mut out = Raster::new(width, height);
+    for y in 0..src.height() {
+        for x in 0..src.width() {
+            let p = src.get(x, y);
+            let [a, c, b, d] = cross(src, x, y);
+            let rule = |e1: [u8; 4], e2: [u8; 4], o1: [u8; 4], o2: [u8; 4]| {
+                if similar(e1, e2) && !similar(e1, o1) && !similar(e2, o2) {
+                    corner(p, e1, e2)
+                } else {
+                    p
+                }
+            };
+            out.set(2*x,     2*y,     rule(c, a, d, b));
+            out.set(2*x + 1, 2*y,     rule(a, b, c, d));
+            out.set(2*x,     2*y + 1, rule(d, c, b, a));
+            out.set(2*x + 1, 2*y + 1, rule(b, d, a, c));
+        }
+    }
+    out
+}
+
+/// Scale2x (also known as EPX/AdvMAME2x): doubles the size, rounding diagonal edges without adding colors
+pub fn scale2x(src: &Raster) -> Raster {
+    expand2x(src, |a, b| a == b, |_, e, _| e)
+}
+
+/// Doubles the size like [`scale2x`], but treats perceptually similar colors as equal
+/// and blends the corners it rounds instead of copying a neighbour
+pub fn hq2x(src: &Raster) -> Raster {
+    fn yuva([r, g, b, a]: [u8; 4]) -> [f32; 4] {
+        let (r, g, b) = (r as f32, g as f32, b as f32);
+        [
+            0.299*r + 0.587*g + 0.114*b,
+            -0.169*r - 0.331*g + 0.5*b,
+            0.5*r - 0.419*g - 0.081*b,
+            a as f32,
+        ]
+    }
+    // thresholds from hqx
+    const THRESHOLD: [f32; 4] = [48.0, 7.0, 6.0, 32.0];
+
+    let similar = |a: [u8; 4], b: [u8; 4]| {
+        let (a, b) = (yuva(a), yuva(b));
+        (0..4).all(|i| (a[i] - b[i]).abs() <= THRESHOLD[i])
+    };
+    let corner = |p: [u8; 4], e1: [u8; 4], e2: [u8; 4]| {
+        std::array::from_fn(|i| ((2*p[i] as u16 + e1[i] as u16 + e2[i] as u16 + 2)/4) as u8)
+    };
+    expand2x(src, similar, corner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const GRAY: [u8; 4] = [128, 128, 128, 255];
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    /// Black and white checkerboard, 2 by 2
+    fn checker() -> Raster {
+        Raster::from_pixels(2, 2, vec![BLACK, WHITE, WHITE, BLACK])
+    }
+
+    #[test]
+    fn filter_weights() {
+        assert_eq!(Filter::Nearest.weight(0.0), 1.0);
+        assert_eq!(Filter::Nearest.weight(-0.5), 1.0);
+        assert_eq!(Filter::Nearest.weight(0.6), 0.0);
+
+        assert!(close(Filter::Bilinear.weight(0.0), 1.0));
+        assert!(close(Filter::Bilinear.weight(-0.25), 0.75));
+        assert!(close(Filter::Bilinear.weight(1.5), 0.0));
+
+        assert!(close(Filter::Bicubic.weight(0.0), 1.0));
+        assert!(close(Filter::Bicubic.weight(0.5), 0.5625));
+        assert!(close(Filter::Bicubic.weight(1.0), 0.0));
+        assert!(close(Filter::Bicubic.weight(-1.5), -0.0625));
+        assert!(close(Filter::Bicubic.weight(2.0), 0.0));
+
+        assert!(close(Filter::Lanczos.weight(0.0), 1.0));
+        assert!(close(Filter::Lanczos.weight(1.0), 0.0));
+        assert!(close(Filter::Lanczos.weight(-2.0), 0.0));
+        assert!(close(Filter::Lanczos.weight(3.5), 0.0));
+    }
+
+    #[test]
+    fn nearest_samples_pixel_centers() {
+        let row = |pixels: &[u8]| Raster::from_pixels(pixels.len(), 1, pixels.iter().map(|&v| [v, v, v, 255]).collect());
+        let src = row(&[0, 1, 2, 3]);
+        assert_eq!(resize(&src, 2, 1, Filter::Nearest), row(&[1, 3]));
+        assert_eq!(resize(&row(&[0, 1, 2]), 2, 1, Filter::Nearest), row(&[0, 2]));
+        assert_eq!(resize(&row(&[0, 1]), 4, 1, Filter::Nearest), row(&[0, 0, 1, 1]));
+    }
+
+    #[test]
+    fn smooth_filters_premultiply_alpha() {
+        // the transparent pixel's blue mustn't bleed into the red
+        let src = Raster::from_pixels(2, 1, vec![[255, 0, 0, 255], [0, 0, 255, 0]]);
+        for filter in [Filter::Bilinear, Filter::Bicubic, Filter::Lanczos] {
+            assert_eq!(resize(&src, 1, 1, filter).get(0, 0), [255, 0, 0, 128], "{filter:?}");
+        }
+        let clear = Raster::from_pixels(2, 1, vec![[0, 0, 255, 0], [255, 0, 0, 0]]);
+        assert_eq!(resize(&clear, 1, 1, Filter::Bilinear).get(0, 0), [0; 4]);
+    }
+
+    #[test]
+    fn bilinear_upscale() {
+        let src = Raster::from_pixels(2, 1, vec![BLACK, WHITE]);
+        let expected = [BLACK, [64, 64, 64, 255], [191, 191, 191, 255], WHITE];
+        assert_eq!(resize(&src, 4, 1, Filter::Bilinear).pixels(), expected);
+    }
+
+    #[test]
+    fn scale2x_rounds_diagonals() {
+        let (b, w) = (BLACK, WHITE);
+        let expected = [
+            b, b, w, w,
+            b, w, b, w,
+            w, b, w, b,
+            w, w, b, b,
+        ];
+        assert_eq!(scale2x(&checker()).pixels(), expected);
+        assert_eq!(scale2x(&Raster::filled(1, 1, w)), Raster::filled(2, 2, w));
+    }
+
+    #[test]
+    fn hq2x_blends_rounded_corners() {
+        let (b, w, g) = (BLACK, WHITE, GRAY);
+        let expected = [
+            b, b, w, w,
+            b, g, g, w,
+            w, g, g, b,
+            w, w, b, b,
+        ];
+        assert_eq!(hq2x(&checker()).pixels(), expected);
+    }
+
+    #[test]
+    fn scalers_finish_with_nearest() {
+        let scaled = scale(&checker(), 6, 6, Scaler::Scale2x);
+        assert_eq!((scaled.width(), scaled.height()), (6, 6));
+        assert_eq!(scaled, resize(&scale2x(&checker()), 6, 6, Filter::Nearest));
+    }
+}
@@ -0,0 +1,241 @@
+use super::{Raster, Rect, resample::{Filter, premultiply, scale2x, unpremultiply}};
+
+/// 2D affine map taking (x, y) to (a*x + c*y + tx, b*x + d*y + ty), in y-down coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub tx: f32,
+    pub ty: f32,
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Affine {
+    pub const IDENTITY: Affine = Affine::scale(1.0, 1.0);
+
+    pub const fn translate(x: f32, y: f32) -> Self {
+        Self { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: x, ty: y }
+    }
+
+    pub const fn scale(x: f32, y: f32) -> Self {
+        Self { a: x, b: 0.0, c: 0.0, d: y, tx: 0.0, ty: 0.0 }
+    }
+
+    /// Clockwise on screen, in radians
+    pub fn rotate(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self { a: cos, b: sin, c: -sin, d: cos, tx: 0.0, ty: 0.0 }
+    }
+
+    /// Shift x by `x` times y, and y by `y` times x
+    pub const fn skew(x: f32, y: f32) -> Self {
+        Self { a: 1.0, b: y, c: x, d: 1.0, tx: 0.0, ty: 0.0 }
+    }
+
+    /// Apply `self`, then `next`
+    pub fn then(self, next: Affine) -> Affine {
+        Affine {
+            a:  next.a*self.a  + next.c*self.b,
+            b:  next.b*self.a  + next.d*self.b,
+            c:  next.a*self.c  + next.c*self.d,
+            d:  next.b*self.c  + next.d*self.d,
+            tx: next.a*self.tx + next.c*self.ty + next.tx,
+            ty: next.b*self.tx + next.d*self.ty + next.ty,
+        }
+    }
+
+    #[inline]
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.a*x + self.c*y + self.tx, self.b*x + self.d*y + self.ty)
+    }
+
+    /// `None` if the map collapses the plane to a line or point
+    pub fn inverse(&self) -> Option<Affine> {
+        let det = self.a*self.d - self.b*self.c;
+        if det.abs() < 1e-9 {
+            return None;
+        }
+        let (a, b, c, d) = (self.d/det, -self.b/det, -self.c/det, self.a/det);
+        Some(Affine {
+            a, b, c, d,
+            tx: -(a*self.tx + c*self.ty),
+            ty: -(b*self.tx + d*self.ty),
+        })
+    }
+
+    /// Where the corners of a `width` by `height` rectangle at the origin end up,
+    /// clockwise from the top-left
+    pub fn corners(&self, width: f32, height: f32) -> [(f32, f32); 4] {
+        [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)]
+            .map(|(x, y)| self.apply(x, y))
+    }
+
+    /// Pixels touched by a `width` by `height` image after mapping
+    pub fn bounds(&self, width: f32, height: f32) -> Rect {
+        let corners = self.corners(width, height);
+        let (mut x0, mut y0, mut x1, mut y1) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for (x, y) in corners {
+            (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+        }
+        let (x0, y0) = (x0.floor() as i32, y0.floor() as i32);
+        Rect::new(x0, y0, x1.ceil() as i32 - x0, y1.ceil() as i32 - y0)
+    }
+}
+
+/// How pixels are reconstructed when transforming
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sampler {
+    Filter(Filter),
+    /// Sample an 8x [`scale2x`] enlargement with nearest-neighbour, keeping edges crisp
+    RotSprite,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self::Filter(Filter::Nearest)
+    }
+}
+
+impl Sampler {
+    pub const ALL: [Sampler; 5] = [
+        Sampler::Filter(Filter::Nearest),
+        Sampler::Filter(Filter::Bilinear),
+        Sampler::Filter(Filter::Bicubic),
+        Sampler::Filter(Filter::Lanczos),
+        Sampler::RotSprite,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Sampler::Filter(Filter::Nearest)  => "Nearest",
+            Sampler::Filter(Filter::Bilinear) => "Bilinear",
+            Sampler::Filter(Filter::Bicubic)  => "Bicubic",
+            Sampler::Filter(Filter::Lanczos)  => "Lanczos",
+            Sampler::RotSprite => "RotSprite",
+        }
+    }
+}
+
+/// Nearest-neighbour sample an 8x [`scale2x`] enlargement of `src` into `out`. The enlargement is
+/// made a tile at a time, since all of it at once takes 64 times the memory of `src`.
+fn rotsprite(src: &Raster, map: Affine, inverse: Affine, out: &mut Raster) {
+    const FACTOR: i32 = 8;
+    const TILE: i32 = 64;
+    // each doubling looks one pixel around, so tiles enlarged with this much around them join without seams
+    const MARGIN: i32 = 3;
+
+    for tile_y in (0..src.height() as i32).step_by(TILE as usize) {
+        for tile_x in (0..src.width() as i32).step_by(TILE as usize) {
+            let tile = Rect::new(tile_x, tile_y, TILE, TILE);
+            let Some(area) = src.bounds().intersect(&Rect::new(tile_x - MARGIN, tile_y - MARGIN, TILE + 2*MARGIN, TILE + 2*MARGIN)) else { continue; };
+            let Some(reach) = out.bounds().intersect(&Affine::translate(tile_x as f32, tile_y as f32).then(map).bounds(TILE as f32, TILE as f32)) else { continue; };
+            let big = scale2x(&scale2x(&scale2x(&src.crop(area))));
+            for y in reach.y..reach.bottom() {
+                for x in reach.x..reach.right() {
+                    let (u, v) = inverse.apply(x as f32 + 0.5, y as f32 + 0.5);
+                    let (big_x, big_y) = ((u*FACTOR as f32).floor() as i32, (v*FACTOR as f32).floor() as i32);
+                    // every pixel is drawn by the one tile it lands in
+                    if !tile.contains(big_x.div_euclid(FACTOR), big_y.div_euclid(FACTOR)) { continue; }
+                    if let Some(px) = big.get_checked(big_x - area.x*FACTOR, big_y - area.y*FACTOR) {
+                        out.set(x as usize, y as usize, px);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Render `src` mapped through `map` into a new `width` by `height` image; uncovered pixels are transparent
+pub fn transform(src: &Raster, map: Affine, width: usize, height: usize, sampler: Sampler) -> Raster {
+    let mut out = Raster::new(width, height);
+    let Some(inverse) = map.inverse() else { return out; };
+
+    let filter = match sampler {
+        Sampler::Filter(filter) => filter,
+        Sampler::RotSprite => {
+            rotsprite(src, map, inverse, &mut out);
+            return out;
+        }
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            let (u, v) = inverse.apply(x as f32 + 0.5, y as f32 + 0.5);
+            let px = match filter {
+                Filter::Nearest => src.get_checked(u.floor() as i32, v.floor() as i32),
+                filter => Some(sample(src, u, v, filter)),
+            };
+            if let Some(px) = px {
+                out.set(x, y, px);
+            }
+        }
+    }
+    out
+}
+
+/// Filtered sample at continuous coordinates, treating everything outside `src` as transparent
+fn sample(src: &Raster, u: f32, v: f32, filter: Filter) -> [u8; 4] {
+    let support = filter.support();
+    let (cu, cv) = (u - 0.5, v - 0.5);
+    let xs = (cu - support).ceil() as i32..=(cu + support).floor() as i32;
+    let ys = (cv - support).ceil() as i32..=(cv + support).floor() as i32;
+
+    let mut acc = [0.0; 4];
+    let mut total = 0.0;
+    for j in ys {
+        let wy = filter.weight(j as f32 - cv);
+        if wy == 0.0 { continue; }
+        for i in xs.clone() {
+            let w = wy*filter.weight(i as f32 - cu);
+            if w == 0.0 { continue; }
+            total += w;
+            if let Some(px) = src.get_checked(i, j) {
+                for (a, c) in acc.iter_mut().zip(premultiply(px)) {
+                    *a += c*w;
+                }
+            }
+        }
+    }
+    if total == 0.0 {
+        return [0; 4];
+    }
+    unpremultiply(acc.map(|c| c/total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotsprite_tiles_match_one_enlargement() {
+        // diagonal stripes and a circle, spanning several tiles
+        let src = Raster::from_pixels(150, 100, (0..150*100).map(|i| {
+            let (x, y) = (i % 150, i / 150);
+            let is_stripe = (x + 2*y) % 23 < 7;
+            let is_circle = (x - 70)*(x - 70) + (y - 50)*(y - 50) < 35*35;
+            [255*is_stripe as u8, 255*is_circle as u8, 90, if is_stripe || is_circle { 255 } else { 0 }]
+        }).collect());
+        let map = Affine::translate(-75.0, -50.0).then(Affine::rotate(0.5)).then(Affine::scale(1.3, 1.3)).then(Affine::translate(110.0, 100.0));
+        let out = transform(&src, map, 220, 200, Sampler::RotSprite);
+
+        let big = scale2x(&scale2x(&scale2x(&src)));
+        let inverse = map.inverse().unwrap();
+        let mut expected = Raster::new(220, 200);
+        for y in 0..200 {
+            for x in 0..220 {
+                let (u, v) = inverse.apply(x as f32 + 0.5, y as f32 + 0.5);
+                if let Some(px) = big.get_checked((u*8.0).floor() as i32, (v*8.0).floor() as i32) {
+                    expected.set(x, y, px);
+                }
+            }
+        }
+        assert_eq!(out, expected);
+    }
+}
@@ -0,0 +1,180 @@
+//! Copies of the open documents written in the background, so a crash doesn't lose everything since
+//! the last save
+//!
+//! Each document's copy is an animated PNG in the recovery directory, with a TOML file beside it
+//! saying which file it was a copy of. Both are removed when the document is closed normally, so
+//! anything left there at launch was left by a crash.
+
+use std::{fs::{self, File}, io::{self, BufReader, BufWriter}, path::{Path, PathBuf}, sync::atomic::{AtomicU32, Ordering}, thread::JoinHandle, time::{SystemTime, UNIX_EPOCH}};
+use serde::{Deserialize, Serialize};
+use crate::codec::{Animation, apng};
+
+/// Where copies are kept; separate from the config directory since they're data, not preferences
+fn recovery_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")).join("recovery"))
+}
+
+/// What the copy is a copy of
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RecoveryMeta {
+    path: Option<PathBuf>,
+}
+
+/// Write `animation` and where it came from next to `stem`
+fn write(stem: &Path, animation: &Animation, path: Option<PathBuf>) -> io::Result<()> {
+    if let Some(dir) = stem.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // written to the side first, so a crash partway through doesn't replace a good copy with half of one
+    let temp = stem.with_extension("png.tmp");
+    apng::encode(BufWriter::new(File::create(&temp)?), animation)?;
+    fs::rename(&temp, stem.with_extension("png"))?;
+    let meta = toml::to_string(&RecoveryMeta { path }).map_err(io::Error::other)?;
+    fs::write(stem.with_extension("toml"), meta)
+}
+
+/// Delete the copy at `stem` and anything written alongside it
+fn remove(stem: &Path) {
+    for ext in ["png", "png.tmp", "toml"] {
+        // most of them won't be there
+        let _ = fs::remove_file(stem.with_extension(ext));
+    }
+}
+
+/// Keeps a copy of one open document up to date
+pub struct Autosave {
+    /// Where the copy goes, without an extension
+    stem: Option<PathBuf>,
+    is_changed: bool,
+    /// When the last copy was started, in seconds since the window opened
+    last_save: f64,
+    /// The copy being written, which has to finish before another starts
+    writer: Option<JoinHandle<io::Result<()>>>,
+    /// Whether the last copy failed, so the same failure isn't reported every interval
+    is_failing: bool,
+}
+
+impl Autosave {
+    /// Seconds between copies while there are changes
+    const INTERVAL: f64 = 30.0;
+
+    pub fn new() -> Self {
+        /// Tells apart documents opened in the same second
+        static COUNT: AtomicU32 = AtomicU32::new(0);
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        Self {
+            stem: recovery_dir().map(|dir| dir.join(format!("{started}-{}-{count}", std::process::id()))),
+            is_changed: false,
+            last_save: 0.0,
+            writer: None,
+            is_failing: false,
+        }
+    }
+
+    /// Note that the document is different from the last copy
+    #[inline]
+    pub fn mark_changed(&mut self) {
+        self.is_changed = true;
+    }
+
+    /// Whether a copy is due, having checked on the one being written.
+    /// Fails if that copy couldn't be written, unless the one before it failed too.
+    pub fn is_due(&mut self, time: f64) -> io::Result<bool> {
+        if self.writer.as_ref().is_some_and(JoinHandle::is_finished) && let Some(writer) = self.writer.take() {
+            let result = writer.join().unwrap_or_else(|_| Err(io::Error::other("the copy panicked while being written")));
+            let was_failing = std::mem::replace(&mut self.is_failing, result.is_err());
+            if let Err(err) = result {
+                // tried again next time
+                self.is_changed = true;
+                if !was_failing {
+                    return Err(err);
+                }
+            }
+        }
+        Ok(self.stem.is_some() && self.is_changed && self.writer.is_none() && time - self.last_save >= Self::INTERVAL)
+    }
+
+    /// Start writing a copy on another thread, so painting carries on while it's encoded
+    pub fn save(&mut self, time: f64, animation: Animation, path: Option<PathBuf>) {
+        let Some(stem) = self.stem.clone() else { return; };
+        self.is_changed = false;
+        self.last_save = time;
+        self.writer = Some(std::thread::spawn(move || write(&stem, &animation, path)));
+    }
+
+    /// Wait another interval before trying again, for when there was nothing to copy
+    pub fn postpone(&mut self, time: f64) {
+        self.last_save = time;
+    }
+
+    /// Write a copy right away, for when the program is about to go down
+    pub fn save_now(&mut self, animation: &Animation, path: Option<PathBuf>) -> io::Result<()> {
+        let Some(stem) = &self.stem else { return Ok(()); };
+        // the copy in progress is older, and mustn't finish after this one
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        write(stem, animation, path)
+    }
+
+    /// Remove the copy, once there's nothing left to recover
+    pub fn clear(mut self) {
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        if let Some(stem) = &self.stem {
+            remove(stem);
+        }
+    }
+}
+
+/// A copy left behind by a run that didn't close normally
+#[derive(Debug, Clone)]
+pub struct Recoverable {
+    stem: PathBuf,
+    /// The file it was a copy of, if it had been saved
+    pub path: Option<PathBuf>,
+    pub modified: SystemTime,
+}
+
+impl Recoverable {
+    /// Every copy in the recovery directory, newest first
+    ///
+    /// Only meant to be called at launch, before this run has written a copy of its own.
+    pub fn list() -> Vec<Self> {
+        let Some(entries) = recovery_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+            return Vec::new();
+        };
+        let mut list: Vec<Self> = entries
+            .filter_map(|entry| {
+                let file = entry.ok()?.path();
+                if file.extension()? != "png" { return None; }
+                let modified = fs::metadata(&file).and_then(|meta| meta.modified()).ok()?;
+                let stem = file.with_extension("");
+                let meta: RecoveryMeta = fs::read_to_string(stem.with_extension("toml")).ok()
+                    .and_then(|text| toml::from_str(&text).ok())
+                    .unwrap_or_default();
+                Some(Self { stem, path: meta.path, modified })
+            })
+            .collect();
+        list.sort_by(|a, b| b.modified.cmp(&a.modified));
+        list
+    }
+
+    /// What to call it in a list
+    pub fn name(&self) -> String {
+        self.path.as_ref()
+            .and_then(|path| path.file_name())
+            .map_or_else(|| "untitled".to_string(), |name| name.to_string_lossy().into_owned())
+    }
+
+    pub fn load(&self) -> io::Result<Animation> {
+        apng::decode(BufReader::new(File::open(self.stem.with_extension("png"))?))
+    }
+
+    /// Delete the copy for good
+    pub fn discard(&self) {
+        remove(&self.stem);
+    }
+}
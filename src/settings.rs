@@ -0,0 +1,225 @@
+//! Preferences and the last session, kept in the config directory between runs
+//!
+//! The file is versioned: a key that's missing or can't be read takes its default, and when a key changes meaning
+//! or name, a migration upgrades older files before they're read.
+
+use std::{fs, io, path::PathBuf};
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::{brush::{Brush, InterpStyle}, editor::art::View};
+
+/// Version written into new files
+const VERSION: u32 = 1;
+
+/// `MIGRATIONS[i]` upgrades a version `i + 1` file to version `i + 2`
+const MIGRATIONS: &[fn(&mut toml::Table)] = &[];
+const _: () = assert!(MIGRATIONS.len() as u32 + 1 == VERSION, "every version needs a migration from the one before");
+
+/// Where settings, the keymap and anything else that outlives a session are kept
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    /// Size when not maximized
+    pub width: i32,
+    pub height: i32,
+    pub is_maximized: bool,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            is_maximized: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrushSettings {
+    pub radius: f32,
+    /// RGBA
+    pub color: [u8; 4],
+    pub interp: InterpStyle,
+}
+
+impl Default for BrushSettings {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            color: [255, 255, 255, 255],
+            interp: InterpStyle::Curve,
+        }
+    }
+}
+
+impl From<&Brush> for BrushSettings {
+    fn from(brush: &Brush) -> Self {
+        let Color { r, g, b, a } = brush.color;
+        Self { radius: brush.radius, color: [r, g, b, a], interp: brush.interp }
+    }
+}
+
+impl From<&BrushSettings> for Brush {
+    fn from(settings: &BrushSettings) -> Self {
+        let [r, g, b, a] = settings.color;
+        Self { radius: settings.radius.max(0.5), color: Color::new(r, g, b, a), interp: settings.interp }
+    }
+}
+
+/// Where the canvas was in the active view
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ViewSettings {
+    pub pan: [f32; 2],
+    pub zoom: f32,
+    pub rotation: f32,
+}
+
+impl From<View> for ViewSettings {
+    fn from(view: View) -> Self {
+        Self { pan: [view.pan.x, view.pan.y], zoom: view.zoom, rotation: view.rotation }
+    }
+}
+
+impl From<ViewSettings> for View {
+    fn from(settings: ViewSettings) -> Self {
+        let [x, y] = settings.pan;
+        // the file may have been edited by hand
+        View { pan: Vector2::new(x, y), zoom: settings.zoom, rotation: settings.rotation }.clamped()
+    }
+}
+
+/// What was open when the program last closed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// The file last saved or opened, reopened on launch
+    pub path: Option<PathBuf>,
+    pub canvas_width: i32,
+    pub canvas_height: i32,
+    pub view: Option<ViewSettings>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            path: None,
+            canvas_width: 720,
+            canvas_height: 480,
+            view: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    /// Saved colors, oldest first, as hue, saturation and value
+    pub palette: Vec<[f32; 3]>,
+    /// Sizes offered for new documents, as width and height
+    pub canvas_presets: Vec<[u32; 2]>,
+    pub window: WindowSettings,
+    pub brush: BrushSettings,
+    pub session: Session,
+    /// Keys in the file that couldn't be read, which kept their defaults
+    #[serde(skip)]
+    problems: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            palette: Vec::new(),
+            canvas_presets: vec![[16, 16], [32, 32], [64, 64], [128, 128], [720, 480], [1280, 720], [1920, 1080]],
+            window: WindowSettings::default(),
+            brush: BrushSettings::default(),
+            session: Session::default(),
+            problems: Vec::new(),
+        }
+    }
+}
+
+/// Copy each of `file`'s keys into the table at `path` in `settings`, leaving out any that would
+/// stop `settings` from being read, and going into tables to leave out only the keys inside that don't fit
+fn merge(settings: &mut toml::Table, path: &[&str], file: toml::Table, problems: &mut Vec<String>) {
+    fn table_at<'a>(settings: &'a mut toml::Table, path: &[&str]) -> &'a mut toml::Table {
+        path.iter().fold(settings, |table, key| table.get_mut(*key).and_then(toml::Value::as_table_mut).expect("only tables are gone into"))
+    }
+
+    for (key, value) in file {
+        let previous = table_at(settings, path).insert(key.clone(), value.clone());
+        let Err(e) = toml::Value::Table(settings.clone()).try_into::<Settings>() else { continue };
+        let table = table_at(settings, path);
+        match previous.clone() {
+            Some(previous) => { table.insert(key.clone(), previous); }
+            None => { table.remove(&key); }
+        }
+        let inner_path = [path, &[key.as_str()]].concat();
+        match (value, previous) {
+            (toml::Value::Table(inner), Some(toml::Value::Table(_))) => merge(settings, &inner_path, inner, problems),
+            _ => problems.push(format!("{}: {}: {}", Settings::FILE_NAME, inner_path.join("."), e.message())),
+        }
+    }
+}
+
+impl Settings {
+    const FILE_NAME: &str = "settings.toml";
+
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(Self::FILE_NAME))
+    }
+
+    /// Read the settings file, upgrading it if it's from an older version; anything missing or
+    /// unreadable takes its default, and anything unreadable is listed in [`Self::problems`]
+    pub fn load() -> Self {
+        let mut problems = Vec::new();
+        let mut table = match Self::path().map(fs::read_to_string) {
+            Some(Ok(text)) => match text.parse::<toml::Table>() {
+                Ok(table) => table,
+                Err(e) => {
+                    problems.push(format!("{}: {e}", Self::FILE_NAME));
+                    return Self { problems, ..Self::default() };
+                }
+            },
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => {
+                problems.push(format!("{}: {e}", Self::FILE_NAME));
+                return Self { problems, ..Self::default() };
+            }
+            _ => return Self::default(),
+        };
+
+        // a file without a version counts as the first one
+        let version = table.get("version").and_then(toml::Value::as_integer).unwrap_or(1);
+        let version = usize::try_from(version).unwrap_or(1).max(1);
+        for migrate in MIGRATIONS.iter().skip(version - 1) {
+            migrate(&mut table);
+        }
+        table.insert("version".to_string(), toml::Value::Integer(VERSION.into()));
+
+        // one bad key only costs that key, not everything else in the file
+        let mut settings = toml::Table::try_from(Self::default()).expect("the defaults should serialize");
+        merge(&mut settings, &[], table, &mut problems);
+        Self { problems, ..toml::Value::Table(settings).try_into().expect("only keys that fit were merged") }
+    }
+
+    /// Keys from the file that couldn't be read
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        let text = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)
+    }
+}
@@ -0,0 +1,103 @@
+use raylib::prelude::*;
+use crate::frame::Frame;
+
+pub enum TabCommand {
+    Select(usize),
+    Close(usize),
+    New,
+}
+
+/// Strip of open documents above the editors, one tab each
+pub struct TabBar {
+    is_dirty: bool,
+    /// Labels and the active tab as last drawn, to notice when they change
+    cached_titles: Vec<String>,
+    cached_active: usize,
+}
+
+impl TabBar {
+    pub const HEIGHT: i32 = 24;
+    const MAX_TAB_WIDTH: f32 = 160.0;
+    const CLOSE_SIZE: f32 = 16.0;
+    const FONT_SIZE: i32 = 10;
+
+    pub fn new() -> Self {
+        Self {
+            is_dirty: true,
+            cached_titles: Vec::new(),
+            cached_active: 0,
+        }
+    }
+
+    #[inline]
+    pub fn mark_dirty(&mut self) {
+        self.is_dirty = true;
+    }
+
+    /// Where each tab goes, with the new tab button after them
+    fn layout(viewport: Rectangle, count: usize) -> (Vec<Rectangle>, Rectangle) {
+        let height = Self::HEIGHT as f32;
+        let width = ((viewport.width - height)/count.max(1) as f32).min(Self::MAX_TAB_WIDTH).floor();
+        let tabs = (0..count)
+            .map(|i| Rectangle::new(viewport.x + i as f32*width, viewport.y, width, height))
+            .collect();
+        let new_button = Rectangle::new(viewport.x + count as f32*width, viewport.y, height, height);
+        (tabs, new_button)
+    }
+
+    /// The close button in the corner of a tab
+    fn close_button(tab: Rectangle) -> Rectangle {
+        let inset = (tab.height - Self::CLOSE_SIZE)*0.5;
+        Rectangle::new(tab.x + tab.width - Self::CLOSE_SIZE - inset, tab.y + inset, Self::CLOSE_SIZE, Self::CLOSE_SIZE)
+    }
+
+    /// Draw the tabs if they've changed, and return what was clicked
+    pub fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, viewport: Rectangle, frame: &mut Frame, titles: Vec<String>, active: usize, is_awake: bool) -> Option<TabCommand> {
+        let (tabs, new_button) = Self::layout(viewport, titles.len());
+
+        let mut command = None;
+        let mouse_pos = rl.get_mouse_position();
+        if is_awake && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            if new_button.check_collision_point_rec(mouse_pos) {
+                command = Some(TabCommand::New);
+            } else if let Some(i) = tabs.iter().position(|tab| tab.check_collision_point_rec(mouse_pos)) {
+                command = Some(if Self::close_button(tabs[i]).check_collision_point_rec(mouse_pos) { TabCommand::Close(i) } else { TabCommand::Select(i) });
+            }
+        } else if is_awake && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_MIDDLE) {
+            command = tabs.iter().position(|tab| tab.check_collision_point_rec(mouse_pos)).map(TabCommand::Close);
+        }
+
+        if titles != self.cached_titles || active != self.cached_active {
+            self.cached_titles = titles;
+            self.cached_active = active;
+            self.is_dirty = true;
+        }
+
+        if self.is_dirty {
+            self.is_dirty = false;
+
+            let mut d = frame.begin_drawing(rl, thread);
+            let mut d = d.begin_scissor_mode(viewport.x as i32, viewport.y as i32, viewport.width as i32, viewport.height as i32);
+            d.clear_background(Color::new(16, 16, 16, 255));
+            for (i, (tab, title)) in tabs.iter().zip(&self.cached_titles).enumerate() {
+                let (background, text) = if i == active {
+                    (Color::new(42, 42, 42, 255), Color::WHITE)
+                } else {
+                    (Color::new(24, 24, 24, 255), Color::GRAY)
+                };
+                d.draw_rectangle_rec(Rectangle { width: tab.width - 1.0, ..*tab }, background);
+                {
+                    // long names are cut off before the close button
+                    let label = Rectangle { width: tab.width - Self::CLOSE_SIZE - 8.0, ..*tab };
+                    let mut d = d.begin_scissor_mode(label.x as i32, label.y as i32, label.width as i32, label.height as i32);
+                    d.draw_text(title, tab.x as i32 + 6, (tab.y + (tab.height - Self::FONT_SIZE as f32)*0.5) as i32, Self::FONT_SIZE, text);
+                }
+                let close = Self::close_button(*tab);
+                d.draw_text("x", close.x as i32 + 5, close.y as i32 + 3, Self::FONT_SIZE, text);
+            }
+            d.draw_text("+", new_button.x as i32 + 8, new_button.y as i32 + 6, Self::FONT_SIZE + 2, Color::GRAY);
+        }
+
+        command
+    }
+}
@@ -0,0 +1,228 @@
+use crate::raster::{Raster, Rect, blend_over};
+
+/// A tile placed on the map: an index into the tileset and how it's flipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Tile {
+    pub index: u32,
+    /// Mirrored left to right
+    pub flip_x: bool,
+    /// Mirrored top to bottom
+    pub flip_y: bool,
+    /// Mirrored across the top-left to bottom-right diagonal, before the other flips
+    pub flip_diagonal: bool,
+}
+
+impl Tile {
+    const FLIP_X_FLAG: u32 = 0x8000_0000;
+    const FLIP_Y_FLAG: u32 = 0x4000_0000;
+    const FLIP_DIAGONAL_FLAG: u32 = 0x2000_0000;
+
+    pub const fn new(index: u32) -> Self {
+        Self { index, flip_x: false, flip_y: false, flip_diagonal: false }
+    }
+
+    pub const fn flipped_x(self) -> Self {
+        Self { flip_x: !self.flip_x, ..self }
+    }
+
+    pub const fn flipped_y(self) -> Self {
+        Self { flip_y: !self.flip_y, ..self }
+    }
+
+    /// Turned a quarter turn clockwise
+    pub const fn rotated_cw(self) -> Self {
+        // a clockwise turn is a diagonal flip followed by a horizontal one
+        Self {
+            index: self.index,
+            flip_x: !self.flip_y,
+            flip_y: self.flip_x,
+            flip_diagonal: !self.flip_diagonal,
+        }
+    }
+
+    /// Where a point on the tile, from (-1, -1) at the top left to (1, 1) at the bottom right, ends up once placed
+    pub fn transform(self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (x, y) = if self.flip_diagonal { (y, x) } else { (x, y) };
+        (if self.flip_x { -x } else { x }, if self.flip_y { -y } else { y })
+    }
+
+    /// Where a point on the placed tile came from; the inverse of [`Tile::transform`]
+    pub fn untransform(self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (x, y) = (if self.flip_x { -x } else { x }, if self.flip_y { -y } else { y });
+        if self.flip_diagonal { (y, x) } else { (x, y) }
+    }
+
+    /// Tiled's global tile ID: the index plus one with the flips in the top bits, 0 being no tile
+    pub const fn gid(tile: Option<Tile>) -> u32 {
+        let Some(tile) = tile else { return 0; };
+        (tile.index + 1)
+            | if tile.flip_x { Self::FLIP_X_FLAG } else { 0 }
+            | if tile.flip_y { Self::FLIP_Y_FLAG } else { 0 }
+            | if tile.flip_diagonal { Self::FLIP_DIAGONAL_FLAG } else { 0 }
+    }
+}
+
+/// An image cut into a grid of equally sized tiles, numbered left to right then top to bottom.
+/// Partial tiles at the right and bottom edges are left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tileset {
+    pub tile_width: usize,
+    pub tile_height: usize,
+    pub columns: usize,
+    pub rows: usize,
+}
+
+impl Tileset {
+    pub fn new(image_width: usize, image_height: usize, tile_width: usize, tile_height: usize) -> Self {
+        let (tile_width, tile_height) = (tile_width.max(1), tile_height.max(1));
+        Self {
+            tile_width,
+            tile_height,
+            columns: image_width/tile_width,
+            rows: image_height/tile_height,
+        }
+    }
+
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.columns*self.rows
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Where the tile is on the tileset image
+    pub fn rect(&self, index: u32) -> Rect {
+        let (column, row) = (index as usize % self.columns.max(1), index as usize / self.columns.max(1));
+        Rect::new(
+            (column*self.tile_width) as i32,
+            (row*self.tile_height) as i32,
+            self.tile_width as i32,
+            self.tile_height as i32,
+        )
+    }
+
+    /// The tile at a pixel of the tileset image
+    pub fn index_at(&self, x: i32, y: i32) -> Option<u32> {
+        let (column, row) = (x.div_euclid(self.tile_width as i32), y.div_euclid(self.tile_height as i32));
+        (0 <= column && (column as usize) < self.columns && 0 <= row && (row as usize) < self.rows)
+            .then(|| (row as usize*self.columns + column as usize) as u32)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileLayer {
+    pub name: String,
+    pub is_visible: bool,
+    width: usize,
+    height: usize,
+    cells: Vec<Option<Tile>>,
+}
+
+impl TileLayer {
+    pub fn new(name: String, width: usize, height: usize) -> Self {
+        Self {
+            name,
+            is_visible: true,
+            width,
+            height,
+            cells: vec![None; width*height],
+        }
+    }
+
+    /// Cells row by row from the top left
+    #[inline]
+    pub fn cells(&self) -> &[Option<Tile>] {
+        &self.cells
+    }
+
+    /// Returns `None` outside the map as well as for empty cells
+    pub fn get(&self, x: i32, y: i32) -> Option<Tile> {
+        Rect::new(0, 0, self.width as i32, self.height as i32).contains(x, y)
+            .then(|| self.cells[y as usize*self.width + x as usize])
+            .flatten()
+    }
+
+    /// Returns whether the cell changed; cells outside the map are ignored
+    pub fn set(&mut self, x: i32, y: i32, tile: Option<Tile>) -> bool {
+        if !Rect::new(0, 0, self.width as i32, self.height as i32).contains(x, y) {
+            return false;
+        }
+        let cell = &mut self.cells[y as usize*self.width + x as usize];
+        let is_changed = *cell != tile;
+        *cell = tile;
+        is_changed
+    }
+}
+
+/// Layers of tiles drawn bottom to top, all sharing one tileset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tilemap {
+    pub tileset: Tileset,
+    width: usize,
+    height: usize,
+    pub layers: Vec<TileLayer>,
+}
+
+impl Tilemap {
+    /// A map `width` by `height` tiles with one empty layer
+    pub fn new(tileset: Tileset, width: usize, height: usize) -> Self {
+        Self {
+            tileset,
+            width,
+            height,
+            layers: vec![TileLayer::new("Layer 1".to_string(), width, height)],
+        }
+    }
+
+    /// In tiles
+    #[inline]
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// In tiles
+    #[inline]
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Add an empty layer above the others and return its index
+    pub fn add_layer(&mut self) -> usize {
+        let name = format!("Layer {}", self.layers.len() + 1);
+        self.layers.push(TileLayer::new(name, self.width, self.height));
+        self.layers.len() - 1
+    }
+
+    /// Every visible layer composited into one image, with tiles read from `image`
+    pub fn flatten(&self, image: &Raster) -> Raster {
+        let Tileset { tile_width, tile_height, .. } = self.tileset;
+        let mut out = Raster::new(self.width*tile_width, self.height*tile_height);
+        for layer in self.layers.iter().filter(|layer| layer.is_visible) {
+            for (i, tile) in layer.cells.iter().enumerate() {
+                let Some(tile) = *tile else { continue; };
+                let src = self.tileset.rect(tile.index);
+                if tile.index as usize >= self.tileset.len() || image.bounds().intersect(&src) != Some(src) {
+                    continue;
+                }
+                let (left, top) = ((i % self.width)*tile_width, (i / self.width)*tile_height);
+                for y in 0..tile_height {
+                    for x in 0..tile_width {
+                        // sample at pixel centers so flips land exactly on pixels
+                        let u = (x as f32 + 0.5)/tile_width  as f32*2.0 - 1.0;
+                        let v = (y as f32 + 0.5)/tile_height as f32*2.0 - 1.0;
+                        let (u, v) = tile.untransform((u, v));
+                        let sx = (((u + 1.0)*0.5*tile_width  as f32) as usize).min(tile_width  - 1);
+                        let sy = (((v + 1.0)*0.5*tile_height as f32) as usize).min(tile_height - 1);
+                        let color = image.get(src.x as usize + sx, src.y as usize + sy);
+                        let dst = out.get(left + x, top + y);
+                        out.set(left + x, top + y, blend_over(dst, color));
+                    }
+                }
+            }
+        }
+        out
+    }
+}
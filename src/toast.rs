@@ -0,0 +1,94 @@
+use std::{collections::VecDeque, fmt::Display};
+use raylib::prelude::*;
+
+struct Toast {
+    message: String,
+    /// When it goes away, in seconds since the window opened
+    expires: f64,
+}
+
+/// Messages stacked in the bottom right corner that go away on their own, so something going wrong
+/// doesn't stop anyone painting
+///
+/// They're drawn over the frame as it's shown rather than into it, so nothing underneath needs
+/// redrawing when one goes.
+pub struct Toasts {
+    /// Oldest first
+    list: VecDeque<Toast>,
+    is_dirty: bool,
+}
+
+impl Toasts {
+    /// Seconds each is shown for
+    const DURATION: f64 = 6.0;
+    /// Most shown at once; older ones make way for newer
+    const MAX_SHOWN: usize = 4;
+    const WIDTH: f32 = 380.0;
+    const HEIGHT: f32 = 36.0;
+    const MARGIN: f32 = 12.0;
+    const FONT_SIZE: i32 = 10;
+
+    pub fn new() -> Self {
+        Self {
+            list: VecDeque::with_capacity(Self::MAX_SHOWN),
+            is_dirty: false,
+        }
+    }
+
+    pub fn push(&mut self, time: f64, message: String) {
+        if self.list.len() == Self::MAX_SHOWN {
+            self.list.pop_front();
+        }
+        self.list.push_back(Toast { message, expires: time + Self::DURATION });
+        self.is_dirty = true;
+    }
+
+    /// Show what went wrong, if anything, after `what`; returns what went right
+    pub fn report<T, E: Display>(&mut self, time: f64, what: &str, result: Result<T, E>) -> Option<T> {
+        result.map_err(|err| self.push(time, format!("{what}: {err}"))).ok()
+    }
+
+    /// Whether any are up, which need the time to pass without input for them to go
+    #[inline]
+    pub fn is_showing(&self) -> bool {
+        !self.list.is_empty()
+    }
+
+    /// Whether the screen needs showing again for toasts coming or going
+    #[inline]
+    pub fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    /// Take down any that have been up long enough
+    pub fn update(&mut self, time: f64) {
+        let count = self.list.len();
+        self.list.retain(|toast| toast.expires > time);
+        if self.list.len() != count {
+            self.is_dirty = true;
+        }
+    }
+
+    pub fn draw<D: RaylibDraw>(&mut self, d: &mut D, screen: Rectangle) {
+        self.is_dirty = false;
+        for (i, toast) in self.list.iter().rev().enumerate() {
+            let bounds = Rectangle::new(
+                screen.x + screen.width - Self::WIDTH - Self::MARGIN,
+                screen.y + screen.height - (Self::HEIGHT + Self::MARGIN)*(i + 1) as f32,
+                Self::WIDTH,
+                Self::HEIGHT,
+            );
+            d.draw_rectangle_rec(bounds, Color::new(48, 16, 16, 230));
+            d.draw_rectangle_lines_ex(bounds, 1.0, Color::new(200, 64, 64, 255));
+            // long messages are cut short rather than spilling out
+            let max_width = bounds.width as i32 - 16;
+            let mut message = toast.message.clone();
+            while measure_text(&message, Self::FONT_SIZE) > max_width && message.pop().is_some() {}
+            if message.len() < toast.message.len() {
+                for _ in 0..3 { message.pop(); }
+                message.push_str("...");
+            }
+            d.draw_text(&message, bounds.x as i32 + 8, (bounds.y + (bounds.height - Self::FONT_SIZE as f32)*0.5) as i32, Self::FONT_SIZE, Color::WHITE);
+        }
+    }
+}